@@ -0,0 +1,262 @@
+// Pulling the payload out of a release archive.
+//
+// Plenty of payloads are published as ZIP or 7z archives (hekate ships its payload
+// next to its SD card files), so when a download turns out to be an archive we
+// find the payload inside by name and extract that, and nothing else.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::download::{sanitize_filename, PartialFile};
+use crate::MAX_PAYLOAD_SIZE;
+
+/// Where payloads live in the archives we know about, best guess last. Patterns
+/// match the entry's file name, ignoring case; `*` and `?` work as in a shell.
+pub const DEFAULT_PAYLOAD_PATTERNS: &[&str] = &[
+    "hekate_ctcaer_*.bin",
+    "fusee.bin",
+    "Lockpick_RCM.bin",
+    "TegraExplorer.bin",
+    "*.bin",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArchiveKind {
+    Zip,
+    SevenZip,
+}
+
+impl ArchiveKind {
+    /// Recognises an archive by its first few bytes.
+    pub fn detect(header: &[u8]) -> Option<Self> {
+        if header.starts_with(b"PK\x03\x04") || header.starts_with(b"PK\x05\x06") {
+            Some(ArchiveKind::Zip)
+        } else if header.starts_with(b"7z\xBC\xAF\x27\x1C") {
+            Some(ArchiveKind::SevenZip)
+        } else {
+            None
+        }
+    }
+
+    /// Recognises an archive by the start of a file.
+    pub fn detect_file(path: &Path) -> io::Result<Option<Self>> {
+        let mut header = Vec::with_capacity(8);
+        File::open(path)?.take(8).read_to_end(&mut header)?;
+        Ok(Self::detect(&header))
+    }
+}
+
+#[derive(Debug)]
+pub enum ArchiveError {
+    /// The archive is corrupt, encrypted or uses something we can't decompress.
+    Unreadable(String),
+    /// Nothing in the archive matches any of the patterns.
+    NoPayload,
+    /// More than one entry matches the best pattern that matched anything.
+    Ambiguous {
+        candidates: Vec<String>,
+    },
+    /// The payload is too big to be one.
+    TooLarge {
+        name: String,
+    },
+    Io(io::Error),
+}
+
+impl ArchiveError {
+    /// A stable, machine-readable name for the error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ArchiveError::Unreadable(_) => "ArchiveUnreadable",
+            ArchiveError::NoPayload => "NoPayloadInArchive",
+            ArchiveError::Ambiguous { .. } => "AmbiguousPayload",
+            ArchiveError::TooLarge { .. } => "ArchivedPayloadTooLarge",
+            ArchiveError::Io(_) => "Io",
+        }
+    }
+}
+
+impl fmt::Display for ArchiveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArchiveError::Unreadable(reason) => write!(f, "Failed to read archive: {}", reason),
+            ArchiveError::NoPayload => write!(f, "Couldn't find a payload in the archive"),
+            ArchiveError::Ambiguous { candidates } => write!(
+                f,
+                "The archive has more than one payload: {}",
+                candidates.join(", ")
+            ),
+            ArchiveError::TooLarge { name } => {
+                write!(f, "{} is too large to be an RCM payload", name)
+            }
+            ArchiveError::Io(e) => write!(f, "Failed to extract payload: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ArchiveError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ArchiveError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ArchiveError {
+    fn from(e: io::Error) -> Self {
+        ArchiveError::Io(e)
+    }
+}
+
+impl From<zip::result::ZipError> for ArchiveError {
+    fn from(e: zip::result::ZipError) -> Self {
+        match e {
+            zip::result::ZipError::Io(e) => ArchiveError::Io(e),
+            e => ArchiveError::Unreadable(e.to_string()),
+        }
+    }
+}
+
+impl From<sevenz_rust::Error> for ArchiveError {
+    fn from(e: sevenz_rust::Error) -> Self {
+        ArchiveError::Unreadable(e.to_string())
+    }
+}
+
+/// Whether `name` matches a shell-style pattern, ignoring case.
+pub fn glob_matches(pattern: &str, name: &str) -> bool {
+    fn matches(pattern: &[char], name: &[char]) -> bool {
+        match pattern.split_first() {
+            None => name.is_empty(),
+            Some(('*', rest)) => (0..=name.len()).any(|skip| matches(rest, &name[skip..])),
+            Some((&expected, rest)) => name.split_first().is_some_and(|(&actual, name)| {
+                (expected == '?' || expected.eq_ignore_ascii_case(&actual)) && matches(rest, name)
+            }),
+        }
+    }
+
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    matches(&pattern, &name)
+}
+
+/// Picks the payload out of an archive's entries: the first pattern to match
+/// anything wins, and of its matches, the one nearest the top of the archive.
+pub fn choose_payload<'a, S: AsRef<str>>(
+    entries: &'a [String],
+    patterns: &[S],
+) -> Result<&'a str, ArchiveError> {
+    let depth = |entry: &str| entry.matches('/').count();
+    let file_name = |entry: &'a str| entry.rsplit('/').next().unwrap_or(entry);
+
+    for pattern in patterns {
+        let matches: Vec<&str> = entries
+            .iter()
+            .map(String::as_str)
+            .filter(|entry| glob_matches(pattern.as_ref(), file_name(entry)))
+            .collect();
+        let Some(shallowest) = matches.iter().map(|entry| depth(entry)).min() else {
+            continue;
+        };
+
+        let candidates: Vec<&str> = matches
+            .into_iter()
+            .filter(|entry| depth(entry) == shallowest)
+            .collect();
+        return match candidates[..] {
+            [only] => Ok(only),
+            _ => Err(ArchiveError::Ambiguous {
+                candidates: candidates.iter().map(|entry| entry.to_string()).collect(),
+            }),
+        };
+    }
+
+    Err(ArchiveError::NoPayload)
+}
+
+/// Extracts the payload from `archive` into `dir`, and returns where it went.
+pub fn extract_payload<S: AsRef<str>>(
+    archive: &Path,
+    kind: ArchiveKind,
+    dir: &Path,
+    patterns: &[S],
+) -> Result<PathBuf, ArchiveError> {
+    match kind {
+        ArchiveKind::Zip => extract_from_zip(archive, dir, patterns),
+        ArchiveKind::SevenZip => extract_from_7z(archive, dir, patterns),
+    }
+}
+
+fn extract_from_zip<S: AsRef<str>>(
+    archive: &Path,
+    dir: &Path,
+    patterns: &[S],
+) -> Result<PathBuf, ArchiveError> {
+    let mut zip = zip::ZipArchive::new(File::open(archive)?)?;
+
+    // Only consider files whose names can't point outside the archive.
+    let mut entries = Vec::new();
+    for index in 0..zip.len() {
+        let entry = zip.by_index(index)?;
+        if entry.is_file() && entry.enclosed_name().is_some() {
+            entries.push(entry.name().to_string());
+        }
+    }
+
+    let name = choose_payload(&entries, patterns)?;
+    let mut entry = zip.by_name(name)?;
+    write_payload(name, &mut entry, dir)
+}
+
+fn extract_from_7z<S: AsRef<str>>(
+    archive: &Path,
+    dir: &Path,
+    patterns: &[S],
+) -> Result<PathBuf, ArchiveError> {
+    let mut reader = sevenz_rust::SevenZReader::open(archive, sevenz_rust::Password::empty())?;
+
+    let entries: Vec<String> = reader
+        .archive()
+        .files
+        .iter()
+        .filter(|entry| !entry.is_directory() && !entry.name().split('/').any(|c| c == ".."))
+        .map(|entry| entry.name().to_string())
+        .collect();
+    let name = choose_payload(&entries, patterns)?.to_string();
+
+    let mut extracted = None;
+    reader.for_each_entries(|entry, data| {
+        if entry.name() != name {
+            // Entries share a compressed stream, so skipping one means reading it.
+            io::copy(data, &mut io::sink())?;
+            return Ok(true);
+        }
+
+        extracted = Some(write_payload(&name, data, dir));
+        Ok(false)
+    })?;
+
+    extracted.unwrap_or(Err(ArchiveError::NoPayload))
+}
+
+/// Writes an archived payload into `dir`, refusing anything too big to be one.
+fn write_payload(name: &str, data: &mut dyn Read, dir: &Path) -> Result<PathBuf, ArchiveError> {
+    let file_name = name.rsplit('/').next().unwrap_or(name);
+    let file_name =
+        sanitize_filename(file_name).map_err(|e| ArchiveError::Unreadable(e.to_string()))?;
+
+    let mut partial = PartialFile::create(dir, &file_name)?;
+    let limit = MAX_PAYLOAD_SIZE as u64;
+    let written = io::copy(&mut data.take(limit + 1), partial.file())?;
+    if written > limit {
+        return Err(ArchiveError::TooLarge {
+            name: name.to_string(),
+        });
+    }
+    partial.file().flush()?;
+
+    Ok(partial.persist_unclobbered(dir, &file_name)?)
+}
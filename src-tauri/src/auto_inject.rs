@@ -0,0 +1,182 @@
+// Injecting automatically as soon as a device enters RCM, like TegraRcmGUI's
+// auto-inject.
+//
+// The settings live in the app's settings store, so they survive restarts. Rules
+// pick a payload for a particular console (by its chip UID) or a particular USB
+// port; anything else gets the default payload.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::thread;
+use tauri::{Emitter, Manager};
+use tracing::{info, warn};
+
+use crate::monitor::{DeviceRegistry, TrackedDevice};
+use crate::storage;
+use crate::{inject, payload, InjectionReport, InjectionRequest, JoltError};
+
+const AUTO_INJECT_KEY: &str = "auto_inject";
+
+/// An automatic injection has finished. Carries an `AutoInjectOutcome`.
+pub const AUTO_INJECT_EVENT: &str = "auto-inject";
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoInjectSettings {
+    pub enabled: bool,
+    /// The payload for devices no rule matches, if any.
+    pub default_payload: Option<String>,
+    /// Checked in order; the first match wins.
+    pub rules: Vec<AutoInjectRule>,
+}
+
+/// Which devices a rule applies to.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "by", rename_all = "snake_case")]
+pub enum DeviceMatch {
+    /// A particular console, by the UID from its device ID.
+    ChipUid { uid: String },
+    /// Whatever's plugged into a particular port, e.g. "1-2.4".
+    PortPath { port_path: String },
+}
+
+impl DeviceMatch {
+    pub fn matches(&self, device: &TrackedDevice) -> bool {
+        match self {
+            DeviceMatch::ChipUid { uid } => device
+                .chip_info
+                .as_ref()
+                .is_some_and(|chip| chip.uid.eq_ignore_ascii_case(uid)),
+            DeviceMatch::PortPath { port_path } => {
+                device.port_path.as_deref() == Some(port_path.as_str())
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AutoInjectRule {
+    #[serde(rename = "match")]
+    pub device: DeviceMatch,
+    pub payload_path: String,
+}
+
+impl AutoInjectSettings {
+    /// The payload to inject into a device that's just entered RCM, if any.
+    pub fn payload_for(&self, device: &TrackedDevice) -> Option<&str> {
+        if !self.enabled {
+            return None;
+        }
+
+        self.rules
+            .iter()
+            .find(|rule| rule.device.matches(device))
+            .map(|rule| rule.payload_path.as_str())
+            .or(self.default_payload.as_deref())
+    }
+
+    /// Whether picking a payload needs the device's chip UID.
+    fn needs_chip(&self) -> bool {
+        self.enabled
+            && self
+                .rules
+                .iter()
+                .any(|rule| matches!(rule.device, DeviceMatch::ChipUid { .. }))
+    }
+
+    /// Every payload the settings refer to.
+    fn payload_paths(&self) -> impl Iterator<Item = &str> {
+        self.default_payload
+            .as_deref()
+            .into_iter()
+            .chain(self.rules.iter().map(|rule| rule.payload_path.as_str()))
+    }
+}
+
+/// How an automatic injection went.
+#[derive(Debug, Serialize)]
+pub struct AutoInjectOutcome {
+    pub device: TrackedDevice,
+    pub payload_path: String,
+    pub report: Option<InjectionReport>,
+    pub error: Option<JoltError>,
+}
+
+pub fn load_settings(app: &tauri::AppHandle) -> AutoInjectSettings {
+    storage::load_setting(app, AUTO_INJECT_KEY).unwrap_or_default()
+}
+
+#[tauri::command]
+pub fn get_auto_inject_settings(app: tauri::AppHandle) -> AutoInjectSettings {
+    load_settings(&app)
+}
+
+/// Saves new settings, as long as every payload they name can be injected.
+#[tauri::command]
+pub fn set_auto_inject_settings(
+    app: tauri::AppHandle,
+    settings: AutoInjectSettings,
+) -> Result<(), String> {
+    for payload_path in settings.payload_paths() {
+        payload::inspect_file(Path::new(payload_path))
+            .and_then(|info| info.check())
+            .map_err(|e| e.to_string())?;
+    }
+
+    storage::save_setting(&app, AUTO_INJECT_KEY, &settings)
+}
+
+/// Called by the monitor when a device enters RCM. Starts an injection in the
+/// background if the settings say to.
+pub(crate) fn on_rcm_connected(app: &tauri::AppHandle, device: &TrackedDevice) {
+    let settings = load_settings(app);
+    if !settings.enabled {
+        return;
+    }
+
+    let app = app.clone();
+    let mut device = device.clone();
+
+    thread::spawn(move || {
+        // Arriving doesn't read the device ID, so read it here if a rule needs it.
+        // The injection uses what we read rather than asking again.
+        if device.chip_info.is_none() && settings.needs_chip() {
+            device.chip_info = crate::read_chip_info(device.bus_and_address());
+            device.chip_id_read_failed = Some(device.chip_info.is_none());
+            app.state::<DeviceRegistry>()
+                .record_chip(device.bus_and_address(), device.chip_info.clone());
+        }
+        let Some(payload_path) = settings.payload_for(&device) else {
+            return;
+        };
+        let payload_path = payload_path.to_string();
+
+        info!(
+            payload = %payload_path,
+            port = device.port_path.as_deref().unwrap_or("unknown"),
+            "Auto-injecting"
+        );
+
+        let request = InjectionRequest {
+            payload_path: payload_path.clone(),
+            backend: None,
+            intermezzo_path: None,
+            intermezzo_sha256: None,
+            device: Some(device.bus_and_address()),
+        };
+        let (report, error) = match inject(&app, &request, None) {
+            Ok(report) => (Some(report), None),
+            Err(e) => (None, Some(e)),
+        };
+
+        let outcome = AutoInjectOutcome {
+            device,
+            payload_path,
+            report,
+            error,
+        };
+        if let Err(e) = app.emit(AUTO_INJECT_EVENT, &outcome) {
+            warn!(event = AUTO_INJECT_EVENT, error = %e, "Failed to report an event");
+        }
+    });
+}
@@ -0,0 +1,431 @@
+// A headless jolt, for scripted test rigs and machines without a display.
+//
+// It drives the same `RCMHax`/`build_payload` code as the app. Diagnostics go to
+// stderr, so with `--json` stdout only ever holds one JSON document. `JOLT_LOG`
+// picks how much of them there is, e.g. `JOLT_LOG=debug`.
+
+use clap::{Args, Parser, Subcommand};
+use std::error::Error;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
+
+use whiz_lib::logging;
+use whiz_lib::monitor::{self, DeviceKind, DeviceRegistry};
+use whiz_lib::relocator::RelocatorParams;
+use whiz_lib::strategy::StrategyRegistry;
+use whiz_lib::verify::{self, VerificationStatus};
+use whiz_lib::{
+    build_payload, execute_exploit, payload, DeviceWait, InjectionProgress, InjectionReport,
+    Intermezzo, JoltError, RCMHax,
+};
+
+const EXIT_CODES: &str = "\
+Exit codes:
+  0  success
+  1  any other failure
+  2  bad arguments
+  3  no device in RCM (for `status`, none connected)
+  4  gave up waiting for a device
+  5  not allowed to open the device
+  6  the payload can't be injected
+  7  the exploit itself failed
+  8  the payload didn't run: the device stayed in, or went back to, RCM
+  9  the device's USB port can't trigger the exploit (try a USB 3 port)";
+
+#[derive(Parser)]
+#[command(
+    name = "jolt-cli",
+    version,
+    about = "Inject RCM payloads into a Nintendo Switch without the GUI.",
+    after_help = EXIT_CODES
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Inject a payload into a device in RCM.
+    Inject {
+        payload: String,
+        /// Wait for a device to enter RCM instead of failing if there isn't one.
+        #[arg(long)]
+        wait: bool,
+        /// Give up waiting after this many seconds.
+        #[arg(long, value_name = "SECS", requires = "wait")]
+        timeout: Option<u64>,
+        /// The USB vendor ID to look for, in hex.
+        #[arg(long, value_parser = parse_hex_u16)]
+        vid: Option<u16>,
+        /// The USB product ID to look for, in hex.
+        #[arg(long, value_parser = parse_hex_u16)]
+        pid: Option<u16>,
+        /// Trigger the vulnerability the way another OS would, e.g. "linux".
+        #[arg(long)]
+        backend: Option<String>,
+        #[command(flatten)]
+        intermezzo: IntermezzoArgs,
+        /// The exploit strategies to try, in order, e.g.
+        /// "fusee-launcher,classic-bulk-interrupt". See `strategies`.
+        #[arg(long = "strategy", value_name = "IDS", value_delimiter = ',')]
+        strategies: Vec<String>,
+        /// Watch the bus for this long afterwards to check the payload is running.
+        /// Zero skips the check.
+        #[arg(long, value_name = "SECS", default_value_t = verify::DEFAULT_WINDOW_SECS)]
+        verify_secs: u64,
+        /// Print the injection report as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// List the USB devices that are connected.
+    List {
+        #[arg(long)]
+        json: bool,
+    },
+    /// Report whether a Switch is connected, and whether it's in RCM.
+    Status {
+        #[arg(long)]
+        json: bool,
+    },
+    /// List the exploit strategies `inject --strategy` can use.
+    Strategies {
+        #[arg(long)]
+        json: bool,
+    },
+    /// Write the RCM command jolt would send for a payload, without sending it.
+    BuildStream {
+        payload: PathBuf,
+        /// Where to write the command. Defaults to stdout.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        #[command(flatten)]
+        intermezzo: IntermezzoArgs,
+    },
+}
+
+#[derive(Args)]
+struct IntermezzoArgs {
+    /// Use this relocator instead of the built-in one.
+    #[arg(long, value_name = "PATH", requires = "intermezzo_sha256")]
+    intermezzo: Option<PathBuf>,
+    /// The SHA-256 the custom relocator must have.
+    #[arg(long, value_name = "SHA256", requires = "intermezzo")]
+    intermezzo_sha256: Option<String>,
+    /// Generate a relocator for a payload linked at this address, in hex, instead
+    /// of 0x40010000.
+    #[arg(long, value_name = "ADDRESS", value_parser = parse_hex_u32, conflicts_with = "intermezzo")]
+    link_address: Option<u32>,
+}
+
+impl IntermezzoArgs {
+    /// The relocator to send `payload` with.
+    fn load(&self, payload: &[u8]) -> Result<Intermezzo, JoltError> {
+        if let Some(address) = self.link_address {
+            // Too large to fit either way; build_payload says so.
+            let length = u32::try_from(payload.len()).unwrap_or(u32::MAX);
+            return Intermezzo::generate(&RelocatorParams::linked_at(address, length));
+        }
+        Intermezzo::select(
+            self.intermezzo.as_deref(),
+            self.intermezzo_sha256.as_deref(),
+        )
+    }
+}
+
+fn parse_hex_u16(value: &str) -> Result<u16, String> {
+    let digits = value.trim_start_matches("0x").trim_start_matches("0X");
+    u16::from_str_radix(digits, 16).map_err(|e| format!("{:?} isn't a hex ID: {}", value, e))
+}
+
+fn parse_hex_u32(value: &str) -> Result<u32, String> {
+    let digits = value.trim_start_matches("0x").trim_start_matches("0X");
+    u32::from_str_radix(digits, 16).map_err(|e| format!("{:?} isn't a hex address: {}", value, e))
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    logging::init_stderr();
+
+    let json = match &cli.command {
+        Command::Inject { json, .. }
+        | Command::List { json }
+        | Command::Status { json }
+        | Command::Strategies { json } => *json,
+        Command::BuildStream { .. } => false,
+    };
+
+    match run(cli.command) {
+        Ok(code) => code,
+        Err(e) => {
+            let jolt_error = e.downcast_ref::<JoltError>();
+            if json {
+                let error = match jolt_error {
+                    Some(jolt_error) => serde_json::to_value(jolt_error).unwrap_or_default(),
+                    None => serde_json::json!({ "code": "Other", "message": e.to_string() }),
+                };
+                println!("{}", serde_json::json!({ "error": error }));
+            } else {
+                eprintln!("error: {}", e);
+            }
+            ExitCode::from(jolt_error.map_or(1, exit_code))
+        }
+    }
+}
+
+fn exit_code(error: &JoltError) -> u8 {
+    match error {
+        JoltError::DeviceNotFound => 3,
+        JoltError::WaitCancelled | JoltError::WaitTimedOut { .. } => 4,
+        JoltError::AccessDenied(_) => 5,
+        JoltError::PayloadUnreadable { .. }
+        | JoltError::PayloadRejected { .. }
+        | JoltError::PayloadTooLarge { .. } => 6,
+        JoltError::UploadFailed(_)
+        | JoltError::HighBufferFailed(_)
+        | JoltError::TriggerFailed(_)
+        | JoltError::StrategyFailed { .. } => 7,
+        JoltError::UnsupportedController(_) => 9,
+        _ => 1,
+    }
+}
+
+fn run(command: Command) -> Result<ExitCode, Box<dyn Error>> {
+    match command {
+        Command::Inject {
+            payload,
+            wait,
+            timeout,
+            vid,
+            pid,
+            backend,
+            intermezzo,
+            strategies,
+            verify_secs,
+            json,
+        } => {
+            let wait = wait.then(|| DeviceWait::new(timeout.map(Duration::from_secs)));
+            let report = inject(InjectOptions {
+                payload_path: &payload,
+                wait: wait.as_ref(),
+                vid,
+                pid,
+                backend: backend.as_deref(),
+                intermezzo: &intermezzo,
+                strategies: &strategies,
+                verify_window: Duration::from_secs(verify_secs),
+                quiet: json,
+            })?;
+
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                print_report(&report);
+            }
+            Ok(match report.verification.status {
+                VerificationStatus::Failed => ExitCode::from(8),
+                _ => ExitCode::SUCCESS,
+            })
+        }
+        Command::List { json } => {
+            let devices = monitor::scan().map_err(JoltError::UsbUnavailable)?;
+
+            if json {
+                println!("{}", serde_json::to_string_pretty(&devices)?);
+            } else {
+                for device in devices {
+                    println!(
+                        "{:03}:{:03} {:04x}:{:04x} {:<6} {:<8} {} {}",
+                        device.bus,
+                        device.address,
+                        device.info.vendor_id,
+                        device.info.product_id,
+                        kind_name(device.kind),
+                        device.port_path.as_deref().unwrap_or("-"),
+                        device.info.manufacturer.as_deref().unwrap_or(""),
+                        device.info.product.as_deref().unwrap_or("")
+                    );
+                }
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Status { json } => {
+            let registry = DeviceRegistry::default();
+            for device in monitor::scan().map_err(JoltError::UsbUnavailable)? {
+                registry.insert(device);
+            }
+            let status = registry.status();
+
+            if json {
+                println!("{}", serde_json::to_string_pretty(&status)?);
+            } else if status.rcm_detected {
+                match &status.chip_info {
+                    Some(chip_info) => println!("In RCM, device ID {}", chip_info.uid),
+                    // Reading the ID here would use it up before an injection could.
+                    None => println!("In RCM"),
+                }
+            } else if status.switch_connected_not_rcm {
+                println!("Switch connected, but not in RCM");
+            } else {
+                println!("No Switch connected");
+            }
+
+            Ok(if status.rcm_detected {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::from(3)
+            })
+        }
+        Command::Strategies { json } => {
+            let strategies = <StrategyRegistry>::builtin().infos();
+
+            if json {
+                println!("{}", serde_json::to_string_pretty(&strategies)?);
+            } else {
+                for strategy in strategies {
+                    let default = if strategy.default { " (default)" } else { "" };
+                    println!("{}{}: {}", strategy.id, default, strategy.description);
+                }
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::BuildStream {
+            payload,
+            output,
+            intermezzo,
+        } => {
+            let bytes = std::fs::read(&payload).map_err(|source| JoltError::PayloadUnreadable {
+                path: payload.clone(),
+                source,
+            })?;
+            let stream = build_payload(&bytes, &intermezzo.load(&bytes)?)?;
+
+            match output {
+                Some(output) => std::fs::write(&output, &stream)
+                    .map_err(|e| format!("Failed to write {:?}: {}", output, e))?,
+                None => std::io::stdout().lock().write_all(&stream)?,
+            }
+            eprintln!("Built a {} byte RCM command", stream.len());
+            Ok(ExitCode::SUCCESS)
+        }
+    }
+}
+
+/// What `inject` was asked to do.
+struct InjectOptions<'a> {
+    payload_path: &'a str,
+    wait: Option<&'a DeviceWait>,
+    vid: Option<u16>,
+    pid: Option<u16>,
+    backend: Option<&'a str>,
+    intermezzo: &'a IntermezzoArgs,
+    /// Empty for the default strategy.
+    strategies: &'a [String],
+    verify_window: Duration,
+    quiet: bool,
+}
+
+fn inject(options: InjectOptions) -> Result<InjectionReport, JoltError> {
+    let InjectOptions {
+        payload_path,
+        wait,
+        vid,
+        pid,
+        backend,
+        intermezzo,
+        strategies,
+        verify_window,
+        quiet,
+    } = options;
+
+    // Same checks as the app: don't grab the device for something we won't send.
+    let target_payload = payload::read_file(Path::new(payload_path))?;
+    let info = payload::inspect(&target_payload);
+    info.check()?;
+    if !quiet {
+        eprintln!("Payload: {} ({} bytes)", info.description, info.size);
+    }
+
+    let intermezzo = intermezzo.load(&target_payload)?;
+    let registry = StrategyRegistry::builtin();
+    let strategies = registry.resolve(strategies)?;
+    let show_progress = |progress: InjectionProgress| {
+        if !quiet {
+            print_progress(&progress);
+        }
+    };
+
+    let switch = RCMHax::new(wait, backend, vid, pid, None, false, &show_progress)?;
+    let watcher = verify::before_injection(switch.bus_and_address());
+    let mut report = execute_exploit(
+        switch,
+        payload_path,
+        &target_payload,
+        &intermezzo,
+        &strategies,
+        &show_progress,
+    )?;
+    report.verification = verify::verify(watcher, verify_window, &show_progress);
+    Ok(report)
+}
+
+fn print_progress(progress: &InjectionProgress) {
+    match progress {
+        InjectionProgress::WaitingForDevice { timeout_secs } => match timeout_secs {
+            Some(secs) => eprintln!("Waiting up to {}s for a device in RCM...", secs),
+            None => eprintln!("Waiting for a device in RCM..."),
+        },
+        InjectionProgress::Uploading {
+            buffers_written,
+            buffers_total,
+            bytes_total,
+            ..
+        } if buffers_written == buffers_total => {
+            eprintln!(
+                "Uploaded {} bytes in {} buffers",
+                bytes_total, buffers_total
+            )
+        }
+        InjectionProgress::Uploading { .. } => {}
+        other => eprintln!("{:?}", other),
+    }
+}
+
+fn print_report(report: &InjectionReport) {
+    let device = match &report.chip_info {
+        Some(chip_info) => format!("device {}", chip_info.uid),
+        None => "a device with an unreadable ID".to_string(),
+    };
+    println!(
+        "Injected {} ({} bytes) into {} in {:.0} ms with {} ({:?})",
+        report.payload_path,
+        report.payload_size,
+        device,
+        report.timings.total_ms,
+        report.strategy,
+        report.trigger
+    );
+    match report.verification.status {
+        VerificationStatus::Verified => println!(
+            "Verified: {:?} ({:.0} ms)",
+            report.verification.observation, report.verification.elapsed_ms
+        ),
+        VerificationStatus::Unverified => {
+            println!("Unverified: {:?}", report.verification.observation)
+        }
+        VerificationStatus::Failed => println!(
+            "The payload doesn't seem to be running: {:?}",
+            report.verification.observation
+        ),
+    }
+}
+
+fn kind_name(kind: DeviceKind) -> &'static str {
+    match kind {
+        DeviceKind::Rcm => "rcm",
+        DeviceKind::Switch => "switch",
+        DeviceKind::Other => "other",
+    }
+}
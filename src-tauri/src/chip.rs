@@ -0,0 +1,109 @@
+// Decoding of the device ID the bootROM sends at the start of RCM.
+//
+// The 16 bytes are the chip's ECID: the fuse-programmed vendor, fab, lot, wafer
+// and die position, packed the same way hekate's fuse_get_hw_ecid() packs them.
+// Taken together they identify one particular console.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+/// The length of the RCM device ID.
+pub const DEVICE_ID_LENGTH: usize = 16;
+
+/// What the device ID tells us about the chip.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChipInfo {
+    /// The raw device ID as hex, which is what identifies the console.
+    pub uid: String,
+    pub vendor_code: u8,
+    pub fab_code: u8,
+    /// The lot code as its five base-36 characters, if it decodes as one.
+    pub lot: Option<String>,
+    pub lot_code_0: u32,
+    pub lot_code_1: u32,
+    pub wafer_id: u8,
+    pub x_coordinate: u16,
+    pub y_coordinate: u16,
+    pub reserved: u8,
+}
+
+impl ChipInfo {
+    /// Decodes a device ID as read from the RCM endpoint.
+    pub fn from_device_id(device_id: &[u8]) -> Result<Self, String> {
+        if device_id.len() != DEVICE_ID_LENGTH {
+            return Err(format!(
+                "Expected a {}-byte device ID, got {} bytes",
+                DEVICE_ID_LENGTH,
+                device_id.len()
+            ));
+        }
+
+        let word = |i: usize| {
+            u32::from_le_bytes([
+                device_id[i * 4],
+                device_id[i * 4 + 1],
+                device_id[i * 4 + 2],
+                device_id[i * 4 + 3],
+            ])
+        };
+        let (ecid0, ecid1, ecid2, ecid3) = (word(0), word(1), word(2), word(3));
+
+        // ecid[0] = lot1 << 30 | wafer << 24 | x << 15 | y << 6 | reserved
+        // ecid[1] = lot0 << 26 | lot1 >> 2
+        // ecid[2] = fab << 26  | lot0 >> 6
+        // ecid[3] = vendor
+        let lot_code_0 = (ecid1 >> 26) | ((ecid2 & 0x03FF_FFFF) << 6);
+        let lot_code_1 = ((ecid0 >> 30) | ((ecid1 & 0x03FF_FFFF) << 2)) & 0x0FFF_FFFF;
+
+        Ok(Self {
+            uid: hex::encode(device_id),
+            vendor_code: (ecid3 & 0xF) as u8,
+            fab_code: ((ecid2 >> 26) & 0x3F) as u8,
+            lot: decode_lot(lot_code_0),
+            lot_code_0,
+            lot_code_1,
+            wafer_id: ((ecid0 >> 24) & 0x3F) as u8,
+            x_coordinate: ((ecid0 >> 15) & 0x1FF) as u16,
+            y_coordinate: ((ecid0 >> 6) & 0x1FF) as u16,
+            reserved: (ecid0 & 0x3F) as u8,
+        })
+    }
+}
+
+/// The lot code is five base-36 digits, six bits each.
+fn decode_lot(lot_code_0: u32) -> Option<String> {
+    (0..5)
+        .rev()
+        .map(|i| {
+            let digit = (lot_code_0 >> (i * 6)) & 0x3F;
+            char::from_digit(digit, 36).map(|c| c.to_ascii_uppercase())
+        })
+        .collect()
+}
+
+// The bootROM only sends the device ID once per RCM session, so whoever reads it
+// first (auto-inject or an injection) remembers it here for the others. The
+// monitor forgets a chip once its device goes away.
+fn cache() -> &'static Mutex<HashMap<(u8, u8), ChipInfo>> {
+    static CACHE: OnceLock<Mutex<HashMap<(u8, u8), ChipInfo>>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Remembers the chip at a bus number and address.
+pub fn remember(bus_and_address: (u8, u8), chip: &ChipInfo) {
+    cache()
+        .lock()
+        .unwrap()
+        .insert(bus_and_address, chip.clone());
+}
+
+/// The chip last seen at a bus number and address, if any.
+pub fn recall(bus_and_address: (u8, u8)) -> Option<ChipInfo> {
+    cache().lock().unwrap().get(&bus_and_address).cloned()
+}
+
+/// Forgets the chip at a bus number and address, e.g. once it's been unplugged.
+pub fn forget(bus_and_address: (u8, u8)) {
+    cache().lock().unwrap().remove(&bus_and_address);
+}
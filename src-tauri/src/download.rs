@@ -0,0 +1,469 @@
+// Downloading payloads without trusting the server, or the frontend, too much.
+//
+// A download streams into a temporary file next to its destination, and is only
+// renamed into place once all of it has arrived (and matched its checksum, if we
+// were given one). A failed or cancelled download never leaves half a payload
+// behind, or replaces a good one.
+
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::archive::ArchiveError;
+use crate::intermezzo::sha256_hex;
+use crate::library::LibraryError;
+
+/// The event the frontend listens to for download progress.
+pub const DOWNLOAD_PROGRESS_EVENT: &str = "download-progress";
+
+/// Big enough for a release archive, small enough that a confused server can't
+/// fill the disk.
+pub const DEFAULT_MAX_SIZE: u64 = 64 * 1024 * 1024;
+/// How long a whole download may take.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(300);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+/// Checksum listings are a line per file; anything bigger isn't one.
+const MAX_CHECKSUMS_SIZE: u64 = 1024 * 1024;
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Where downloads wait, next to the library, until they've been checked.
+pub const STAGING_DIR: &str = ".incoming";
+
+#[derive(Debug)]
+pub enum DownloadError {
+    /// The filename can't be used, even after cleaning it up.
+    InvalidFilename(String),
+    /// The request failed, including by timing out.
+    Request(reqwest::Error),
+    /// The server answered with something other than success.
+    Status(u16),
+    /// The download is bigger than we're willing to take.
+    TooLarge {
+        max_size: u64,
+    },
+    /// The download arrived, but isn't what we expected.
+    ChecksumMismatch {
+        expected: String,
+        actual: String,
+    },
+    /// The source's checksum listing doesn't have the file in it.
+    ChecksumNotListed {
+        filename: String,
+    },
+    Cancelled,
+    /// Writing the download to disk failed.
+    Io(std::io::Error),
+    /// The download was an archive, and getting the payload out of it failed.
+    Extract(ArchiveError),
+    /// The download isn't a payload the library will take.
+    Library(LibraryError),
+    /// The download stopped without finishing, e.g. because its thread panicked.
+    Interrupted(String),
+}
+
+impl DownloadError {
+    /// A stable, machine-readable name for the error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            DownloadError::InvalidFilename(_) => "InvalidFilename",
+            DownloadError::Request(e) if e.is_timeout() => "TimedOut",
+            DownloadError::Request(_) => "RequestFailed",
+            DownloadError::Status(_) => "BadStatus",
+            DownloadError::TooLarge { .. } => "TooLarge",
+            DownloadError::ChecksumMismatch { .. } => "ChecksumMismatch",
+            DownloadError::ChecksumNotListed { .. } => "ChecksumNotListed",
+            DownloadError::Cancelled => "Cancelled",
+            DownloadError::Io(_) => "Io",
+            DownloadError::Extract(e) => e.code(),
+            DownloadError::Library(e) => e.code(),
+            DownloadError::Interrupted(_) => "Interrupted",
+        }
+    }
+}
+
+impl fmt::Display for DownloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DownloadError::InvalidFilename(name) => {
+                write!(f, "{:?} can't be used as a filename", name)
+            }
+            DownloadError::Request(e) if e.is_timeout() => write!(f, "Download timed out: {}", e),
+            DownloadError::Request(e) => write!(f, "Failed to download file: {}", e),
+            DownloadError::Status(status) => {
+                write!(f, "Download failed with status: {}", status)
+            }
+            DownloadError::TooLarge { max_size } => {
+                write!(f, "Download is larger than the {} byte limit", max_size)
+            }
+            DownloadError::ChecksumMismatch { expected, actual } => write!(
+                f,
+                "Download has SHA-256 {}, but {} was expected",
+                actual, expected
+            ),
+            DownloadError::ChecksumNotListed { filename } => {
+                write!(f, "The published checksums don't include {}", filename)
+            }
+            DownloadError::Cancelled => write!(f, "Download cancelled"),
+            DownloadError::Io(e) => write!(f, "Failed to save file: {}", e),
+            DownloadError::Extract(e) => write!(f, "{}", e),
+            DownloadError::Library(e) => write!(f, "{}", e),
+            DownloadError::Interrupted(reason) => {
+                write!(f, "The download stopped unexpectedly: {}", reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DownloadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DownloadError::Request(e) => Some(e),
+            DownloadError::Io(e) => Some(e),
+            DownloadError::Extract(e) => Some(e),
+            DownloadError::Library(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for DownloadError {
+    fn from(e: std::io::Error) -> Self {
+        DownloadError::Io(e)
+    }
+}
+
+impl From<ArchiveError> for DownloadError {
+    fn from(e: ArchiveError) -> Self {
+        DownloadError::Extract(e)
+    }
+}
+
+// Sent to the frontend the same way as a `JoltError`.
+impl Serialize for DownloadError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut error = serializer.serialize_struct("DownloadError", 2)?;
+        error.serialize_field("code", self.code())?;
+        error.serialize_field("message", &self.to_string())?;
+        error.end()
+    }
+}
+
+/// How far along a download is.
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct DownloadProgress {
+    pub filename: String,
+    pub bytes_downloaded: u64,
+    /// What the server says the size is, if it said.
+    pub bytes_total: Option<u64>,
+}
+
+#[derive(Clone, Debug)]
+pub struct DownloadOptions {
+    pub max_size: u64,
+    /// If set, the download is rejected unless it has this SHA-256.
+    pub expected_sha256: Option<String>,
+    pub timeout: Duration,
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        Self {
+            max_size: DEFAULT_MAX_SIZE,
+            expected_sha256: None,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+}
+
+/// Turns whatever we were given into a plain filename that stays inside the
+/// directory it's joined onto.
+pub fn sanitize_filename(name: &str) -> Result<String, DownloadError> {
+    // Only the last path component counts, whichever separator it uses.
+    let base = name.rsplit(['/', '\\']).next().unwrap_or_default();
+
+    let cleaned: String = base
+        .chars()
+        .filter(|c| !c.is_control() && !matches!(c, '<' | '>' | ':' | '"' | '|' | '?' | '*'))
+        .collect();
+    // Leading dots would hide the file (or make it "." or ".."); trailing dots and
+    // spaces are dropped by Windows anyway.
+    let cleaned = cleaned
+        .trim_start_matches(['.', ' '])
+        .trim_end_matches(['.', ' ']);
+
+    if cleaned.is_empty() {
+        return Err(DownloadError::InvalidFilename(name.to_string()));
+    }
+    Ok(cleaned.to_string())
+}
+
+/// Finds `filename`'s SHA-256 in a `sha256sum`-style listing. A listing that's just
+/// a hash is taken to be the hash of `filename`.
+pub fn find_checksum(listing: &str, filename: &str) -> Option<String> {
+    let is_sha256 = |hash: &str| hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit());
+
+    let mut lines = listing
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty());
+    let mut entries = lines.clone().filter_map(|line| {
+        let (hash, name) = line.split_once(char::is_whitespace)?;
+        // `sha256sum` marks files it read in binary mode with a '*'.
+        let name = name.trim_start().trim_start_matches('*');
+        Some((hash, name.rsplit('/').next().unwrap_or(name)))
+    });
+
+    let hash = match entries.find(|(_, name)| *name == filename) {
+        Some((hash, _)) => hash,
+        None => match (lines.next(), lines.next()) {
+            (Some(only), None) => only,
+            _ => return None,
+        },
+    };
+    is_sha256(hash).then(|| hash.to_lowercase())
+}
+
+/// Fetches the listing at `url` and finds `filename`'s SHA-256 in it.
+pub fn fetch_checksum(url: &str, filename: &str) -> Result<String, DownloadError> {
+    let client = reqwest::blocking::Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(CONNECT_TIMEOUT)
+        .build()
+        .map_err(DownloadError::Request)?;
+
+    let response = client.get(url).send().map_err(DownloadError::Request)?;
+    if !response.status().is_success() {
+        return Err(DownloadError::Status(response.status().as_u16()));
+    }
+
+    let mut listing = String::new();
+    response
+        .take(MAX_CHECKSUMS_SIZE + 1)
+        .read_to_string(&mut listing)?;
+    if listing.len() as u64 > MAX_CHECKSUMS_SIZE {
+        return Err(DownloadError::TooLarge {
+            max_size: MAX_CHECKSUMS_SIZE,
+        });
+    }
+
+    find_checksum(&listing, filename).ok_or_else(|| DownloadError::ChecksumNotListed {
+        filename: filename.to_string(),
+    })
+}
+
+/// Downloads `url` to `dir/filename` once the download has succeeded. A different
+/// file that's already there is left alone, and the download goes next to it with
+/// its hash in front of the name. `filename` must already be sanitized.
+pub fn download(
+    url: &str,
+    dir: &Path,
+    filename: &str,
+    options: &DownloadOptions,
+    cancelled: &AtomicBool,
+    progress: &dyn Fn(DownloadProgress),
+) -> Result<PathBuf, DownloadError> {
+    let client = reqwest::blocking::Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(options.timeout)
+        .build()
+        .map_err(DownloadError::Request)?;
+
+    let mut response = client.get(url).send().map_err(DownloadError::Request)?;
+    if !response.status().is_success() {
+        return Err(DownloadError::Status(response.status().as_u16()));
+    }
+
+    // Don't even start on something that says it's too big.
+    let bytes_total = response.content_length();
+    if bytes_total.is_some_and(|length| length > options.max_size) {
+        return Err(DownloadError::TooLarge {
+            max_size: options.max_size,
+        });
+    }
+
+    let mut partial = PartialFile::create(dir, filename)?;
+    let mut hasher = Sha256::new();
+    let mut bytes_downloaded = 0u64;
+    let mut chunk = vec![0u8; CHUNK_SIZE];
+
+    loop {
+        if cancelled.load(Ordering::SeqCst) {
+            return Err(DownloadError::Cancelled);
+        }
+
+        let read = response.read(&mut chunk)?;
+        if read == 0 {
+            break;
+        }
+
+        // The server may not have told the truth about the length, if it said.
+        bytes_downloaded += read as u64;
+        if bytes_downloaded > options.max_size {
+            return Err(DownloadError::TooLarge {
+                max_size: options.max_size,
+            });
+        }
+
+        hasher.update(&chunk[..read]);
+        partial.file.write_all(&chunk[..read])?;
+        progress(DownloadProgress {
+            filename: filename.to_string(),
+            bytes_downloaded,
+            bytes_total,
+        });
+    }
+
+    if let Some(expected) = &options.expected_sha256 {
+        let actual = hex::encode(hasher.finalize());
+        if !actual.eq_ignore_ascii_case(expected.trim()) {
+            return Err(DownloadError::ChecksumMismatch {
+                expected: expected.trim().to_lowercase(),
+                actual,
+            });
+        }
+    }
+
+    Ok(partial.persist_unclobbered(dir, filename)?)
+}
+
+/// A file being written, deleted unless it's persisted.
+pub(crate) struct PartialFile {
+    path: PathBuf,
+    file: File,
+    persisted: bool,
+}
+
+impl PartialFile {
+    pub(crate) fn create(dir: &Path, filename: &str) -> std::io::Result<Self> {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+        // In the same directory, so the final rename can't cross filesystems.
+        let path = dir.join(format!(
+            ".{}.{}-{}.part",
+            filename,
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+
+        Ok(Self {
+            path,
+            file,
+            persisted: false,
+        })
+    }
+
+    pub(crate) fn file(&mut self) -> &mut File {
+        &mut self.file
+    }
+
+    /// Moves the file into place, replacing whatever was there.
+    pub(crate) fn persist(mut self, destination: &Path) -> std::io::Result<PathBuf> {
+        self.file.sync_all()?;
+        fs::rename(&self.path, destination)?;
+        self.persisted = true;
+        Ok(destination.to_path_buf())
+    }
+
+    /// Moves the file into `dir` as `filename`, without replacing a different file.
+    pub(crate) fn persist_unclobbered(
+        self,
+        dir: &Path,
+        filename: &str,
+    ) -> std::io::Result<PathBuf> {
+        let destination = unclobbered(&self.path, dir, filename)?;
+        self.persist(&destination)
+    }
+}
+
+/// Where `path` can go in `dir` as `filename`. If a different file already has that
+/// name, it gets the first eight characters of its SHA-256 in front, like an import
+/// would, so whatever refers to the other file still finds it.
+fn unclobbered(path: &Path, dir: &Path, filename: &str) -> std::io::Result<PathBuf> {
+    let destination = dir.join(filename);
+    if !destination.exists() {
+        return Ok(destination);
+    }
+
+    let sha256 = sha256_hex(&fs::read(path)?);
+    if sha256_hex(&fs::read(&destination)?) == sha256 {
+        Ok(destination)
+    } else {
+        Ok(dir.join(format!("{}-{}", &sha256[..8], filename)))
+    }
+}
+
+/// Moves a finished file into `dir` under its own name, without replacing a
+/// different file. Returns where it went.
+pub fn move_unclobbered(path: &Path, dir: &Path) -> std::io::Result<PathBuf> {
+    let filename = path
+        .file_name()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "not a file"))?
+        .to_string_lossy();
+    let destination = unclobbered(path, dir, &filename)?;
+    fs::rename(path, &destination)?;
+    Ok(destination)
+}
+
+impl Drop for PartialFile {
+    fn drop(&mut self) {
+        if !self.persisted {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// The downloads in progress, by filename, so the frontend can cancel them.
+#[derive(Default)]
+pub struct DownloadState {
+    active: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+impl DownloadState {
+    /// Starts tracking a download, cancelling any earlier one of the same file.
+    pub fn begin(&self, filename: &str) -> Arc<AtomicBool> {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        if let Some(previous) = self
+            .active
+            .lock()
+            .unwrap()
+            .insert(filename.to_string(), cancelled.clone())
+        {
+            previous.store(true, Ordering::SeqCst);
+        }
+        cancelled
+    }
+
+    /// Stops tracking a download once it's over, unless a newer one has replaced it.
+    pub fn finish(&self, filename: &str, cancelled: &Arc<AtomicBool>) {
+        let mut active = self.active.lock().unwrap();
+        if active
+            .get(filename)
+            .is_some_and(|current| Arc::ptr_eq(current, cancelled))
+        {
+            active.remove(filename);
+        }
+    }
+
+    /// Cancels a download. Returns whether it was running.
+    pub fn cancel(&self, filename: &str) -> bool {
+        match self.active.lock().unwrap().remove(filename) {
+            Some(cancelled) => {
+                cancelled.store(true, Ordering::SeqCst);
+                true
+            }
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,306 @@
+// A software model of the Tegra X1 (T210) bootROM's RCM endpoint.
+//
+// This is just enough of the bootROM to run the exploit end to end without a
+// Switch attached: it hands out a device ID, receives the RCM command into the
+// two alternating DMA buffers, copies the message body into IRAM and performs
+// the unchecked GET_STATUS memcpy that Fusée Gelée relies on.
+
+use std::cell::RefCell;
+use std::time::Duration;
+
+use crate::transport::Transport;
+use crate::{RCMHax, RCM_PAYLOAD_ADDR};
+
+// The RCM endpoints.
+const EP_BULK_IN: u8 = 0x81;
+const EP_BULK_OUT: u8 = 0x01;
+
+// Standard GET_STATUS request.
+const GET_STATUS: u8 = 0x0;
+
+// The size of a single RCM DMA buffer.
+const RCM_BUFFER_SIZE: usize = 0x1000;
+
+// The RCM command header that precedes the loaded image.
+const RCM_HEADER_SIZE: usize = 680;
+
+/// Where the modelled bootROM is in the RCM exchange.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EmulatorState {
+    /// Accepting RCM command data.
+    Receiving,
+    /// More data arrived than the RCM header announced; the command was rejected.
+    Rejected,
+    /// The GET_STATUS memcpy ran over the saved return address and the bootROM
+    /// returned through it.
+    Smashed { return_address: u32 },
+}
+
+struct Inner {
+    device_id: [u8; 16],
+    device_id_sent: bool,
+    iram: Vec<u8>,
+    current_buffer: usize,
+    buffers_received: usize,
+    bytes_received: usize,
+    command_length: Option<u32>,
+    state: EmulatorState,
+}
+
+/// A simulated T210 in RCM mode, usable anywhere a [`Transport`] is.
+pub struct RcmEmulator {
+    inner: RefCell<Inner>,
+}
+
+impl RcmEmulator {
+    /// Start of the modelled IRAM.
+    pub const IRAM_BASE: u32 = 0x40000000;
+    /// Size of the modelled IRAM.
+    pub const IRAM_SIZE: usize = 0x40000;
+
+    /// Where the GET_STATUS handler keeps the status it copies out. It sits near
+    /// the top of the stack, so an oversized copy drags whatever follows it
+    /// (our stack spray) down over the stack.
+    pub const STATUS_BUFFER_ADDR: u32 = 0x4000FC00;
+    /// The stack slot holding the return address of the GET_STATUS handler.
+    pub const RETURN_ADDRESS_SLOT: u32 = 0x4000FE00;
+
+    /// The device ID handed out when none is specified.
+    pub const DEFAULT_DEVICE_ID: [u8; 16] = [
+        0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF, 0xFE, 0xDC, 0xBA, 0x98, 0x76, 0x54, 0x32,
+        0x10,
+    ];
+
+    /// Creates a freshly booted device in RCM mode with the given device ID.
+    pub fn new(device_id: [u8; 16]) -> Self {
+        Self {
+            inner: RefCell::new(Inner {
+                device_id,
+                device_id_sent: false,
+                iram: vec![0u8; Self::IRAM_SIZE],
+                current_buffer: 0,
+                buffers_received: 0,
+                bytes_received: 0,
+                command_length: None,
+                state: EmulatorState::Receiving,
+            }),
+        }
+    }
+
+    /// Where the device is in the RCM exchange.
+    pub fn state(&self) -> EmulatorState {
+        self.inner.borrow().state
+    }
+
+    /// The number of bulk buffers received so far.
+    pub fn buffers_received(&self) -> usize {
+        self.inner.borrow().buffers_received
+    }
+
+    /// The number of RCM command bytes received so far.
+    pub fn bytes_received(&self) -> usize {
+        self.inner.borrow().bytes_received
+    }
+
+    /// The DMA buffer the next transfer (or memcpy) will land in.
+    pub fn current_buffer_address(&self) -> u32 {
+        RCMHax::<RcmEmulator>::COPY_BUFFER_ADDRESSES[self.inner.borrow().current_buffer]
+    }
+
+    /// Reads a range of IRAM. Addresses outside IRAM read as zero.
+    pub fn read_memory(&self, address: u32, length: usize) -> Vec<u8> {
+        let inner = self.inner.borrow();
+        (0..length)
+            .map(|i| {
+                Self::iram_offset(address.wrapping_add(i as u32))
+                    .map(|offset| inner.iram[offset])
+                    .unwrap_or(0)
+            })
+            .collect()
+    }
+
+    /// Reads a little-endian word from IRAM.
+    pub fn read_u32(&self, address: u32) -> u32 {
+        let bytes = self.read_memory(address, 4);
+        u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    }
+
+    fn iram_offset(address: u32) -> Option<usize> {
+        let offset = address.checked_sub(Self::IRAM_BASE)? as usize;
+        (offset < Self::IRAM_SIZE).then_some(offset)
+    }
+
+    fn check_alive(inner: &Inner) -> Result<(), rusb::Error> {
+        match inner.state {
+            EmulatorState::Receiving => Ok(()),
+            EmulatorState::Rejected => Err(rusb::Error::Pipe),
+            // Once the bootROM has jumped somewhere else, it stops talking RCM.
+            EmulatorState::Smashed { .. } => Err(rusb::Error::NoDevice),
+        }
+    }
+
+    fn receive_buffer(inner: &mut Inner, data: &[u8]) {
+        // DMA the data into the active buffer...
+        let buffer_address = RCMHax::<RcmEmulator>::COPY_BUFFER_ADDRESSES[inner.current_buffer];
+        let buffer_offset = Self::iram_offset(buffer_address).unwrap();
+        inner.iram[buffer_offset..buffer_offset + data.len()].copy_from_slice(data);
+
+        // ... and copy it out into the command header or the loaded image.
+        for (i, byte) in data.iter().enumerate() {
+            let stream_offset = inner.bytes_received + i;
+            if stream_offset < RCM_HEADER_SIZE {
+                continue;
+            }
+
+            let address = RCM_PAYLOAD_ADDR + (stream_offset - RCM_HEADER_SIZE) as u32;
+            if let Some(offset) = Self::iram_offset(address) {
+                inner.iram[offset] = *byte;
+            }
+        }
+
+        inner.bytes_received += data.len();
+        inner.buffers_received += 1;
+
+        // The first word of the command is the total length the bootROM will accept.
+        if inner.command_length.is_none() && data.len() >= 4 {
+            inner.command_length = Some(u32::from_le_bytes([data[0], data[1], data[2], data[3]]));
+        }
+
+        if let Some(length) = inner.command_length {
+            if inner.bytes_received > length as usize {
+                inner.state = EmulatorState::Rejected;
+            }
+        }
+
+        // The bootROM alternates between its two DMA buffers.
+        inner.current_buffer = 1 - inner.current_buffer;
+    }
+
+    fn get_status(inner: &mut Inner, buf: &mut [u8]) -> Result<usize, rusb::Error> {
+        // The unchecked copy: wLength bytes from the status buffer into the DMA
+        // buffer the next transfer would use. Source is above destination, so a
+        // forward copy behaves like the bootROM's.
+        let length = u16::try_from(buf.len()).map_err(|_| rusb::Error::InvalidParam)? as usize;
+        let destination = RCMHax::<RcmEmulator>::COPY_BUFFER_ADDRESSES[inner.current_buffer];
+
+        for (i, response) in buf.iter_mut().enumerate().take(length) {
+            let source = Self::iram_offset(Self::STATUS_BUFFER_ADDR + i as u32);
+            let target = Self::iram_offset(destination + i as u32);
+            let byte = source.map(|offset| inner.iram[offset]).unwrap_or(0);
+
+            if let Some(offset) = target {
+                inner.iram[offset] = byte;
+            }
+            *response = byte;
+        }
+
+        // If we ran over the saved return address, the handler returns into whatever
+        // we left there and never answers.
+        if destination as usize + length >= Self::RETURN_ADDRESS_SLOT as usize + 4 {
+            let offset = Self::iram_offset(Self::RETURN_ADDRESS_SLOT).unwrap();
+            let return_address = u32::from_le_bytes([
+                inner.iram[offset],
+                inner.iram[offset + 1],
+                inner.iram[offset + 2],
+                inner.iram[offset + 3],
+            ]);
+            inner.state = EmulatorState::Smashed { return_address };
+            return Err(rusb::Error::Timeout);
+        }
+
+        Ok(length)
+    }
+}
+
+impl Default for RcmEmulator {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_DEVICE_ID)
+    }
+}
+
+impl Transport for RcmEmulator {
+    fn read_control(
+        &self,
+        request_type: u8,
+        request: u8,
+        _value: u16,
+        _index: u16,
+        buf: &mut [u8],
+        _timeout: Duration,
+    ) -> Result<usize, rusb::Error> {
+        let mut inner = self.inner.borrow_mut();
+        Self::check_alive(&inner)?;
+
+        // Device, interface and endpoint GET_STATUS all share the vulnerable handler.
+        if request == GET_STATUS && (0x80..=0x82).contains(&request_type) {
+            return Self::get_status(&mut inner, buf);
+        }
+
+        Err(rusb::Error::Pipe)
+    }
+
+    fn read_bulk(
+        &self,
+        endpoint: u8,
+        buf: &mut [u8],
+        _timeout: Duration,
+    ) -> Result<usize, rusb::Error> {
+        let mut inner = self.inner.borrow_mut();
+        Self::check_alive(&inner)?;
+
+        if endpoint != EP_BULK_IN {
+            return Err(rusb::Error::InvalidParam);
+        }
+
+        // The device ID is sent exactly once, at the start of the exchange.
+        if inner.device_id_sent {
+            return Err(rusb::Error::Timeout);
+        }
+
+        let length = std::cmp::min(buf.len(), inner.device_id.len());
+        buf[..length].copy_from_slice(&inner.device_id[..length]);
+        inner.device_id_sent = true;
+        Ok(length)
+    }
+
+    fn write_bulk(
+        &self,
+        endpoint: u8,
+        buf: &[u8],
+        _timeout: Duration,
+    ) -> Result<usize, rusb::Error> {
+        let mut inner = self.inner.borrow_mut();
+        Self::check_alive(&inner)?;
+
+        if endpoint != EP_BULK_OUT {
+            return Err(rusb::Error::InvalidParam);
+        }
+
+        // Each transfer lands in a single DMA buffer.
+        if buf.len() > RCM_BUFFER_SIZE {
+            return Err(rusb::Error::Overflow);
+        }
+
+        Self::receive_buffer(&mut inner, buf);
+        Ok(buf.len())
+    }
+
+    fn claim_interface(&self, iface: u8) -> Result<(), rusb::Error> {
+        // RCM only exposes a single interface.
+        match iface {
+            0 => Ok(()),
+            _ => Err(rusb::Error::NotFound),
+        }
+    }
+
+    fn reset(&self) -> Result<(), rusb::Error> {
+        // A port reset brings the bootROM back to the start of the RCM exchange.
+        let device_id = self.inner.borrow().device_id;
+        *self.inner.borrow_mut() = Self::new(device_id).inner.into_inner();
+        Ok(())
+    }
+
+    fn clear_halt(&self, _endpoint: u8) -> Result<(), rusb::Error> {
+        Self::check_alive(&self.inner.borrow())
+    }
+}
@@ -0,0 +1,202 @@
+// The errors the injection path can fail with.
+//
+// These are sent to the frontend as `{ code, message, usb_error }`, so the UI can
+// react to the code (e.g. point the user at udev rules on AccessDenied) instead of
+// matching on the English message.
+
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Debug)]
+pub enum JoltError {
+    /// There's no way to trigger the vulnerability on this system.
+    UnsupportedPlatform { system: String },
+    /// No RCM device is connected.
+    DeviceNotFound,
+    /// We were waiting for a device and the user gave up.
+    WaitCancelled,
+    /// We were waiting for a device and none showed up in time.
+    WaitTimedOut { timeout: Duration },
+    /// Another injection is already using the device.
+    InjectionInProgress,
+    /// The device is there, but we aren't allowed to open it.
+    AccessDenied(rusb::Error),
+    /// Talking to the USB stack failed before we got to the device.
+    UsbUnavailable(rusb::Error),
+    /// We couldn't claim the RCM interface, usually because something else has.
+    ClaimFailed { interface: u8, source: rusb::Error },
+    /// A custom intermezzo couldn't be read.
+    IntermezzoMissing {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// An intermezzo didn't match the hash it was supposed to have.
+    IntermezzoCorrupted(String),
+    /// A custom intermezzo was given without a SHA-256 to check it against.
+    IntermezzoNotPinned,
+    /// A relocator couldn't be generated for the requested layout.
+    InvalidRelocatorLayout(String),
+    /// The payload file couldn't be read.
+    PayloadUnreadable {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// The file isn't an RCM payload at all.
+    PayloadRejected { description: String, reason: String },
+    /// The payload doesn't fit in a single RCM command.
+    PayloadTooLarge { bytes_over: usize },
+    /// Sending the RCM command failed.
+    UploadFailed(rusb::Error),
+    /// Padding the upload onto the high DMA buffer failed.
+    HighBufferFailed(rusb::Error),
+    /// The oversized GET_STATUS request failed in a way that doesn't mean success.
+    TriggerFailed(rusb::Error),
+    /// The device's host controller can't send the oversized request, e.g. because
+    /// it isn't an XHCI one. Carries what the check found.
+    UnsupportedController(String),
+    /// There's no exploit strategy by that name.
+    UnknownStrategy(String),
+    /// One of the legacy strategies didn't get the device to crash.
+    StrategyFailed { strategy: String, reason: String },
+    /// The injection stopped without finishing, e.g. because its thread panicked.
+    Interrupted(String),
+}
+
+impl JoltError {
+    /// A stable, machine-readable name for the error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            JoltError::UnsupportedPlatform { .. } => "UnsupportedPlatform",
+            JoltError::DeviceNotFound => "DeviceNotFound",
+            JoltError::WaitCancelled => "WaitCancelled",
+            JoltError::WaitTimedOut { .. } => "WaitTimedOut",
+            JoltError::InjectionInProgress => "InjectionInProgress",
+            JoltError::AccessDenied(_) => "AccessDenied",
+            JoltError::UsbUnavailable(_) => "UsbUnavailable",
+            JoltError::ClaimFailed { .. } => "ClaimFailed",
+            JoltError::IntermezzoMissing { .. } => "IntermezzoMissing",
+            JoltError::IntermezzoCorrupted(_) => "IntermezzoCorrupted",
+            JoltError::IntermezzoNotPinned => "IntermezzoNotPinned",
+            JoltError::InvalidRelocatorLayout(_) => "InvalidRelocatorLayout",
+            JoltError::PayloadUnreadable { .. } => "PayloadUnreadable",
+            JoltError::PayloadRejected { .. } => "PayloadRejected",
+            JoltError::PayloadTooLarge { .. } => "PayloadTooLarge",
+            JoltError::UploadFailed(_) => "UploadFailed",
+            JoltError::HighBufferFailed(_) => "HighBufferFailed",
+            JoltError::TriggerFailed(_) => "TriggerFailed",
+            JoltError::UnsupportedController(_) => "UnsupportedController",
+            JoltError::UnknownStrategy(_) => "UnknownStrategy",
+            JoltError::StrategyFailed { .. } => "StrategyFailed",
+            JoltError::Interrupted(_) => "Interrupted",
+        }
+    }
+
+    /// The USB error underneath this one, if there is one.
+    pub fn usb_error(&self) -> Option<rusb::Error> {
+        match self {
+            JoltError::AccessDenied(e)
+            | JoltError::UsbUnavailable(e)
+            | JoltError::ClaimFailed { source: e, .. }
+            | JoltError::UploadFailed(e)
+            | JoltError::HighBufferFailed(e)
+            | JoltError::TriggerFailed(e) => Some(*e),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for JoltError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JoltError::UnsupportedPlatform { system } => write!(
+                f,
+                "No backend to trigger the vulnerability on '{}'-- it's likely we don't support your OS!",
+                system
+            ),
+            JoltError::DeviceNotFound => write!(f, "No TegraRCM device found?"),
+            JoltError::WaitCancelled => write!(f, "Stopped waiting for a TegraRCM device"),
+            JoltError::WaitTimedOut { timeout } => write!(
+                f,
+                "No TegraRCM device showed up within {} seconds",
+                timeout.as_secs()
+            ),
+            JoltError::InjectionInProgress => write!(f, "An injection is already in progress"),
+            JoltError::AccessDenied(e) => write!(
+                f,
+                "Found a TegraRCM device, but don't have permission to open it: {}",
+                e
+            ),
+            JoltError::UsbUnavailable(e) => write!(f, "Failed to talk to the USB stack: {}", e),
+            JoltError::ClaimFailed { interface, source } => {
+                write!(f, "Failed to claim interface {}: {}", interface, source)
+            }
+            JoltError::IntermezzoMissing { path, source } => {
+                write!(f, "Failed to read intermezzo {:?}: {}", path, source)
+            }
+            JoltError::IntermezzoCorrupted(reason) => write!(f, "{}", reason),
+            JoltError::IntermezzoNotPinned => write!(
+                f,
+                "A custom intermezzo can only be used with a pinned SHA-256"
+            ),
+            JoltError::InvalidRelocatorLayout(reason) => write!(f, "{}", reason),
+            JoltError::PayloadUnreadable { path, source } => {
+                write!(f, "Failed to read payload file {:?}: {}", path, source)
+            }
+            JoltError::PayloadRejected {
+                description,
+                reason,
+            } => write!(
+                f,
+                "{} doesn't look like a usable payload: {}",
+                description, reason
+            ),
+            // Word for word what fusee-launcher says.
+            JoltError::PayloadTooLarge { bytes_over } => write!(
+                f,
+                "ERROR: Payload is too large to be submitted via RCM. ({} bytes larger than max).",
+                bytes_over
+            ),
+            JoltError::UploadFailed(e) => write!(f, "Failed to upload payload: {}", e),
+            JoltError::HighBufferFailed(e) => write!(f, "Failed to switch to high buffer: {}", e),
+            JoltError::TriggerFailed(e) => write!(f, "Exploit failed: {}", e),
+            JoltError::UnsupportedController(reason) => {
+                write!(f, "Can't trigger the vulnerability through usbfs: {}", reason)
+            }
+            JoltError::UnknownStrategy(id) => write!(f, "There's no exploit strategy called '{}'", id),
+            JoltError::StrategyFailed { strategy, reason } => {
+                write!(f, "The {} strategy failed: {}", strategy, reason)
+            }
+            JoltError::Interrupted(reason) => {
+                write!(f, "The injection stopped unexpectedly: {}", reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for JoltError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            JoltError::IntermezzoMissing { source, .. }
+            | JoltError::PayloadUnreadable { source, .. } => Some(source),
+            JoltError::AccessDenied(e)
+            | JoltError::UsbUnavailable(e)
+            | JoltError::ClaimFailed { source: e, .. }
+            | JoltError::UploadFailed(e)
+            | JoltError::HighBufferFailed(e)
+            | JoltError::TriggerFailed(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl Serialize for JoltError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut error = serializer.serialize_struct("JoltError", 3)?;
+        error.serialize_field("code", self.code())?;
+        error.serialize_field("message", &self.to_string())?;
+        error.serialize_field("usb_error", &self.usb_error().map(|e| format!("{:?}", e)))?;
+        error.end()
+    }
+}
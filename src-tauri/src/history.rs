@@ -0,0 +1,359 @@
+// A record of every injection attempt, for working out why something that worked
+// yesterday doesn't today.
+//
+// Attempts are appended to a JSON Lines file in the data directory as they
+// finish, one entry per line, so a crash can at worst lose the line being
+// written. Nothing is ever rewritten except by clearing the whole history.
+
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::warn;
+
+use crate::download::PartialFile;
+use crate::report::{millis, unix_now, PhaseTimings};
+use crate::verify::VerificationStatus;
+use crate::{InjectionReport, JoltError};
+
+/// The history file, in the data directory.
+pub const HISTORY_FILE: &str = "history.jsonl";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Succeeded,
+    Failed,
+    /// The user stopped waiting for a device, so nothing was tried.
+    Cancelled,
+}
+
+impl Outcome {
+    fn as_str(self) -> &'static str {
+        match self {
+            Outcome::Succeeded => "succeeded",
+            Outcome::Failed => "failed",
+            Outcome::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// One injection attempt. Whatever wasn't known by the time it finished, e.g. the
+/// port of a device that was never found, is missing.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// When the attempt started, in seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub payload_path: String,
+    pub payload_sha256: Option<String>,
+    pub chip_uid: Option<String>,
+    pub port_path: Option<String>,
+    /// The exploit strategy that delivered the payload, if one did.
+    pub strategy: Option<String>,
+    pub outcome: Outcome,
+    /// Whether the payload looked to be running afterwards, for injections that
+    /// got to the end.
+    pub verification: Option<VerificationStatus>,
+    pub error_code: Option<String>,
+    pub error_message: Option<String>,
+    /// How long the whole attempt took, including waiting for the device.
+    pub duration_ms: f64,
+    /// How long each phase took, for injections that got to the end.
+    pub timings: Option<PhaseTimings>,
+}
+
+impl HistoryEntry {
+    /// An attempt that's just starting. It's filled in as it goes, then finished.
+    pub fn begin(payload_path: &str) -> Self {
+        Self {
+            timestamp: unix_now(),
+            payload_path: payload_path.to_string(),
+            payload_sha256: None,
+            chip_uid: None,
+            port_path: None,
+            strategy: None,
+            outcome: Outcome::Failed,
+            verification: None,
+            error_code: None,
+            error_message: None,
+            duration_ms: 0.0,
+            timings: None,
+        }
+    }
+
+    /// Records how the attempt ended.
+    pub fn finish(&mut self, result: &Result<InjectionReport, JoltError>, elapsed: Duration) {
+        self.duration_ms = millis(elapsed);
+        match result {
+            Ok(report) => {
+                self.outcome = Outcome::Succeeded;
+                self.payload_sha256 = Some(report.payload_sha256.clone());
+                if let Some(chip_info) = &report.chip_info {
+                    self.chip_uid = Some(chip_info.uid.clone());
+                }
+                self.strategy = Some(report.strategy.clone());
+                self.verification = Some(report.verification.status);
+                self.timings = Some(report.timings.clone());
+            }
+            Err(e) => {
+                self.outcome = match e {
+                    JoltError::WaitCancelled => Outcome::Cancelled,
+                    _ => Outcome::Failed,
+                };
+                self.error_code = Some(e.code().to_string());
+                self.error_message = Some(e.to_string());
+            }
+        }
+    }
+}
+
+/// Which entries to return. Everything that's set has to match.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistoryFilter {
+    /// Entries from this time on, in seconds since the Unix epoch.
+    pub since: Option<u64>,
+    /// Entries from before this time.
+    pub until: Option<u64>,
+    pub outcome: Option<Outcome>,
+    pub chip_uid: Option<String>,
+    pub port_path: Option<String>,
+    pub payload_sha256: Option<String>,
+    pub error_code: Option<String>,
+    /// At most this many of the newest matches.
+    pub limit: Option<usize>,
+}
+
+impl HistoryFilter {
+    pub fn matches(&self, entry: &HistoryEntry) -> bool {
+        let same = |wanted: &Option<String>, actual: &Option<String>| {
+            wanted.as_ref().is_none_or(|wanted| {
+                actual
+                    .as_ref()
+                    .is_some_and(|actual| actual.eq_ignore_ascii_case(wanted))
+            })
+        };
+
+        self.since.is_none_or(|since| entry.timestamp >= since)
+            && self.until.is_none_or(|until| entry.timestamp < until)
+            && self.outcome.is_none_or(|outcome| entry.outcome == outcome)
+            && same(&self.chip_uid, &entry.chip_uid)
+            && same(&self.port_path, &entry.port_path)
+            && same(&self.payload_sha256, &entry.payload_sha256)
+            && same(&self.error_code, &entry.error_code)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+pub struct InjectionHistory {
+    path: PathBuf,
+    /// Keeps appends from interleaving.
+    lock: Mutex<()>,
+}
+
+impl InjectionHistory {
+    pub fn open(path: PathBuf) -> Self {
+        Self {
+            path,
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn append(&self, entry: &HistoryEntry) -> io::Result<()> {
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+
+        let _appending = self.lock.lock().unwrap();
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        // One write, so a line is never split between attempts.
+        file.write_all(line.as_bytes())
+    }
+
+    /// Every entry, oldest first. Lines that can't be read are skipped.
+    pub fn entries(&self) -> io::Result<Vec<HistoryEntry>> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        Ok(contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(entry) => Some(entry),
+                Err(e) => {
+                    warn!(error = %e, "Skipping an unreadable history entry");
+                    None
+                }
+            })
+            .collect())
+    }
+
+    /// The entries the filter matches, newest first.
+    pub fn query(&self, filter: &HistoryFilter) -> io::Result<Vec<HistoryEntry>> {
+        let mut entries: Vec<HistoryEntry> = self
+            .entries()?
+            .into_iter()
+            .rev()
+            .filter(|entry| filter.matches(entry))
+            .collect();
+        if let Some(limit) = filter.limit {
+            entries.truncate(limit);
+        }
+        Ok(entries)
+    }
+
+    pub fn clear(&self) -> io::Result<()> {
+        let _appending = self.lock.lock().unwrap();
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+const CSV_COLUMNS: [&str; 17] = [
+    "timestamp",
+    "outcome",
+    "verification",
+    "payload_path",
+    "payload_sha256",
+    "chip_uid",
+    "port_path",
+    "strategy",
+    "error_code",
+    "error_message",
+    "duration_ms",
+    "read_device_id_ms",
+    "build_payload_ms",
+    "upload_ms",
+    "switch_to_highbuf_ms",
+    "trigger_ms",
+    "total_ms",
+];
+
+/// Writes the entries out in the given format.
+pub fn export(entries: &[HistoryEntry], format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Json => serde_json::to_string_pretty(entries).unwrap_or_default(),
+        ExportFormat::Csv => to_csv(entries),
+    }
+}
+
+fn to_csv(entries: &[HistoryEntry]) -> String {
+    let mut csv = CSV_COLUMNS.join(",");
+    csv.push_str("\r\n");
+
+    for entry in entries {
+        let optional = |value: &Option<String>| value.as_deref().map(csv_field).unwrap_or_default();
+        let timing = |pick: fn(&PhaseTimings) -> f64| {
+            entry
+                .timings
+                .as_ref()
+                .map(|timings| format!("{:.3}", pick(timings)))
+                .unwrap_or_default()
+        };
+
+        let fields: [String; CSV_COLUMNS.len()] = [
+            entry.timestamp.to_string(),
+            entry.outcome.as_str().to_string(),
+            entry
+                .verification
+                .map(|status| status.as_str().to_string())
+                .unwrap_or_default(),
+            csv_field(&entry.payload_path),
+            optional(&entry.payload_sha256),
+            optional(&entry.chip_uid),
+            optional(&entry.port_path),
+            optional(&entry.strategy),
+            optional(&entry.error_code),
+            optional(&entry.error_message),
+            format!("{:.3}", entry.duration_ms),
+            timing(|t| t.read_device_id_ms),
+            timing(|t| t.build_payload_ms),
+            timing(|t| t.upload_ms),
+            timing(|t| t.switch_to_highbuf_ms),
+            timing(|t| t.trigger_ms),
+            timing(|t| t.total_ms),
+        ];
+        let _ = write!(csv, "{}\r\n", fields.join(","));
+    }
+    csv
+}
+
+/// Quotes a field if it needs it, as RFC 4180 says.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Writes an export to `path`, replacing whatever was there.
+pub fn export_to(path: &Path, entries: &[HistoryEntry], format: ExportFormat) -> io::Result<()> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "history".to_string());
+
+    let mut partial = PartialFile::create(dir, &file_name)?;
+    partial
+        .file()
+        .write_all(export(entries, format).as_bytes())?;
+    partial.file().flush()?;
+    partial.persist(path)?;
+    Ok(())
+}
+
+/// The entries the filter matches, newest first.
+#[tauri::command]
+pub fn query_history(
+    history: tauri::State<'_, InjectionHistory>,
+    filter: Option<HistoryFilter>,
+) -> Result<Vec<HistoryEntry>, String> {
+    history
+        .query(&filter.unwrap_or_default())
+        .map_err(|e| e.to_string())
+}
+
+/// Saves the entries the filter matches to `path`, and returns how many there were.
+#[tauri::command]
+pub fn export_history(
+    history: tauri::State<'_, InjectionHistory>,
+    path: String,
+    format: ExportFormat,
+    filter: Option<HistoryFilter>,
+) -> Result<usize, String> {
+    let entries = history
+        .query(&filter.unwrap_or_default())
+        .map_err(|e| e.to_string())?;
+    export_to(Path::new(&path), &entries, format).map_err(|e| e.to_string())?;
+    Ok(entries.len())
+}
+
+#[tauri::command]
+pub fn clear_history(history: tauri::State<'_, InjectionHistory>) -> Result<(), String> {
+    history.clear().map_err(|e| e.to_string())
+}
@@ -0,0 +1,132 @@
+// The intermezzo relocator: the first-stage payload that moves the user payload
+// into place once we have control.
+//
+// The built-in relocator is generated from the layout `build_payload` uses, and
+// is checked against the hash of the stub fusee-launcher ships before we build an
+// RCM stream around it. Relocators read from disk are only accepted with a pinned
+// SHA-256, so a truncated or tampered stub can never reach the device.
+
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+use crate::error::JoltError;
+use crate::relocator::{self, RelocatorParams};
+use crate::{PAYLOAD_START_ADDR, RCM_PAYLOAD_ADDR};
+
+/// SHA-256 of the relocator generated for the default layout, which is the
+/// stub fusee-launcher ships.
+pub const BUILTIN_INTERMEZZO_SHA256: &str =
+    "8517c0cb36ecb0b53129ae15269cf7f28a4f48ea599e8b6fc4cb8ead96ea3dfa";
+
+/// The most a relocator can take up: it has to end before the payload starts.
+pub const MAX_INTERMEZZO_SIZE: usize = (PAYLOAD_START_ADDR - RCM_PAYLOAD_ADDR) as usize;
+
+/// An intermezzo relocator whose contents match a known hash.
+///
+/// Built-in and generated relocators come from [`relocator::assemble`]; ones read
+/// from disk have to match a pinned hash.
+#[derive(Clone, Debug)]
+pub struct Intermezzo {
+    bytes: Vec<u8>,
+}
+
+impl Intermezzo {
+    /// The relocator for payloads linked at 0x40010000.
+    pub fn builtin() -> Result<Self, JoltError> {
+        let bytes = relocator::assemble(&RelocatorParams::default())
+            .and_then(|bytes| verify(&bytes, BUILTIN_INTERMEZZO_SHA256).map(|_| bytes))
+            .map_err(|e| {
+                JoltError::IntermezzoCorrupted(format!(
+                    "The built-in intermezzo is corrupted: {}",
+                    e
+                ))
+            })?;
+
+        Ok(Self { bytes })
+    }
+
+    /// A relocator generated for a custom layout, e.g. a payload linked elsewhere.
+    pub fn generate(params: &RelocatorParams) -> Result<Self, JoltError> {
+        let bytes = relocator::assemble(params).map_err(JoltError::InvalidRelocatorLayout)?;
+        check_size(&bytes).map_err(|e| {
+            JoltError::IntermezzoCorrupted(format!("The generated intermezzo is unusable: {}", e))
+        })?;
+
+        Ok(Self { bytes })
+    }
+
+    /// A user-supplied relocator, accepted only if it matches the pinned SHA-256
+    /// and fits before the payload.
+    pub fn from_file(path: &Path, pinned_sha256: &str) -> Result<Self, JoltError> {
+        let bytes = std::fs::read(path).map_err(|source| JoltError::IntermezzoMissing {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        verify(&bytes, pinned_sha256)
+            .and_then(|_| check_size(&bytes))
+            .map_err(|e| {
+                JoltError::IntermezzoCorrupted(format!(
+                    "Refusing to use intermezzo {:?}: {}",
+                    path, e
+                ))
+            })?;
+
+        Ok(Self { bytes })
+    }
+
+    /// The built-in relocator, unless the user has pinned their own.
+    pub fn select(path: Option<&Path>, pinned_sha256: Option<&str>) -> Result<Self, JoltError> {
+        match (path, pinned_sha256) {
+            (Some(path), Some(sha256)) => Self::from_file(path, sha256),
+            (Some(_), None) => Err(JoltError::IntermezzoNotPinned),
+            (None, _) => Self::builtin(),
+        }
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+}
+
+/// Returns the lowercase hex SHA-256 of some data.
+pub fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+fn check_size(bytes: &[u8]) -> Result<(), String> {
+    if bytes.len() > MAX_INTERMEZZO_SIZE {
+        return Err(format!(
+            "the relocator is {} bytes, but only {} fit before the payload",
+            bytes.len(),
+            MAX_INTERMEZZO_SIZE
+        ));
+    }
+
+    Ok(())
+}
+
+fn verify(bytes: &[u8], expected_sha256: &str) -> Result<(), String> {
+    if bytes.is_empty() {
+        return Err("the relocator is empty".to_string());
+    }
+
+    let actual = sha256_hex(bytes);
+    if !actual.eq_ignore_ascii_case(expected_sha256.trim()) {
+        return Err(format!(
+            "SHA-256 mismatch (expected {}, got {})",
+            expected_sha256.trim(),
+            actual
+        ));
+    }
+
+    Ok(())
+}
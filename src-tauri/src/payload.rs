@@ -0,0 +1,249 @@
+// Payload identification and pre-flight checks.
+//
+// Tells the user what they actually picked before we touch the device, and
+// catches the usual mistakes (an archive, a homebrew app, a game dump) that would
+// otherwise only show up as a confusing failure halfway through the exploit.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::archive::ArchiveKind;
+use crate::error::JoltError;
+use crate::intermezzo::sha256_hex;
+use crate::MAX_PAYLOAD_SIZE;
+
+// hekate keeps its version metadata ("ICTC" followed by the version digits) at a
+// fixed offset near the start of the binary.
+const HEKATE_META_OFFSET: usize = 0x118;
+const HEKATE_MAGIC: &[u8] = b"ICTC";
+
+// Reboot-to-RCM stubs poke PMC scratch0 (0x7000E450) and reset the SoC; they're
+// tiny and carry the register address as a literal.
+const PMC_SCRATCH0: u32 = 0x7000E450;
+const REBOOT_STUB_MAX_SIZE: usize = 0x1000;
+
+/// What a payload file looks like.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PayloadKind {
+    Hekate {
+        version: String,
+        nyx_version: Option<String>,
+    },
+    Fusee,
+    LockpickRcm {
+        version: Option<String>,
+    },
+    TegraExplorer,
+    RebootToRcm,
+    /// Nothing we recognise, but it may well be a valid payload.
+    Unknown,
+
+    // Files that are definitely not RCM payloads.
+    Empty,
+    Zip,
+    SevenZip,
+    Nro,
+    Nsp,
+    Xci,
+}
+
+impl PayloadKind {
+    /// A short human-readable name.
+    pub fn describe(&self) -> String {
+        match self {
+            PayloadKind::Hekate {
+                version,
+                nyx_version: Some(nyx_version),
+            } => format!("hekate v{} (Nyx v{})", version, nyx_version),
+            PayloadKind::Hekate { version, .. } => format!("hekate v{}", version),
+            PayloadKind::Fusee => "Atmosphère fusee".to_string(),
+            PayloadKind::LockpickRcm {
+                version: Some(version),
+            } => format!("Lockpick_RCM v{}", version),
+            PayloadKind::LockpickRcm { version: None } => "Lockpick_RCM".to_string(),
+            PayloadKind::TegraExplorer => "TegraExplorer".to_string(),
+            PayloadKind::RebootToRcm => "reboot-to-RCM stub".to_string(),
+            PayloadKind::Unknown => "unrecognised payload".to_string(),
+            PayloadKind::Empty => "empty file".to_string(),
+            PayloadKind::Zip => "ZIP archive".to_string(),
+            PayloadKind::SevenZip => "7-Zip archive".to_string(),
+            PayloadKind::Nro => "homebrew application (NRO)".to_string(),
+            PayloadKind::Nsp => "eShop package (NSP)".to_string(),
+            PayloadKind::Xci => "game card image (XCI)".to_string(),
+        }
+    }
+
+    /// Why this file can't be injected, if it can't.
+    fn rejection(&self) -> Option<&'static str> {
+        match self {
+            PayloadKind::Empty => Some("The file is empty."),
+            PayloadKind::Zip | PayloadKind::SevenZip => {
+                Some("This is an archive. Extract it and pick the .bin payload inside.")
+            }
+            PayloadKind::Nro => {
+                Some("This is a homebrew app; it runs from the homebrew menu, not over RCM.")
+            }
+            PayloadKind::Nsp | PayloadKind::Xci => {
+                Some("This is a game or title dump, not an RCM payload.")
+            }
+            _ => None,
+        }
+    }
+}
+
+/// What we know about a payload before sending it.
+#[derive(Clone, Debug, Serialize)]
+pub struct PayloadInfo {
+    pub kind: PayloadKind,
+    pub description: String,
+    pub size: usize,
+    pub sha256: String,
+    /// The largest payload that fits in a single RCM command.
+    pub max_size: usize,
+    /// How many bytes are left before the RCM limit; negative if over it.
+    pub headroom: i64,
+    /// Why the payload can't be injected, if it can't.
+    pub problem: Option<String>,
+}
+
+impl PayloadInfo {
+    /// Fails with the reason this payload can't be injected, if there is one.
+    pub fn check(&self) -> Result<(), JoltError> {
+        if let Some(reason) = self.kind.rejection() {
+            return Err(JoltError::PayloadRejected {
+                description: self.description.clone(),
+                reason: reason.to_string(),
+            });
+        }
+
+        if self.headroom < 0 {
+            return Err(JoltError::PayloadTooLarge {
+                bytes_over: -self.headroom as usize,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Identifies a payload and checks it can be sent.
+pub fn inspect(data: &[u8]) -> PayloadInfo {
+    let kind = identify(data);
+    let headroom = MAX_PAYLOAD_SIZE as i64 - data.len() as i64;
+
+    let problem = kind.rejection().map(str::to_string).or_else(|| {
+        (headroom < 0).then(|| {
+            format!(
+                "It is {} bytes larger than the {} bytes that fit in an RCM command.",
+                -headroom, MAX_PAYLOAD_SIZE
+            )
+        })
+    });
+
+    PayloadInfo {
+        description: kind.describe(),
+        kind,
+        size: data.len(),
+        sha256: sha256_hex(data),
+        max_size: MAX_PAYLOAD_SIZE,
+        headroom,
+        problem,
+    }
+}
+
+/// Reads and inspects a payload file.
+pub fn inspect_file(path: &Path) -> Result<PayloadInfo, JoltError> {
+    Ok(inspect(&read_file(path)?))
+}
+
+/// Reads a payload, so it can be inspected and then sent without reading it again.
+pub fn read_file(path: &Path) -> Result<Vec<u8>, JoltError> {
+    std::fs::read(path).map_err(|source| JoltError::PayloadUnreadable {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+fn identify(data: &[u8]) -> PayloadKind {
+    if data.is_empty() {
+        return PayloadKind::Empty;
+    }
+
+    // Containers and Switch formats, by their magic.
+    match ArchiveKind::detect(data) {
+        Some(ArchiveKind::Zip) => return PayloadKind::Zip,
+        Some(ArchiveKind::SevenZip) => return PayloadKind::SevenZip,
+        None => {}
+    }
+    if data.starts_with(b"PFS0") {
+        return PayloadKind::Nsp;
+    }
+    if data.get(0x10..0x14) == Some(b"NRO0") {
+        return PayloadKind::Nro;
+    }
+    if data.get(0x100..0x104) == Some(b"HEAD") {
+        return PayloadKind::Xci;
+    }
+
+    if let Some(version) = hekate_version(data) {
+        return PayloadKind::Hekate {
+            version,
+            nyx_version: version_after(data, b"Nyx v"),
+        };
+    }
+
+    // Everything else is recognised by the strings it carries.
+    if contains(data, b"Lockpick_RCM") {
+        return PayloadKind::LockpickRcm {
+            version: version_after(data, b"Lockpick_RCM v"),
+        };
+    }
+    if contains(data, b"TegraExplorer") {
+        return PayloadKind::TegraExplorer;
+    }
+    if contains(data, b"fusee") && contains(data, b"Atmosph") {
+        return PayloadKind::Fusee;
+    }
+    if data.len() <= REBOOT_STUB_MAX_SIZE && contains(data, &PMC_SCRATCH0.to_le_bytes()) {
+        return PayloadKind::RebootToRcm;
+    }
+
+    PayloadKind::Unknown
+}
+
+/// hekate stores its version as ASCII digits (major, minor, hotfix) right after
+/// the magic.
+fn hekate_version(data: &[u8]) -> Option<String> {
+    let meta = data.get(HEKATE_META_OFFSET..HEKATE_META_OFFSET + 7)?;
+    if &meta[..4] != HEKATE_MAGIC || !meta[4..].iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+
+    Some(format!(
+        "{}.{}.{}",
+        meta[4] as char, meta[5] as char, meta[6] as char
+    ))
+}
+
+/// The dotted version number that follows `marker`, if any.
+fn version_after(data: &[u8], marker: &[u8]) -> Option<String> {
+    let start = find(data, marker)? + marker.len();
+    let version: String = data[start..]
+        .iter()
+        .take_while(|b| b.is_ascii_digit() || **b == b'.')
+        .map(|b| *b as char)
+        .collect();
+
+    let version = version.trim_end_matches('.');
+    (!version.is_empty()).then(|| version.to_string())
+}
+
+fn find(data: &[u8], needle: &[u8]) -> Option<usize> {
+    data.windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn contains(data: &[u8], needle: &[u8]) -> bool {
+    find(data, needle).is_some()
+}
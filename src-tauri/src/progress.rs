@@ -0,0 +1,46 @@
+// Progress reporting for an injection in flight.
+//
+// The exploit reports each phase through a callback, so the same code can feed a
+// Tauri event stream, a terminal or a test.
+
+use serde::{Deserialize, Serialize};
+
+/// The event the frontend listens to for injection progress.
+pub const INJECTION_PROGRESS_EVENT: &str = "injection-progress";
+
+/// Where an injection is up to.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "phase", rename_all = "snake_case")]
+pub enum InjectionProgress {
+    /// Looking for the RCM device and opening it.
+    OpeningDevice,
+    /// No device yet; waiting for one to enter RCM.
+    WaitingForDevice { timeout_secs: Option<u64> },
+    /// Claiming the RCM interface.
+    ClaimingInterface { interface: u8 },
+    /// Reading the device ID.
+    ReadingDeviceId,
+    /// Building the RCM command around the payload.
+    BuildingPayload,
+    /// Delivering the payload with a strategy, e.g. "fusee-launcher".
+    TryingStrategy { strategy: String },
+    /// Uploading the RCM command, one buffer at a time.
+    Uploading {
+        buffers_written: usize,
+        buffers_total: usize,
+        bytes_written: usize,
+        bytes_total: usize,
+    },
+    /// Padding the upload onto the high DMA buffer.
+    SwitchingToHighBuffer,
+    /// Issuing the oversized GET_STATUS request.
+    Triggering,
+    /// Watching the bus to see whether the payload is running.
+    Verifying { window_secs: u64 },
+}
+
+/// Something that wants to hear about injection progress.
+pub type ProgressCallback<'a> = &'a dyn Fn(InjectionProgress);
+
+/// Discards progress, for callers that don't care.
+pub fn ignore_progress(_: InjectionProgress) {}
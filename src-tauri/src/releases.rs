@@ -0,0 +1,456 @@
+// Finding payload releases on GitHub, politely.
+//
+// Anonymous API calls are limited to 60 an hour, so every listing is cached on
+// disk with its ETag and re-requested with `If-None-Match`; GitHub doesn't count
+// a 304 against the limit. When GitHub can't be reached (or we've been told not
+// to try) the last listing we saw is served instead, marked as such.
+
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use serde::Deserialize;
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::warn;
+
+use crate::download::PartialFile;
+use crate::report::unix_now;
+use crate::storage;
+
+pub const GITHUB_API: &str = "https://api.github.com";
+const RELEASES_KEY: &str = "releases";
+
+const USER_AGENT: &str = concat!("jolt/", env!("CARGO_PKG_VERSION"));
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// GitHub's maximum; nobody needs more than the last hundred releases of a payload.
+const PER_PAGE: u32 = 100;
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, Deserialize)]
+pub struct ReleaseAsset {
+    pub id: u64,
+    pub name: String,
+    pub browser_download_url: String,
+    pub size: u64,
+}
+
+/// A release, with just the fields jolt uses.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, Deserialize)]
+pub struct Release {
+    pub id: u64,
+    pub name: Option<String>,
+    pub tag_name: String,
+    pub published_at: Option<String>,
+    #[serde(default)]
+    pub prerelease: bool,
+    #[serde(default)]
+    pub assets: Vec<ReleaseAsset>,
+}
+
+/// What GitHub said about our rate limit on its last response.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct RateLimit {
+    pub limit: u64,
+    pub remaining: u64,
+    /// When the limit resets, in seconds since the Unix epoch.
+    pub reset_at: u64,
+}
+
+impl RateLimit {
+    fn from_headers(headers: &reqwest::header::HeaderMap) -> Option<Self> {
+        let number = |name: &str| headers.get(name)?.to_str().ok()?.trim().parse().ok();
+        Some(RateLimit {
+            limit: number("x-ratelimit-limit")?,
+            remaining: number("x-ratelimit-remaining")?,
+            reset_at: number("x-ratelimit-reset")?,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum ReleaseError {
+    /// The owner or repository name isn't one GitHub would accept.
+    InvalidRepo(String),
+    /// We're offline and have never listed this repository's releases.
+    NotCached { repo: String },
+    /// The request failed, including by timing out.
+    Request(reqwest::Error),
+    /// GitHub won't answer until the rate limit resets.
+    RateLimited(RateLimit),
+    /// GitHub answered with something other than success.
+    Status {
+        status: u16,
+        message: Option<String>,
+    },
+    /// GitHub's answer isn't a list of releases.
+    Parse(String),
+    /// The request stopped without finishing, e.g. because its thread panicked.
+    Interrupted(String),
+}
+
+impl ReleaseError {
+    /// A stable, machine-readable name for the error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ReleaseError::InvalidRepo(_) => "InvalidRepo",
+            ReleaseError::NotCached { .. } => "NotCached",
+            ReleaseError::Request(e) if e.is_timeout() => "TimedOut",
+            ReleaseError::Request(_) => "RequestFailed",
+            ReleaseError::RateLimited(_) => "RateLimited",
+            ReleaseError::Status { .. } => "BadStatus",
+            ReleaseError::Parse(_) => "BadResponse",
+            ReleaseError::Interrupted(_) => "Interrupted",
+        }
+    }
+}
+
+impl fmt::Display for ReleaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReleaseError::InvalidRepo(repo) => write!(f, "{:?} isn't a GitHub repository", repo),
+            ReleaseError::NotCached { repo } => {
+                write!(
+                    f,
+                    "Offline, and the releases of {} were never fetched",
+                    repo
+                )
+            }
+            ReleaseError::Request(e) if e.is_timeout() => {
+                write!(f, "Timed out listing releases: {}", e)
+            }
+            ReleaseError::Request(e) => write!(f, "Failed to list releases: {}", e),
+            ReleaseError::RateLimited(rate_limit) => {
+                let wait = rate_limit.reset_at.saturating_sub(unix_now());
+                write!(
+                    f,
+                    "GitHub's rate limit of {} requests an hour is used up; it resets in {} minutes",
+                    rate_limit.limit,
+                    wait.div_ceil(60)
+                )
+            }
+            ReleaseError::Status {
+                status,
+                message: Some(message),
+            } => write!(f, "GitHub answered {}: {}", status, message),
+            ReleaseError::Status {
+                status,
+                message: None,
+            } => write!(f, "GitHub answered {}", status),
+            ReleaseError::Parse(reason) => write!(f, "Couldn't read GitHub's answer: {}", reason),
+            ReleaseError::Interrupted(reason) => {
+                write!(f, "Listing releases stopped unexpectedly: {}", reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReleaseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReleaseError::Request(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// Sent to the frontend the same way as a `JoltError`.
+impl Serialize for ReleaseError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut error = serializer.serialize_struct("ReleaseError", 2)?;
+        error.serialize_field("code", self.code())?;
+        error.serialize_field("message", &self.to_string())?;
+        error.end()
+    }
+}
+
+/// Where a listing came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ListingSource {
+    /// GitHub sent the releases.
+    Fetched,
+    /// GitHub said the cached releases are still current.
+    NotModified,
+    /// The cache, without asking GitHub.
+    Cached,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct ReleaseListing {
+    pub releases: Vec<Release>,
+    pub source: ListingSource,
+    /// When GitHub last gave us (or confirmed) these releases, in seconds since the
+    /// Unix epoch.
+    pub fetched_at: u64,
+    pub rate_limit: Option<RateLimit>,
+    /// Why the cache was served, if it was because asking GitHub failed.
+    pub error: Option<ReleaseError>,
+}
+
+/// What's kept on disk for each repository.
+#[derive(serde::Serialize, Deserialize)]
+struct CachedListing {
+    etag: Option<String>,
+    fetched_at: u64,
+    releases: Vec<Release>,
+}
+
+impl CachedListing {
+    fn into_listing(
+        self,
+        source: ListingSource,
+        rate_limit: Option<RateLimit>,
+        error: Option<ReleaseError>,
+    ) -> ReleaseListing {
+        ReleaseListing {
+            releases: self.releases,
+            source,
+            fetched_at: self.fetched_at,
+            rate_limit,
+            error,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ReleaseClient {
+    /// The GitHub API to talk to; only ever changed for tests.
+    pub api_base: String,
+    pub cache_dir: PathBuf,
+    /// Sent as a bearer token, for the much higher authenticated rate limit.
+    pub token: Option<String>,
+    /// Only ever serve the cache.
+    pub offline: bool,
+}
+
+impl ReleaseClient {
+    pub fn new(cache_dir: PathBuf) -> Self {
+        Self {
+            api_base: GITHUB_API.to_string(),
+            cache_dir,
+            token: None,
+            offline: false,
+        }
+    }
+
+    /// Lists a repository's releases, newest first, falling back to the last listing
+    /// we saw when GitHub can't give us a new one.
+    pub fn list_releases(&self, owner: &str, repo: &str) -> Result<ReleaseListing, ReleaseError> {
+        for name in [owner, repo] {
+            if !is_valid_name(name) {
+                return Err(ReleaseError::InvalidRepo(format!("{}/{}", owner, repo)));
+            }
+        }
+
+        let cache_path = self.cache_dir.join(format!("{}__{}.json", owner, repo));
+        let cached = read_cache(&cache_path);
+
+        if self.offline {
+            return cached
+                .map(|cached| cached.into_listing(ListingSource::Cached, None, None))
+                .ok_or_else(|| ReleaseError::NotCached {
+                    repo: format!("{}/{}", owner, repo),
+                });
+        }
+
+        match self.fetch(owner, repo, cached.as_ref(), &cache_path) {
+            Ok(listing) => Ok(listing),
+            Err(e) => match cached {
+                Some(cached) => {
+                    warn!(owner, repo, error = %e, "Serving cached releases");
+                    let rate_limit = match &e {
+                        ReleaseError::RateLimited(rate_limit) => Some(*rate_limit),
+                        _ => None,
+                    };
+                    Ok(cached.into_listing(ListingSource::Cached, rate_limit, Some(e)))
+                }
+                None => Err(e),
+            },
+        }
+    }
+
+    fn fetch(
+        &self,
+        owner: &str,
+        repo: &str,
+        cached: Option<&CachedListing>,
+        cache_path: &Path,
+    ) -> Result<ReleaseListing, ReleaseError> {
+        let client = reqwest::blocking::Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(REQUEST_TIMEOUT)
+            .user_agent(USER_AGENT)
+            .build()
+            .map_err(ReleaseError::Request)?;
+
+        let url = format!(
+            "{}/repos/{}/{}/releases?per_page={}",
+            self.api_base.trim_end_matches('/'),
+            owner,
+            repo,
+            PER_PAGE
+        );
+        let mut request = client
+            .get(url)
+            .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+            .header("X-GitHub-Api-Version", "2022-11-28");
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        if let Some(etag) = cached.and_then(|cached| cached.etag.as_deref()) {
+            request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+        }
+
+        let response = request.send().map_err(ReleaseError::Request)?;
+        let status = response.status();
+        let rate_limit = RateLimit::from_headers(response.headers());
+
+        if status == reqwest::StatusCode::NOT_MODIFIED {
+            if let Some(cached) = cached {
+                let refreshed = CachedListing {
+                    etag: cached.etag.clone(),
+                    fetched_at: unix_now(),
+                    releases: cached.releases.clone(),
+                };
+                write_cache(cache_path, &refreshed);
+                return Ok(refreshed.into_listing(ListingSource::NotModified, rate_limit, None));
+            }
+        }
+
+        if status.is_success() {
+            let etag = response
+                .headers()
+                .get(reqwest::header::ETAG)
+                .and_then(|etag| etag.to_str().ok())
+                .map(str::to_string);
+            let body = response.bytes().map_err(ReleaseError::Request)?;
+            let releases: Vec<Release> =
+                serde_json::from_slice(&body).map_err(|e| ReleaseError::Parse(e.to_string()))?;
+
+            let listing = CachedListing {
+                etag,
+                fetched_at: unix_now(),
+                releases,
+            };
+            write_cache(cache_path, &listing);
+            return Ok(listing.into_listing(ListingSource::Fetched, rate_limit, None));
+        }
+
+        // GitHub uses 403 for a spent primary limit and 429 for the secondary one.
+        let spent = rate_limit.is_some_and(|rate_limit| rate_limit.remaining == 0);
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS
+            || (status == reqwest::StatusCode::FORBIDDEN && spent)
+        {
+            if let Some(rate_limit) = rate_limit {
+                return Err(ReleaseError::RateLimited(rate_limit));
+            }
+        }
+
+        #[derive(Deserialize)]
+        struct GitHubMessage {
+            message: String,
+        }
+        let message = response
+            .bytes()
+            .ok()
+            .and_then(|body| serde_json::from_slice::<GitHubMessage>(&body).ok())
+            .map(|body| body.message);
+        Err(ReleaseError::Status {
+            status: status.as_u16(),
+            message,
+        })
+    }
+}
+
+/// Whether `name` can be an owner or repository name, and so can go in a URL path
+/// and a filename as it is.
+pub(crate) fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+fn read_cache(path: &Path) -> Option<CachedListing> {
+    let contents = fs::read(path).ok()?;
+    match serde_json::from_slice(&contents) {
+        Ok(cached) => Some(cached),
+        Err(e) => {
+            warn!(?path, error = %e, "Ignoring an unreadable release cache");
+            None
+        }
+    }
+}
+
+/// Saves a listing for next time. Failing to is worth a mention, not an error.
+fn write_cache(path: &Path, listing: &CachedListing) {
+    let write = || -> std::io::Result<()> {
+        let dir = path.parent().unwrap_or(Path::new("."));
+        fs::create_dir_all(dir)?;
+
+        let filename = path.file_name().unwrap_or_default().to_string_lossy();
+        let mut partial = PartialFile::create(dir, &filename)?;
+        serde_json::to_writer(&mut *partial.file(), listing)?;
+        partial.file().flush()?;
+        partial.persist(path)?;
+        Ok(())
+    };
+
+    if let Err(e) = write() {
+        warn!(?path, error = %e, "Failed to cache releases");
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReleaseSettings {
+    /// A GitHub token, for when 60 requests an hour isn't enough.
+    pub github_token: Option<String>,
+    /// Never ask GitHub; only show what's cached.
+    pub offline: bool,
+}
+
+pub fn load_settings(app: &tauri::AppHandle) -> ReleaseSettings {
+    storage::load_setting(app, RELEASES_KEY).unwrap_or_default()
+}
+
+#[tauri::command]
+pub fn get_release_settings(app: tauri::AppHandle) -> ReleaseSettings {
+    load_settings(&app)
+}
+
+#[tauri::command]
+pub fn set_release_settings(
+    app: tauri::AppHandle,
+    mut settings: ReleaseSettings,
+) -> Result<(), String> {
+    settings.github_token = settings
+        .github_token
+        .map(|token| token.trim().to_string())
+        .filter(|token| !token.is_empty());
+
+    storage::save_setting(&app, RELEASES_KEY, &settings)
+}
+
+#[tauri::command]
+pub async fn list_releases(
+    app: tauri::AppHandle,
+    owner: String,
+    repo: String,
+) -> Result<ReleaseListing, ReleaseError> {
+    let settings = load_settings(&app);
+    let cache_dir = storage::cache_dir(&app).join("releases");
+
+    let client = ReleaseClient {
+        token: settings.github_token,
+        offline: settings.offline,
+        ..ReleaseClient::new(cache_dir)
+    };
+
+    // The HTTP client is synchronous, so keep it off the async runtime's threads.
+    tokio::task::spawn_blocking(move || client.list_releases(&owner, &repo))
+        .await
+        .map_err(|e| ReleaseError::Interrupted(e.to_string()))?
+}
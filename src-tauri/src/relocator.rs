@@ -0,0 +1,343 @@
+// Generates the intermezzo relocator from its addresses instead of shipping a
+// prebuilt blob.
+//
+// This emits the same stub fusee-launcher ships, instruction by instruction. It
+// is ARM (A32) code, not Thumb: the bootROM runs on the BPMP in ARM state and
+// returns into the stub through a word-aligned address, so its first instruction
+// has to be an ARM one.
+//
+// What the stub does, in order:
+//
+//   1. Copies everything after its own prologue to `relocation_address`, out of
+//      the way of the payload, and jumps there.
+//   2. Copies `head_length` bytes from `head_source` (the part of the payload
+//      that precedes the stack spray) to `destination`.
+//   3. Copies `tail_length` bytes from `tail_source` (the rest of the payload,
+//      after the spray) to `destination + head_length`.
+//   4. Jumps to `entry_point`.
+
+use std::ops::Range;
+
+use crate::{PAYLOAD_START_ADDR, RCM_PAYLOAD_ADDR, STACK_SPRAY_END, STACK_SPRAY_START};
+
+/// Where the stub moves itself by default, just below the RCM payload area.
+pub const DEFAULT_RELOCATION_ADDR: u32 = 0x4000F000;
+
+/// How much of the payload past the stack spray gets copied by default.
+pub const DEFAULT_TAIL_LENGTH: u32 = 0x30000;
+
+// The stub's layout. The instruction count never changes (constants that don't
+// fit in an immediate come from the literal pool instead), so these are fixed.
+const POST_RELOCATION_OFFSET: u32 = 0x20;
+const COPY_ROUTINE_OFFSET: u32 = 0x50;
+const LITERAL_POOL_OFFSET: u32 = 0x64;
+
+// Registers.
+const R0: u32 = 0;
+const R1: u32 = 1;
+const R2: u32 = 2;
+const LR: u32 = 14;
+
+/// The addresses and lengths the relocator is built around.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RelocatorParams {
+    /// Where the stub itself is loaded (the start of the RCM image).
+    pub load_address: u32,
+    /// Where the stub moves itself before copying the payload.
+    pub relocation_address: u32,
+    /// Where the start of the payload sits in the loaded image.
+    pub head_source: u32,
+    /// How much of the payload precedes the stack spray.
+    pub head_length: u32,
+    /// Where the rest of the payload resumes after the stack spray.
+    pub tail_source: u32,
+    /// How much of the payload follows the stack spray.
+    pub tail_length: u32,
+    /// Where the payload is copied to; this is the address it is linked at.
+    pub destination: u32,
+    /// Where the stub jumps once the payload is in place.
+    pub entry_point: u32,
+}
+
+impl Default for RelocatorParams {
+    /// The layout `build_payload` produces, for payloads linked at 0x40010000.
+    fn default() -> Self {
+        Self {
+            load_address: RCM_PAYLOAD_ADDR,
+            relocation_address: DEFAULT_RELOCATION_ADDR,
+            head_source: PAYLOAD_START_ADDR,
+            head_length: STACK_SPRAY_START - PAYLOAD_START_ADDR,
+            tail_source: STACK_SPRAY_END,
+            tail_length: DEFAULT_TAIL_LENGTH,
+            destination: RCM_PAYLOAD_ADDR,
+            entry_point: RCM_PAYLOAD_ADDR,
+        }
+    }
+}
+
+impl RelocatorParams {
+    /// The default layout, for a `payload_length`-byte payload linked at (and
+    /// entered through) `address`. Only as much of the tail as the payload has is
+    /// copied, so the copy doesn't run into memory it is about to overwrite.
+    pub fn linked_at(address: u32, payload_length: u32) -> Self {
+        let defaults = Self::default();
+        let tail_length = payload_length
+            .saturating_sub(defaults.head_length)
+            .next_multiple_of(4)
+            .max(4);
+
+        Self {
+            tail_length,
+            destination: address,
+            entry_point: address,
+            ..defaults
+        }
+    }
+}
+
+/// Assembles the relocator for the given parameters.
+pub fn assemble(params: &RelocatorParams) -> Result<Vec<u8>, String> {
+    validate(params)?;
+
+    let mut stub = Assembler::default();
+
+    // Move everything after this prologue out of the way, then continue there.
+    stub.load_constant(R0, params.relocation_address);
+    stub.load_constant(R1, params.load_address.wrapping_add(POST_RELOCATION_OFFSET));
+    let end_of_stub = stub.reserve_literal(R2);
+    stub.emit(0xE0422001); // sub r2, r2, r1
+    stub.branch_link(COPY_ROUTINE_OFFSET);
+    stub.load_constant(R0, params.relocation_address);
+    stub.branch_exchange(R0);
+    stub.emit(0xE1A00000); // nop
+    debug_assert_eq!(stub.offset(), POST_RELOCATION_OFFSET);
+
+    // Copy the part of the payload that precedes the stack spray...
+    stub.load_constant(R0, params.destination);
+    stub.load_constant(R1, params.head_source);
+    stub.load_constant(R2, params.head_length);
+    stub.branch_link(COPY_ROUTINE_OFFSET);
+
+    // ... and the part that follows it, right after the first part.
+    stub.load_constant(R0, params.destination);
+    stub.load_constant(R1, params.head_length);
+    stub.emit(0xE0800001); // add r0, r0, r1
+    stub.load_constant(R1, params.tail_source);
+    stub.load_constant(R2, params.tail_length);
+    stub.branch_link(COPY_ROUTINE_OFFSET);
+
+    // Jump into the payload.
+    stub.load_constant(R0, params.entry_point);
+    stub.branch_exchange(R0);
+    debug_assert_eq!(stub.offset(), COPY_ROUTINE_OFFSET);
+
+    // copy(r0 = destination, r1 = source, r2 = length), a word at a time.
+    stub.emit(0xE4913004); // ldr r3, [r1], #4
+    stub.emit(0xE4803004); // str r3, [r0], #4
+    stub.emit(0xE2522004); // subs r2, r2, #4
+    stub.emit(0x1AFFFFFB); // bne copy
+    stub.branch_exchange(LR);
+    debug_assert_eq!(stub.offset(), LITERAL_POOL_OFFSET);
+
+    // Now that the pool is complete, we know where the stub ends.
+    let size = stub.size();
+    stub.set_literal(end_of_stub, params.load_address.wrapping_add(size));
+
+    // The relocated copy has to survive the payload being copied into place, and
+    // must not land on the parts of the payload that haven't been copied yet.
+    let relocated = span(
+        params.relocation_address,
+        (size - POST_RELOCATION_OFFSET) as u64,
+    );
+    let regions = [
+        (
+            "payload destination",
+            span(
+                params.destination,
+                params.head_length as u64 + params.tail_length as u64,
+            ),
+        ),
+        (
+            "head source",
+            span(params.head_source, params.head_length as u64),
+        ),
+        (
+            "tail source",
+            span(params.tail_source, params.tail_length as u64),
+        ),
+    ];
+    for (name, region) in regions {
+        if overlaps(&relocated, &region) {
+            return Err(format!(
+                "The relocator can't be moved to 0x{:08X}: it would overlap the {}",
+                params.relocation_address, name
+            ));
+        }
+    }
+
+    Ok(stub.finish())
+}
+
+fn validate(params: &RelocatorParams) -> Result<(), String> {
+    let addresses = [
+        ("load address", params.load_address),
+        ("relocation address", params.relocation_address),
+        ("head source", params.head_source),
+        ("tail source", params.tail_source),
+        ("destination", params.destination),
+        ("entry point", params.entry_point),
+    ];
+    for (name, address) in addresses {
+        if address % 4 != 0 {
+            return Err(format!(
+                "The relocator's {} (0x{:08X}) is not word-aligned",
+                name, address
+            ));
+        }
+    }
+
+    // The copy loop only stops when the count hits zero exactly.
+    let lengths = [
+        ("head length", params.head_length),
+        ("tail length", params.tail_length),
+    ];
+    for (name, length) in lengths {
+        if length == 0 || length % 4 != 0 {
+            return Err(format!(
+                "The relocator's {} (0x{:X}) must be a non-zero multiple of 4",
+                name, length
+            ));
+        }
+    }
+
+    // The copies run forwards, head first, so neither may clobber source data it
+    // hasn't read yet.
+    let head_destination = params.destination as u64;
+    let tail_destination = head_destination + params.head_length as u64;
+    let head_source = span(params.head_source, params.head_length as u64);
+    let tail_source = span(params.tail_source, params.tail_length as u64);
+
+    let clobbers_head = head_destination > head_source.start && head_destination < head_source.end;
+    let clobbers_tail = (tail_destination > tail_source.start
+        && tail_destination < tail_source.end)
+        || overlaps(&(head_destination..tail_destination), &tail_source);
+    if clobbers_head || clobbers_tail {
+        return Err(format!(
+            "Copying the payload to 0x{:08X} would overwrite it before it is copied",
+            params.destination
+        ));
+    }
+
+    Ok(())
+}
+
+fn overlaps(a: &Range<u64>, b: &Range<u64>) -> bool {
+    a.start < b.end && b.start < a.end
+}
+
+fn span(start: u32, length: u64) -> Range<u64> {
+    start as u64..start as u64 + length
+}
+
+/// Encodes `value` as an ARM rotated immediate, if it can be. Like GNU as, this
+/// picks the smallest rotation that works.
+fn encode_immediate(value: u32) -> Option<u32> {
+    (0..16).find_map(|rotation| {
+        let imm8 = value.rotate_left(rotation * 2);
+        (imm8 <= 0xFF).then_some((rotation << 8) | imm8)
+    })
+}
+
+/// Emits ARM instructions, collecting `ldr rd, =constant` values into a literal
+/// pool placed right after the code.
+#[derive(Default)]
+struct Assembler {
+    code: Vec<u32>,
+    pool: Vec<Literal>,
+    // (instruction index, pool entry) for every literal load.
+    loads: Vec<(usize, usize)>,
+}
+
+struct Literal {
+    value: u32,
+    // Reserved entries are filled in later, so they can't be shared.
+    shared: bool,
+}
+
+impl Assembler {
+    fn offset(&self) -> u32 {
+        (self.code.len() * 4) as u32
+    }
+
+    fn size(&self) -> u32 {
+        self.offset() + (self.pool.len() * 4) as u32
+    }
+
+    fn emit(&mut self, instruction: u32) {
+        self.code.push(instruction);
+    }
+
+    /// mov rd, #value if it fits in an immediate, otherwise ldr rd, =value.
+    fn load_constant(&mut self, rd: u32, value: u32) {
+        match encode_immediate(value) {
+            Some(immediate) => self.emit(0xE3A00000 | (rd << 12) | immediate),
+            None => {
+                let entry = self
+                    .pool
+                    .iter()
+                    .position(|literal| literal.shared && literal.value == value)
+                    .unwrap_or_else(|| self.add_literal(value, true));
+                self.load_literal(rd, entry);
+            }
+        }
+    }
+
+    /// ldr rd, =<value set later with `set_literal`>.
+    fn reserve_literal(&mut self, rd: u32) -> usize {
+        let entry = self.add_literal(0, false);
+        self.load_literal(rd, entry);
+        entry
+    }
+
+    fn set_literal(&mut self, entry: usize, value: u32) {
+        self.pool[entry].value = value;
+    }
+
+    fn add_literal(&mut self, value: u32, shared: bool) -> usize {
+        self.pool.push(Literal { value, shared });
+        self.pool.len() - 1
+    }
+
+    fn load_literal(&mut self, rd: u32, entry: usize) {
+        self.loads.push((self.code.len(), entry));
+        self.emit(0xE59F0000 | (rd << 12)); // ldr rd, [pc, #offset]
+    }
+
+    /// bl to an offset within the stub.
+    fn branch_link(&mut self, target: u32) {
+        let displacement = (target as i32 - (self.offset() as i32 + 8)) >> 2;
+        self.emit(0xEB000000 | (displacement as u32 & 0x00FF_FFFF));
+    }
+
+    /// bx rm
+    fn branch_exchange(&mut self, rm: u32) {
+        self.emit(0xE12FFF10 | rm);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        // Point every literal load at its pool entry, relative to pc (two
+        // instructions ahead).
+        let pool_start = self.offset();
+        for &(index, entry) in &self.loads {
+            let pc = index as u32 * 4 + 8;
+            self.code[index] |= pool_start + entry as u32 * 4 - pc;
+        }
+
+        self.code
+            .iter()
+            .copied()
+            .chain(self.pool.iter().map(|literal| literal.value))
+            .flat_map(u32::to_le_bytes)
+            .collect()
+    }
+}
@@ -0,0 +1,71 @@
+// What happened during an injection, in a form that can be logged and compared.
+
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::verify::Verification;
+use crate::ChipInfo;
+
+/// How the oversized GET_STATUS request finished.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TriggerOutcome {
+    /// The request timed out: the device crashed into our payload, as expected.
+    TimedOut,
+    /// The request completed normally. This isn't supposed to happen, but the
+    /// payload may still be running.
+    Returned,
+    /// The request was handed to the kernel, which doesn't wait for it to finish.
+    Submitted,
+    /// One of the legacy strategies stopped getting answers from the device, which
+    /// it takes to mean the device crashed.
+    Unresponsive,
+}
+
+/// How long each phase of the injection took, in milliseconds.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PhaseTimings {
+    pub read_device_id_ms: f64,
+    pub build_payload_ms: f64,
+    pub upload_ms: f64,
+    pub switch_to_highbuf_ms: f64,
+    pub trigger_ms: f64,
+    pub total_ms: f64,
+}
+
+/// The outcome of a successful injection.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InjectionReport {
+    /// The chip we injected, if we know it: read during this injection, or earlier
+    /// in the same RCM session.
+    pub chip_info: Option<ChipInfo>,
+    /// Set when we ended up without the chip: the device ID couldn't be read, and
+    /// nothing had read it earlier in this RCM session.
+    pub device_id_read_failed: bool,
+    pub payload_path: String,
+    pub payload_sha256: String,
+    pub payload_size: usize,
+    /// The size of the RCM command we sent, including the stack spray and padding.
+    pub rcm_stream_size: usize,
+    /// The number of 0x1000-byte buffers written, including any high-buffer padding.
+    pub buffers_written: usize,
+    /// Whether an extra buffer had to be sent to land on the high DMA buffer.
+    pub padded_to_high_buffer: bool,
+    pub trigger: TriggerOutcome,
+    /// The ID of the strategy that delivered the payload.
+    pub strategy: String,
+    pub timings: PhaseTimings,
+    /// Whether the payload looks to be running, from watching the bus afterwards.
+    pub verification: Verification,
+}
+
+pub(crate) fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Seconds since the Unix epoch, for timestamps that get stored.
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
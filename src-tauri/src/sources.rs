@@ -0,0 +1,356 @@
+// Where payloads come from.
+//
+// A source is a repository whose releases carry a payload (or an archive with one
+// inside), or a plain URL for payloads that aren't published that way. jolt ships
+// with sources for the usual payloads; users can remove those and add their own,
+// e.g. a team's internal builds.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tracing::warn;
+
+use crate::archive::glob_matches;
+use crate::releases::{
+    self, ListingSource, RateLimit, Release, ReleaseAsset, ReleaseClient, ReleaseError,
+};
+use crate::storage;
+
+const SOURCES_KEY: &str = "sources";
+
+/// Where a source's releases are found.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SourceLocation {
+    /// A repository's releases, on GitHub or anything with the same API (GitHub
+    /// Enterprise, Gitea, Forgejo).
+    Repo {
+        owner: String,
+        repo: String,
+        /// The API to ask instead of GitHub's, e.g. "https://git.example.com/api/v1".
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        api_base: Option<String>,
+    },
+    /// A single file that's always the latest build.
+    Url { url: String },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PayloadSource {
+    /// Unique within the catalog.
+    pub id: String,
+    pub name: String,
+    pub location: SourceLocation,
+    /// Which release asset to download, e.g. "hekate_ctcaer_*.zip".
+    pub asset_pattern: String,
+    /// Which file to take out of the asset, if it's an archive.
+    #[serde(default)]
+    pub archive_member: Option<String>,
+    /// The release asset listing the other assets' SHA-256s, e.g. "SHA256SUMS".
+    #[serde(default)]
+    pub checksum_asset: Option<String>,
+}
+
+/// A release of a source, narrowed down to the one asset we'd download.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct SourceRelease {
+    pub name: Option<String>,
+    pub tag_name: String,
+    pub published_at: Option<String>,
+    pub prerelease: bool,
+    pub asset: ReleaseAsset,
+    /// Where to find the asset's checksum, if the source publishes one.
+    pub checksum_url: Option<String>,
+}
+
+/// A source's releases, or why we couldn't get them.
+#[derive(Debug, Serialize)]
+pub struct SourceListing {
+    pub source_id: String,
+    pub releases: Vec<SourceRelease>,
+    pub listed_from: Option<ListingSource>,
+    pub fetched_at: Option<u64>,
+    pub rate_limit: Option<RateLimit>,
+    pub error: Option<ReleaseError>,
+}
+
+fn repo_source(
+    id: &str,
+    name: &str,
+    owner: &str,
+    repo: &str,
+    asset_pattern: &str,
+    archive_member: Option<&str>,
+) -> PayloadSource {
+    PayloadSource {
+        id: id.to_string(),
+        name: name.to_string(),
+        location: SourceLocation::Repo {
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            api_base: None,
+        },
+        asset_pattern: asset_pattern.to_string(),
+        archive_member: archive_member.map(str::to_string),
+        checksum_asset: None,
+    }
+}
+
+/// The sources jolt starts out with.
+pub fn default_sources() -> Vec<PayloadSource> {
+    vec![
+        repo_source(
+            "hekate",
+            "hekate",
+            "CTCaer",
+            "hekate",
+            "hekate_ctcaer_*.zip",
+            Some("hekate_ctcaer_*.bin"),
+        ),
+        repo_source(
+            "atmosphere-fusee",
+            "Atmosphère (fusee)",
+            "Atmosphere-NX",
+            "Atmosphere",
+            "fusee.bin",
+            None,
+        ),
+        repo_source(
+            "lockpick-rcm",
+            "Lockpick_RCM",
+            "shchmue",
+            "Lockpick_RCM",
+            "Lockpick_RCM*.bin",
+            None,
+        ),
+        repo_source(
+            "tegraexplorer",
+            "TegraExplorer",
+            "suchmememanyskill",
+            "TegraExplorer",
+            "TegraExplorer*.bin",
+            None,
+        ),
+    ]
+}
+
+impl PayloadSource {
+    /// Checks the source makes sense before it goes in the catalog.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.id.trim().is_empty() {
+            return Err("A source needs an ID".to_string());
+        }
+        // The ID names the source's cache directory.
+        if !releases::is_valid_name(&self.id) {
+            return Err(format!(
+                "{:?} can't be a source ID; use letters, digits, '-', '_' and '.'",
+                self.id
+            ));
+        }
+        if self.asset_pattern.trim().is_empty() {
+            return Err(format!("{} needs an asset pattern", self.id));
+        }
+
+        match &self.location {
+            SourceLocation::Repo {
+                owner,
+                repo,
+                api_base,
+            } => {
+                if !releases::is_valid_name(owner) || !releases::is_valid_name(repo) {
+                    return Err(format!("{}/{} isn't a repository", owner, repo));
+                }
+                if let Some(api_base) = api_base {
+                    check_url(api_base)?;
+                }
+            }
+            SourceLocation::Url { url } => check_url(url)?,
+        }
+        Ok(())
+    }
+
+    /// The release's asset this source would download, and its checksum's URL.
+    pub fn select(&self, release: &Release) -> Option<SourceRelease> {
+        let asset = release
+            .assets
+            .iter()
+            .find(|asset| glob_matches(&self.asset_pattern, &asset.name))?;
+        let checksum_url = self.checksum_asset.as_ref().and_then(|checksum| {
+            release
+                .assets
+                .iter()
+                .find(|asset| asset.name == *checksum)
+                .map(|asset| asset.browser_download_url.clone())
+        });
+
+        Some(SourceRelease {
+            name: release.name.clone(),
+            tag_name: release.tag_name.clone(),
+            published_at: release.published_at.clone(),
+            prerelease: release.prerelease,
+            asset: asset.clone(),
+            checksum_url,
+        })
+    }
+
+    /// Lists the source's releases that have something to download.
+    pub fn list(&self, cache_dir: &Path, settings: &releases::ReleaseSettings) -> SourceListing {
+        let mut listing = SourceListing {
+            source_id: self.id.clone(),
+            releases: Vec::new(),
+            listed_from: None,
+            fetched_at: None,
+            rate_limit: None,
+            error: None,
+        };
+
+        match &self.location {
+            SourceLocation::Repo {
+                owner,
+                repo,
+                api_base,
+            } => {
+                let mut client = ReleaseClient {
+                    offline: settings.offline,
+                    ..ReleaseClient::new(cache_dir.to_path_buf())
+                };
+                match api_base {
+                    // The token is for GitHub; don't hand it to anyone else. Keep
+                    // other servers' listings apart, in case names clash.
+                    Some(api_base) => {
+                        client.api_base = api_base.clone();
+                        client.cache_dir = cache_dir.join(&self.id);
+                    }
+                    None => client.token = settings.github_token.clone(),
+                }
+
+                match client.list_releases(owner, repo) {
+                    Ok(releases) => {
+                        listing.releases = releases
+                            .releases
+                            .iter()
+                            .filter_map(|release| self.select(release))
+                            .collect();
+                        listing.listed_from = Some(releases.source);
+                        listing.fetched_at = Some(releases.fetched_at);
+                        listing.rate_limit = releases.rate_limit;
+                        listing.error = releases.error;
+                    }
+                    Err(e) => listing.error = Some(e),
+                }
+            }
+            SourceLocation::Url { url } => listing.releases.push(self.url_release(url)),
+        }
+
+        listing
+    }
+
+    /// A URL source has one "release": whatever is at the URL right now.
+    fn url_release(&self, url: &str) -> SourceRelease {
+        let path = url.split(['?', '#']).next().unwrap_or(url);
+        let (base, file_name) = path.rsplit_once('/').unwrap_or(("", path));
+
+        SourceRelease {
+            name: Some(self.name.clone()),
+            tag_name: "latest".to_string(),
+            published_at: None,
+            prerelease: false,
+            asset: ReleaseAsset {
+                id: 0,
+                name: file_name.to_string(),
+                browser_download_url: url.to_string(),
+                size: 0,
+            },
+            checksum_url: self
+                .checksum_asset
+                .as_ref()
+                .map(|checksum| format!("{}/{}", base, checksum)),
+        }
+    }
+}
+
+fn check_url(url: &str) -> Result<(), String> {
+    if url.starts_with("https://") || url.starts_with("http://") {
+        Ok(())
+    } else {
+        Err(format!("{:?} isn't an http(s) URL", url))
+    }
+}
+
+/// The catalog, which is the defaults until the user first changes it.
+pub fn load_sources(app: &tauri::AppHandle) -> Vec<PayloadSource> {
+    storage::load_setting(app, SOURCES_KEY)
+        .map(|mut sources: Vec<PayloadSource>| {
+            // Saved before we checked as much, maybe.
+            sources.retain(|source| match source.validate() {
+                Ok(()) => true,
+                Err(e) => {
+                    warn!(source = %source.id, error = %e, "Ignoring an invalid payload source");
+                    false
+                }
+            });
+            sources
+        })
+        .unwrap_or_else(default_sources)
+}
+
+#[tauri::command]
+pub fn list_sources(app: tauri::AppHandle) -> Vec<PayloadSource> {
+    load_sources(&app)
+}
+
+/// Adds a source, or replaces the one with the same ID.
+#[tauri::command]
+pub fn add_source(
+    app: tauri::AppHandle,
+    source: PayloadSource,
+) -> Result<Vec<PayloadSource>, String> {
+    source.validate()?;
+
+    let mut sources = load_sources(&app);
+    match sources.iter_mut().find(|existing| existing.id == source.id) {
+        Some(existing) => *existing = source,
+        None => sources.push(source),
+    }
+    storage::save_setting(&app, SOURCES_KEY, &sources)?;
+    Ok(sources)
+}
+
+#[tauri::command]
+pub fn remove_source(app: tauri::AppHandle, id: String) -> Result<Vec<PayloadSource>, String> {
+    let mut sources = load_sources(&app);
+    sources.retain(|source| source.id != id);
+    storage::save_setting(&app, SOURCES_KEY, &sources)?;
+    Ok(sources)
+}
+
+/// Brings the catalog back to what jolt ships with.
+#[tauri::command]
+pub fn reset_sources(app: tauri::AppHandle) -> Result<Vec<PayloadSource>, String> {
+    let sources = default_sources();
+    storage::save_setting(&app, SOURCES_KEY, &sources)?;
+    Ok(sources)
+}
+
+/// Lists the releases of the sources with the given IDs, or of all of them.
+#[tauri::command]
+pub async fn refresh_sources(
+    app: tauri::AppHandle,
+    ids: Option<Vec<String>>,
+) -> Result<Vec<SourceListing>, ReleaseError> {
+    let settings = releases::load_settings(&app);
+    let cache_dir = storage::cache_dir(&app).join("releases");
+    let sources: Vec<PayloadSource> = load_sources(&app)
+        .into_iter()
+        .filter(|source| ids.as_ref().is_none_or(|ids| ids.contains(&source.id)))
+        .collect();
+
+    // The HTTP client is synchronous, so keep it off the async runtime's threads.
+    tokio::task::spawn_blocking(move || {
+        sources
+            .iter()
+            .map(|source| source.list(&cache_dir, &settings))
+            .collect()
+    })
+    .await
+    .map_err(|e| ReleaseError::Interrupted(e.to_string()))
+}
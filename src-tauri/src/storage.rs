@@ -0,0 +1,244 @@
+// Where jolt keeps its files.
+//
+// Normally that's the usual per-user directories: settings and the payload
+// library in the app data directory, release listings in the cache directory,
+// logs in the log directory. In portable mode, for jolt on a USB stick,
+// everything goes in a `jolt-data` directory next to the executable instead, so
+// nothing is left on the machine it runs on. Portable mode is on if that
+// directory exists, or if `JOLT_PORTABLE` is set.
+//
+// The library can be moved anywhere, e.g. to a drive with more room. A relative
+// location is relative to the data directory, so a portable library moves with
+// the stick.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use tauri::Manager;
+use tauri_plugin_store::StoreExt;
+use tracing::{info, warn};
+
+use crate::library::{self, LibraryError, PayloadLibrary};
+
+/// The store file jolt keeps its settings in.
+pub const SETTINGS_STORE: &str = "settings.json";
+const STORAGE_KEY: &str = "storage";
+
+/// The directory next to the executable that turns on portable mode.
+pub const PORTABLE_DIR: &str = "jolt-data";
+/// Turns on portable mode without the directory having to exist yet.
+pub const PORTABLE_ENV: &str = "JOLT_PORTABLE";
+
+/// The resolved directories, managed as app state.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Storage {
+    pub portable: bool,
+    /// Where settings and, by default, the library go.
+    pub data_dir: PathBuf,
+    pub cache_dir: PathBuf,
+    pub log_dir: PathBuf,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StorageSettings {
+    /// Where the library is, if not in the default place.
+    pub library_dir: Option<String>,
+}
+
+/// Where everything is, for the frontend.
+#[derive(Clone, Debug, Serialize)]
+pub struct StorageInfo {
+    #[serde(flatten)]
+    pub storage: Storage,
+    pub library_dir: PathBuf,
+    pub default_library_dir: PathBuf,
+}
+
+impl Storage {
+    /// Portable storage rooted at `data_dir`.
+    pub fn portable(data_dir: PathBuf) -> Self {
+        Self {
+            portable: true,
+            cache_dir: data_dir.join("cache"),
+            log_dir: data_dir.join("logs"),
+            data_dir,
+        }
+    }
+
+    /// Works out where everything goes for this run.
+    pub fn resolve(app: &tauri::AppHandle) -> tauri::Result<Self> {
+        if let Some(data_dir) = portable_dir() {
+            if let Err(e) = fs::create_dir_all(&data_dir) {
+                warn!(?data_dir, error = %e, "Failed to create the portable data directory");
+            }
+            return Ok(Self::portable(data_dir));
+        }
+
+        Ok(Self {
+            portable: false,
+            data_dir: app.path().app_data_dir()?,
+            cache_dir: app.path().app_cache_dir()?,
+            log_dir: app.path().app_log_dir()?,
+        })
+    }
+
+    /// The settings store, as the store plugin wants it: relative paths are in
+    /// the app data directory, which portable mode doesn't use.
+    pub fn settings_store(&self) -> PathBuf {
+        if self.portable {
+            self.data_dir.join(SETTINGS_STORE)
+        } else {
+            PathBuf::from(SETTINGS_STORE)
+        }
+    }
+
+    pub fn default_library_dir(&self) -> PathBuf {
+        self.data_dir.join("payloads")
+    }
+
+    /// Where the settings put the library.
+    pub fn library_dir(&self, settings: &StorageSettings) -> PathBuf {
+        match settings.library_dir.as_deref().map(str::trim) {
+            // Joining an absolute path replaces the data directory.
+            Some(dir) if !dir.is_empty() => self.data_dir.join(dir),
+            _ => self.default_library_dir(),
+        }
+    }
+}
+
+/// The portable data directory, if jolt is running in portable mode.
+pub fn portable_dir() -> Option<PathBuf> {
+    let exe = std::env::current_exe().ok()?;
+    let data_dir = exe.parent()?.join(PORTABLE_DIR);
+    (data_dir.is_dir() || std::env::var_os(PORTABLE_ENV).is_some()).then_some(data_dir)
+}
+
+/// Where libraries were before they could be moved. Downloads always went here.
+pub fn legacy_library_dir() -> Option<PathBuf> {
+    dirs::download_dir().map(|downloads| downloads.join("payloads"))
+}
+
+/// The settings store to open. Everything that keeps settings goes through this.
+pub fn settings_store(app: &tauri::AppHandle) -> PathBuf {
+    app.state::<Storage>().settings_store()
+}
+
+pub fn cache_dir(app: &tauri::AppHandle) -> PathBuf {
+    app.state::<Storage>().cache_dir.clone()
+}
+
+/// Reads the setting under `key` from the settings store. One that's missing, or
+/// that can't be read, is `None`.
+pub fn load_setting<T: DeserializeOwned>(app: &tauri::AppHandle, key: &str) -> Option<T> {
+    let stored = match app.store(settings_store(app)) {
+        Ok(store) => store.get(key),
+        Err(e) => {
+            warn!(store = SETTINGS_STORE, error = %e, "Failed to open the settings store");
+            None
+        }
+    };
+
+    stored.and_then(|value| match serde_json::from_value(value) {
+        Ok(setting) => Some(setting),
+        Err(e) => {
+            warn!(key, error = %e, "Ignoring an unreadable setting");
+            None
+        }
+    })
+}
+
+/// Writes the setting under `key` to the settings store.
+pub fn save_setting<T: Serialize + ?Sized>(
+    app: &tauri::AppHandle,
+    key: &str,
+    setting: &T,
+) -> Result<(), String> {
+    let store = app.store(settings_store(app)).map_err(|e| e.to_string())?;
+    let value = serde_json::to_value(setting).map_err(|e| e.to_string())?;
+    store.set(key, value);
+    store.save().map_err(|e| e.to_string())
+}
+
+pub fn load_settings(app: &tauri::AppHandle) -> StorageSettings {
+    load_setting(app, STORAGE_KEY).unwrap_or_default()
+}
+
+/// Where the library is this run.
+pub fn library_dir(app: &tauri::AppHandle) -> PathBuf {
+    app.state::<Storage>().library_dir(&load_settings(app))
+}
+
+/// Fills a library that's just been created from wherever payloads were before:
+/// the frontend's `payloads.dat`, and the old library in the downloads folder.
+/// Both belong to the machine rather than the stick, so a portable library starts
+/// empty instead.
+pub(crate) fn migrate_legacy_library(app: &tauri::AppHandle, library: &PayloadLibrary) {
+    if !library.is_new() {
+        return;
+    }
+
+    let portable = app.state::<Storage>().portable;
+    if let Some(legacy_dir) = legacy_library_dir().filter(|_| !portable) {
+        library::migrate_payloads_dat(app, library, &legacy_dir);
+        if legacy_dir != library.dir() {
+            match library.adopt(&legacy_dir) {
+                Ok(moved) if moved > 0 => {
+                    info!(moved, from = ?legacy_dir, "Moved payloads out of the old library")
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    warn!(from = ?legacy_dir, error = %e, "Failed to move payloads out of the old library")
+                }
+            }
+        }
+        for path in library.left_behind(&legacy_dir) {
+            warn!(?path, "Not adopting a file from the old library");
+        }
+    }
+
+    if let Err(e) = library.create() {
+        warn!(error = %e, "Failed to create the payload library");
+    }
+}
+
+fn storage_info(app: &tauri::AppHandle, library: &PayloadLibrary) -> StorageInfo {
+    let storage = app.state::<Storage>().inner().clone();
+    StorageInfo {
+        library_dir: library.dir(),
+        default_library_dir: storage.default_library_dir(),
+        storage,
+    }
+}
+
+#[tauri::command]
+pub fn get_storage_info(
+    app: tauri::AppHandle,
+    library: tauri::State<'_, PayloadLibrary>,
+) -> StorageInfo {
+    storage_info(&app, &library)
+}
+
+/// Moves the library, payloads and all. `None` moves it back to the default place.
+#[tauri::command]
+pub fn set_library_dir(
+    app: tauri::AppHandle,
+    library: tauri::State<'_, PayloadLibrary>,
+    path: Option<String>,
+) -> Result<StorageInfo, LibraryError> {
+    let settings = StorageSettings {
+        library_dir: path.filter(|path| !path.trim().is_empty()),
+    };
+    let old_dir = library.dir();
+    let new_dir = app.state::<Storage>().library_dir(&settings);
+
+    library.move_to(&new_dir)?;
+    // A library that moved without the settings knowing would be lost next run.
+    if let Err(e) = save_setting(&app, STORAGE_KEY, &settings) {
+        library.move_to(&old_dir)?;
+        return Err(LibraryError::Io(io::Error::other(e)));
+    }
+    Ok(storage_info(&app, &library))
+}
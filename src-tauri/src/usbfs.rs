@@ -0,0 +1,146 @@
+// Linux usbfs support for the Fusée Gelée trigger.
+//
+// libusb (and the kernel underneath it) is free to split or cap large control
+// transfers, which is exactly what we don't want when smashing the stack. Instead
+// we hand a single control URB straight to usbfs with the SUBMITURB ioctl, the
+// same way fusee-launcher's LinuxBackend does.
+
+use std::fs::OpenOptions;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+// Size of the setup packet that usbfs expects at the start of a control buffer.
+const SETUP_PACKET_SIZE: usize = 8;
+
+// usbfs URB type for control transfers.
+const USBDEVFS_URB_TYPE_CONTROL: u8 = 2;
+
+// _IOR('U', 10, struct usbdevfs_urb)
+const IOCTL_IOR: u32 = 0x8000_0000;
+const IOCTL_TYPE: u32 = b'U' as u32;
+const IOCTL_NR_SUBMIT_URB: u32 = 10;
+
+// Host controller drivers known to pass our oversized control request through.
+// We assume a whole bus is owned by a single host controller.
+const SUPPORTED_USB_CONTROLLERS: [&str; 2] = ["pci/drivers/xhci_hcd", "platform/drivers/dwc_otg"];
+
+/// Mirror of the kernel's `struct usbdevfs_urb` (without the trailing ISO descriptors).
+#[repr(C)]
+struct SubmitUrbIoctl {
+    urb_type: libc::c_uchar,
+    endpoint: libc::c_uchar,
+    status: libc::c_int,
+    flags: libc::c_uint,
+    buffer: *mut libc::c_void,
+    buffer_length: libc::c_int,
+    actual_length: libc::c_int,
+    start_frame: libc::c_int,
+    stream_id: libc::c_uint,
+    error_count: libc::c_int,
+    signr: libc::c_uint,
+    usercontext: *mut libc::c_void,
+}
+
+fn submit_urb_ioctl_number() -> u32 {
+    IOCTL_IOR
+        | (std::mem::size_of::<SubmitUrbIoctl>() as u32) << 16
+        | IOCTL_TYPE << 8
+        | IOCTL_NR_SUBMIT_URB
+}
+
+/// Checks that the given bus is driven by a host controller that can issue our
+/// oversized control request -- typically xhci_hcd on most PCs.
+pub(crate) fn validate_environment(bus_number: u8) -> Result<(), String> {
+    for hci_name in SUPPORTED_USB_CONTROLLERS {
+        let driver_dir = Path::new("/sys/bus").join(hci_name);
+        let Ok(controllers) = std::fs::read_dir(&driver_dir) else {
+            continue;
+        };
+
+        // Search each device bound to the driver for the bus our device is on...
+        for controller in controllers.flatten() {
+            let Ok(nodes) = std::fs::read_dir(controller.path()) else {
+                continue;
+            };
+
+            for node in nodes.flatten() {
+                if !node.file_name().to_string_lossy().starts_with("usb") {
+                    continue;
+                }
+
+                if node_matches_bus(&node.path(), bus_number) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    Err("This device needs to be on a supported backend. Usually that means plugged into a blue/USB 3.0 port!".to_string())
+}
+
+fn node_matches_bus(path: &Path, bus_number: u8) -> bool {
+    // If this isn't a valid USB device node, it's not what we're looking for.
+    match std::fs::read_to_string(path.join("busnum")) {
+        Ok(contents) => contents.trim().parse::<u8>().ok() == Some(bus_number),
+        Err(_) => false,
+    }
+}
+
+/// Submits a device-to-host control request directly through usbfs.
+///
+/// The URB is never reaped: a successful smash crashes the device, so the request
+/// never completes. Closing the file descriptor lets the kernel clean it up.
+pub(crate) fn submit_control_request(
+    bus_number: u8,
+    address: u8,
+    request_type: u8,
+    request: u8,
+    value: u16,
+    index: u16,
+    length: u16,
+) -> std::io::Result<()> {
+    // Figure out the USB device file we're going to use to issue the control request.
+    let path = format!("/dev/bus/usb/{:03}/{:03}", bus_number, address);
+    let device = OpenOptions::new().read(true).write(true).open(path)?;
+
+    // Build the setup packet, followed by room for the data stage.
+    let mut buffer = vec![0u8; SETUP_PACKET_SIZE + length as usize];
+    buffer[0] = request_type;
+    buffer[1] = request;
+    buffer[2..4].copy_from_slice(&value.to_le_bytes());
+    buffer[4..6].copy_from_slice(&index.to_le_bytes());
+    buffer[6..8].copy_from_slice(&length.to_le_bytes());
+
+    let mut urb = SubmitUrbIoctl {
+        urb_type: USBDEVFS_URB_TYPE_CONTROL,
+        endpoint: 0,
+        status: 0,
+        flags: 0,
+        buffer: buffer.as_mut_ptr() as *mut libc::c_void,
+        buffer_length: buffer.len() as libc::c_int,
+        actual_length: 0,
+        start_frame: 0,
+        stream_id: 0,
+        error_count: 0,
+        signr: 0,
+        usercontext: std::ptr::null_mut(),
+    };
+
+    // Manually submit the URB to the kernel, so it issues our 'evil' control request.
+    // SAFETY: `urb` matches the kernel's usbdevfs_urb layout and `buffer` outlives the
+    // file descriptor, which is the only thing that can still reference it.
+    let result = unsafe {
+        libc::ioctl(
+            device.as_raw_fd(),
+            submit_urb_ioctl_number() as _,
+            &mut urb as *mut SubmitUrbIoctl,
+        )
+    };
+
+    if result < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    drop(device);
+    Ok(())
+}
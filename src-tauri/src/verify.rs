@@ -0,0 +1,339 @@
+// Checking that an injection actually took.
+//
+// The trigger timing out is what success looks like, but it's also what a wedged
+// device looks like. So after the smash we watch the bus for a while: a payload
+// that runs takes the RCM device away with it, and often brings up a device of its
+// own (hekate's USB mass storage, say). One that crashes sends the Switch back to
+// RCM, and a device that's still sitting there in RCM never ran anything.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::monitor::{self, DeviceKind};
+use crate::report::millis;
+use crate::storage;
+use crate::{InjectionProgress, ProgressCallback};
+
+const VERIFICATION_KEY: &str = "verification";
+
+/// How long to watch the bus for by default.
+pub const DEFAULT_WINDOW_SECS: u64 = 5;
+
+/// How often to look at the bus while watching.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Devices a payload is known to bring up, by vendor and (optionally) product ID.
+const KNOWN_DEVICES: [(u16, Option<u16>, &str); 2] = [
+    (0x11EC, Some(0xA7E0), "hekate USB mass storage"),
+    (0x11EC, None, "hekate"),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerificationStatus {
+    /// Something the payload brought up appeared.
+    Verified,
+    /// Nothing went wrong, but nothing proved the payload is running either.
+    Unverified,
+    /// The device stayed in RCM, or came back to it.
+    Failed,
+}
+
+impl VerificationStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            VerificationStatus::Verified => "verified",
+            VerificationStatus::Unverified => "unverified",
+            VerificationStatus::Failed => "failed",
+        }
+    }
+}
+
+/// What we saw on the bus after the smash.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Observation {
+    /// We didn't watch, e.g. because verification is turned off.
+    NotWatched,
+    /// The device never left RCM, so it's probably wedged.
+    StillInRcm,
+    /// The device left RCM and nothing else showed up. Payloads that don't use USB
+    /// look like this.
+    Vanished,
+    /// The device left RCM and came back, so the payload probably crashed.
+    ReturnedToRcm,
+    /// A device we recognise showed up where the Switch is plugged in.
+    KnownDeviceAppeared {
+        name: String,
+        vendor_id: u16,
+        product_id: u16,
+    },
+}
+
+impl Observation {
+    pub fn status(&self) -> VerificationStatus {
+        match self {
+            Observation::KnownDeviceAppeared { .. } => VerificationStatus::Verified,
+            Observation::NotWatched | Observation::Vanished => VerificationStatus::Unverified,
+            Observation::StillInRcm | Observation::ReturnedToRcm => VerificationStatus::Failed,
+        }
+    }
+}
+
+/// How verifying an injection went.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Verification {
+    pub status: VerificationStatus,
+    pub observation: Observation,
+    /// How long we watched for, in milliseconds.
+    pub elapsed_ms: f64,
+}
+
+impl Verification {
+    pub fn new(observation: Observation, elapsed: Duration) -> Self {
+        Self {
+            status: observation.status(),
+            observation,
+            elapsed_ms: millis(elapsed),
+        }
+    }
+
+    /// For injections nobody watched.
+    pub fn not_watched() -> Self {
+        Self::new(Observation::NotWatched, Duration::ZERO)
+    }
+}
+
+/// A device on the bus, as much as we can tell without opening it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BusDevice {
+    pub bus: u8,
+    pub address: u8,
+    pub port_path: Option<String>,
+    pub vendor_id: u16,
+    pub product_id: u16,
+}
+
+impl BusDevice {
+    fn bus_and_address(&self) -> (u8, u8) {
+        (self.bus, self.address)
+    }
+
+    fn is_rcm(&self) -> bool {
+        DeviceKind::classify(self.vendor_id, self.product_id) == DeviceKind::Rcm
+    }
+}
+
+/// What a device that appeared after an injection is, if we know.
+pub fn recognise(vendor_id: u16, product_id: u16) -> Option<&'static str> {
+    KNOWN_DEVICES
+        .iter()
+        .find(|(vendor, product, _)| {
+            *vendor == vendor_id && product.is_none_or(|product| product == product_id)
+        })
+        .map(|(_, _, name)| *name)
+        .or_else(|| {
+            (DeviceKind::classify(vendor_id, product_id) == DeviceKind::Switch)
+                .then_some("a booted Switch")
+        })
+}
+
+/// Lists what's on the bus. Nothing gets opened, so a device that's just come
+/// back in RCM is left alone.
+pub fn snapshot() -> Result<Vec<BusDevice>, rusb::Error> {
+    Ok(rusb::devices()?
+        .iter()
+        .filter_map(|device| {
+            let desc = device.device_descriptor().ok()?;
+            Some(BusDevice {
+                bus: device.bus_number(),
+                address: device.address(),
+                port_path: monitor::port_path(&device),
+                vendor_id: desc.vendor_id(),
+                product_id: desc.product_id(),
+            })
+        })
+        .collect())
+}
+
+/// Follows the bus after an injection, one look at a time.
+pub struct Watcher {
+    /// Where the RCM device we injected was.
+    rcm: (u8, u8),
+    /// Where it's plugged in, if we know; anything elsewhere is someone else's.
+    port_path: Option<String>,
+    /// What was already there, which doesn't count as appearing.
+    present: HashSet<(u8, u8)>,
+    vanished: bool,
+}
+
+impl Watcher {
+    /// Starts watching the RCM device at `rcm`, with the bus as it was before the
+    /// injection.
+    pub fn new(rcm: (u8, u8), before: &[BusDevice]) -> Self {
+        Self {
+            rcm,
+            port_path: before
+                .iter()
+                .find(|device| device.bus_and_address() == rcm)
+                .and_then(|device| device.port_path.clone()),
+            present: before.iter().map(BusDevice::bus_and_address).collect(),
+            vanished: false,
+        }
+    }
+
+    /// Takes in the bus as it is now, returning an observation once there's one
+    /// that settles it.
+    pub fn observe(&mut self, devices: &[BusDevice]) -> Option<Observation> {
+        let on_our_port = |device: &&BusDevice| {
+            self.port_path
+                .as_ref()
+                .is_none_or(|port_path| device.port_path.as_ref() == Some(port_path))
+        };
+
+        let appeared = devices
+            .iter()
+            .filter(|device| !self.present.contains(&device.bus_and_address()))
+            .filter(on_our_port)
+            .find_map(|device| {
+                recognise(device.vendor_id, device.product_id).map(|name| {
+                    Observation::KnownDeviceAppeared {
+                        name: name.to_string(),
+                        vendor_id: device.vendor_id,
+                        product_id: device.product_id,
+                    }
+                })
+            });
+        if appeared.is_some() {
+            return appeared;
+        }
+
+        let still_there = devices
+            .iter()
+            .any(|device| device.bus_and_address() == self.rcm && device.is_rcm());
+        if !still_there {
+            self.vanished = true;
+        }
+
+        // Once it's gone, a device in RCM where it was, or a new one where it's
+        // plugged in, means it came back.
+        let back_in_rcm = devices.iter().filter(on_our_port).any(|device| {
+            device.is_rcm()
+                && (device.bus_and_address() == self.rcm
+                    || !self.present.contains(&device.bus_and_address()))
+        });
+        (self.vanished && back_in_rcm).then_some(Observation::ReturnedToRcm)
+    }
+
+    /// What we can say once we've stopped watching.
+    pub fn conclude(&self) -> Observation {
+        if self.vanished {
+            Observation::Vanished
+        } else {
+            Observation::StillInRcm
+        }
+    }
+}
+
+/// Watches the bus for up to `window`, looking every `interval`, until something
+/// settles whether the injection took.
+pub fn watch(
+    mut watcher: Watcher,
+    window: Duration,
+    interval: Duration,
+    mut scan: impl FnMut() -> Result<Vec<BusDevice>, rusb::Error>,
+) -> Verification {
+    let started = Instant::now();
+    loop {
+        match scan() {
+            Ok(devices) => {
+                if let Some(observation) = watcher.observe(&devices) {
+                    return Verification::new(observation, started.elapsed());
+                }
+            }
+            Err(e) => warn!(error = %e, "Failed to enumerate USB devices while verifying"),
+        }
+
+        if started.elapsed() >= window {
+            return Verification::new(watcher.conclude(), started.elapsed());
+        }
+        thread::sleep(interval);
+    }
+}
+
+/// Notes what's on the bus before injecting the RCM device at `rcm`, so it can be
+/// verified afterwards. Returns `None` if we can't tell what's there.
+pub fn before_injection(rcm: Option<(u8, u8)>) -> Option<Watcher> {
+    let rcm = rcm?;
+    match snapshot() {
+        Ok(before) => Some(Watcher::new(rcm, &before)),
+        Err(e) => {
+            warn!(error = %e, "Failed to enumerate USB devices; the injection won't be verified");
+            None
+        }
+    }
+}
+
+/// Watches the real bus after an injection, for up to `window`.
+pub fn verify(
+    watcher: Option<Watcher>,
+    window: Duration,
+    progress: ProgressCallback,
+) -> Verification {
+    let Some(watcher) = watcher.filter(|_| !window.is_zero()) else {
+        return Verification::not_watched();
+    };
+
+    progress(InjectionProgress::Verifying {
+        window_secs: window.as_secs(),
+    });
+    let verification = watch(watcher, window, POLL_INTERVAL, snapshot);
+    info!(
+        status = ?verification.status,
+        observation = ?verification.observation,
+        "Verified the injection"
+    );
+    verification
+}
+
+/// How long to watch the bus after an injection.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VerificationSettings {
+    /// Zero turns verification off.
+    pub window_secs: u64,
+}
+
+impl Default for VerificationSettings {
+    fn default() -> Self {
+        Self {
+            window_secs: DEFAULT_WINDOW_SECS,
+        }
+    }
+}
+
+impl VerificationSettings {
+    pub fn window(&self) -> Duration {
+        Duration::from_secs(self.window_secs)
+    }
+}
+
+pub fn load_settings(app: &tauri::AppHandle) -> VerificationSettings {
+    storage::load_setting(app, VERIFICATION_KEY).unwrap_or_default()
+}
+
+#[tauri::command]
+pub fn get_verification_settings(app: tauri::AppHandle) -> VerificationSettings {
+    load_settings(&app)
+}
+
+#[tauri::command]
+pub fn set_verification_settings(
+    app: tauri::AppHandle,
+    settings: VerificationSettings,
+) -> Result<(), String> {
+    storage::save_setting(&app, VERIFICATION_KEY, &settings)
+}
@@ -0,0 +1,82 @@
+// Waiting for an RCM device to show up, for "inject when ready".
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How long to wait for a device, and a way to give up early.
+///
+/// Clones share the same cancellation flag, so one can be handed to the thread
+/// doing the waiting while another stays behind to cancel it.
+#[derive(Clone, Debug)]
+pub struct DeviceWait {
+    timeout: Option<Duration>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl DeviceWait {
+    /// How often we look for the device while waiting.
+    pub const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+    /// Waits for at most `timeout`, or until cancelled if there's no timeout.
+    pub fn new(timeout: Option<Duration>) -> Self {
+        Self {
+            timeout,
+            cancelled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Stops the wait at its next poll.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    fn is_same(&self, other: &DeviceWait) -> bool {
+        Arc::ptr_eq(&self.cancelled, &other.cancelled)
+    }
+}
+
+/// The wait in progress, if any, so the frontend can cancel it.
+#[derive(Default)]
+pub struct WaitState {
+    current: Mutex<Option<DeviceWait>>,
+}
+
+impl WaitState {
+    /// Starts tracking a new wait, cancelling any previous one.
+    pub fn begin(&self, wait: &DeviceWait) {
+        if let Some(previous) = self.current.lock().unwrap().replace(wait.clone()) {
+            previous.cancel();
+        }
+    }
+
+    /// Stops tracking a wait once it's over, unless a newer one has replaced it.
+    pub fn finish(&self, wait: &DeviceWait) {
+        let mut current = self.current.lock().unwrap();
+        if current
+            .as_ref()
+            .is_some_and(|current| current.is_same(wait))
+        {
+            *current = None;
+        }
+    }
+
+    /// Cancels the wait in progress. Returns whether there was one.
+    pub fn cancel(&self) -> bool {
+        match self.current.lock().unwrap().take() {
+            Some(wait) => {
+                wait.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
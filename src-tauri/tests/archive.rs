@@ -0,0 +1,99 @@
+use std::io::Write;
+use whiz_lib::archive::{
+    choose_payload, extract_payload, glob_matches, ArchiveError, ArchiveKind,
+    DEFAULT_PAYLOAD_PATTERNS,
+};
+
+mod common;
+use common::ScratchDir;
+
+fn entries(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+#[test]
+fn glob_matches_like_a_shell_ignoring_case() {
+    assert!(glob_matches(
+        "hekate_ctcaer_*.bin",
+        "hekate_ctcaer_6.2.1.bin"
+    ));
+    assert!(glob_matches(
+        "hekate_ctcaer_*.bin",
+        "HEKATE_CTCAER_6.2.1.BIN"
+    ));
+    assert!(glob_matches("fusee.b?n", "fusee.bin"));
+    assert!(glob_matches("*", ""));
+    assert!(!glob_matches("*.bin", "payload.bin.txt"));
+    assert!(!glob_matches("fusee.bin", "fusee.bi"));
+}
+
+#[test]
+fn choose_payload_prefers_earlier_patterns_then_shallower_entries() {
+    // Roughly what a hekate release looks like.
+    let hekate = entries(&[
+        "bootloader/",
+        "bootloader/sys/nyx.bin",
+        "bootloader/payloads/",
+        "hekate_ctcaer_6.2.1.bin",
+    ]);
+    assert_eq!(
+        choose_payload(&hekate, DEFAULT_PAYLOAD_PATTERNS).unwrap(),
+        "hekate_ctcaer_6.2.1.bin"
+    );
+
+    // With only the catch-all, the payload at the root still beats the one nested
+    // inside.
+    assert_eq!(
+        choose_payload(&hekate, &["*.bin"]).unwrap(),
+        "hekate_ctcaer_6.2.1.bin"
+    );
+
+    let two = entries(&["a.bin", "b.bin", "nested/c.bin"]);
+    match choose_payload(&two, &["*.bin"]) {
+        Err(ArchiveError::Ambiguous { candidates }) => {
+            assert_eq!(candidates, vec!["a.bin", "b.bin"])
+        }
+        other => panic!("expected Ambiguous, got {:?}", other),
+    }
+
+    assert!(matches!(
+        choose_payload(&entries(&["README.md"]), DEFAULT_PAYLOAD_PATTERNS),
+        Err(ArchiveError::NoPayload)
+    ));
+}
+
+#[test]
+fn extract_payload_writes_only_the_payload() {
+    let dir = ScratchDir::new("archive-zip");
+    let archive = dir.join("hekate_ctcaer_6.2.1.zip");
+    let payload = vec![0x5Au8; 4096];
+
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(&archive).unwrap());
+    let options = zip::write::SimpleFileOptions::default();
+    zip.start_file("bootloader/sys/nyx.bin", options).unwrap();
+    zip.write_all(b"not the payload").unwrap();
+    zip.start_file("hekate_ctcaer_6.2.1.bin", options).unwrap();
+    zip.write_all(&payload).unwrap();
+    zip.finish().unwrap();
+
+    let kind = ArchiveKind::detect_file(&archive).unwrap();
+    assert_eq!(kind, Some(ArchiveKind::Zip));
+
+    let extracted =
+        extract_payload(&archive, kind.unwrap(), &dir, DEFAULT_PAYLOAD_PATTERNS).unwrap();
+    assert_eq!(extracted, dir.join("hekate_ctcaer_6.2.1.bin"));
+    assert_eq!(std::fs::read(&extracted).unwrap(), payload);
+
+    // Nothing else from the archive, and no leftover temporary files.
+    let mut names: Vec<String> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    names.sort();
+    assert_eq!(
+        names,
+        vec!["hekate_ctcaer_6.2.1.bin", "hekate_ctcaer_6.2.1.zip"]
+    );
+
+    assert_eq!(ArchiveKind::detect(b"\x00\x00\x00\x00"), None);
+}
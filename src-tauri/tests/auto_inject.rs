@@ -0,0 +1,91 @@
+use whiz_lib::auto_inject::{AutoInjectRule, AutoInjectSettings, DeviceMatch};
+use whiz_lib::monitor::{DeviceKind, TrackedDevice};
+use whiz_lib::{ChipInfo, DeviceInfo};
+
+fn rcm_device(port_path: &str, uid: Option<&str>) -> TrackedDevice {
+    TrackedDevice {
+        bus: 1,
+        address: 7,
+        kind: DeviceKind::Rcm,
+        port_path: Some(port_path.to_string()),
+        info: DeviceInfo {
+            vendor_id: 0x0955,
+            product_id: 0x7321,
+            manufacturer: None,
+            product: None,
+            serial_number: None,
+        },
+        chip_info: uid.map(|uid| {
+            let mut device_id = [0u8; 16];
+            hex::decode_to_slice(uid, &mut device_id).unwrap();
+            ChipInfo::from_device_id(&device_id).unwrap()
+        }),
+        chip_id_read_failed: None,
+    }
+}
+
+fn settings() -> AutoInjectSettings {
+    AutoInjectSettings {
+        enabled: true,
+        default_payload: Some("hekate.bin".to_string()),
+        rules: vec![
+            AutoInjectRule {
+                device: DeviceMatch::ChipUid {
+                    uid: "00112233445566778899AABBCCDDEEFF".to_string(),
+                },
+                payload_path: "lockpick.bin".to_string(),
+            },
+            AutoInjectRule {
+                device: DeviceMatch::PortPath {
+                    port_path: "1-2.4".to_string(),
+                },
+                payload_path: "tegraexplorer.bin".to_string(),
+            },
+        ],
+    }
+}
+
+#[test]
+fn rules_are_checked_in_order_before_the_default() {
+    let settings = settings();
+    let uid = "00112233445566778899aabbccddeeff";
+
+    assert_eq!(
+        settings.payload_for(&rcm_device("1-2.4", Some(uid))),
+        Some("lockpick.bin")
+    );
+    assert_eq!(
+        settings.payload_for(&rcm_device("1-2.4", None)),
+        Some("tegraexplorer.bin")
+    );
+    assert_eq!(
+        settings.payload_for(&rcm_device("1-3", None)),
+        Some("hekate.bin")
+    );
+}
+
+#[test]
+fn nothing_is_injected_unless_enabled() {
+    let mut settings = settings();
+    settings.enabled = false;
+    assert_eq!(settings.payload_for(&rcm_device("1-2.4", None)), None);
+
+    settings.enabled = true;
+    settings.default_payload = None;
+    assert_eq!(settings.payload_for(&rcm_device("1-3", None)), None);
+}
+
+#[test]
+fn settings_round_trip_through_the_store_format() {
+    let value = serde_json::to_value(settings()).unwrap();
+    assert_eq!(value["rules"][1]["match"]["by"], "port_path");
+    assert_eq!(
+        serde_json::from_value::<AutoInjectSettings>(value).unwrap(),
+        settings()
+    );
+
+    // Settings saved before a field existed still load.
+    let partial: AutoInjectSettings = serde_json::from_str(r#"{"enabled": true}"#).unwrap();
+    assert!(partial.enabled);
+    assert!(partial.rules.is_empty());
+}
@@ -0,0 +1,57 @@
+use whiz_lib::{BackendKind, JoltError};
+
+#[test]
+fn each_os_gets_its_own_backend_by_default() {
+    let expected = if cfg!(target_os = "linux") {
+        BackendKind::Linux
+    } else {
+        BackendKind::Libusb
+    };
+
+    assert_eq!(BackendKind::select(None).unwrap(), expected);
+}
+
+#[test]
+fn libusb_platforms_can_be_asked_for_by_name() {
+    for system in [
+        "macos",
+        "Darwin",
+        "libusbhax",
+        "freebsd",
+        "FreeBSD",
+        "windows",
+        "Windows",
+    ] {
+        let kind = BackendKind::select(Some(system)).unwrap();
+        assert_eq!(kind, BackendKind::Libusb, "{}", system);
+        assert_eq!(kind.name(), "libusb");
+    }
+}
+
+#[test]
+fn usbfs_is_only_offered_on_linux() {
+    for system in ["linux", "Linux"] {
+        let result = BackendKind::select(Some(system));
+        if cfg!(target_os = "linux") {
+            assert_eq!(result.unwrap(), BackendKind::Linux);
+        } else {
+            assert!(matches!(
+                result,
+                Err(JoltError::UnsupportedPlatform { system: s }) if s == system
+            ));
+        }
+    }
+}
+
+#[test]
+fn unknown_backends_are_rejected() {
+    let error = BackendKind::select(Some("amigaos")).unwrap_err();
+
+    assert_eq!(error.code(), "UnsupportedPlatform");
+    assert!(
+        matches!(&error, JoltError::UnsupportedPlatform { system } if system == "amigaos"),
+        "{:?}",
+        error
+    );
+    assert!(error.to_string().contains("'amigaos'"));
+}
@@ -0,0 +1,120 @@
+// Golden tests for build_payload: the RCM streams in tests/fixtures/build_payload
+// come from fusee-launcher's stream construction (see generate.py there), and
+// our port has to reproduce them byte for byte.
+
+use std::path::{Path, PathBuf};
+
+use whiz_lib::{build_payload, Intermezzo};
+
+fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/build_payload")
+}
+
+/// Mirrors fixture_payload() in generate.py.
+fn fixture_payload(size: usize) -> Vec<u8> {
+    (0..size).map(|i| ((i * 31 + 7) & 0xFF) as u8).collect()
+}
+
+fn manifest() -> Vec<serde_json::Value> {
+    let manifest = std::fs::read_to_string(fixtures_dir().join("manifest.json")).unwrap();
+    serde_json::from_str(&manifest).unwrap()
+}
+
+fn case(name: &str) -> serde_json::Value {
+    manifest()
+        .into_iter()
+        .find(|case| case["name"] == name)
+        .unwrap_or_else(|| panic!("no fixture named {}", name))
+}
+
+fn assert_matches_reference(name: &str) {
+    let case = case(name);
+    let size = case["payload_size"].as_u64().unwrap() as usize;
+    let reference = std::fs::read(fixtures_dir().join(case["stream"].as_str().unwrap())).unwrap();
+
+    let stream = build_payload(&fixture_payload(size), &Intermezzo::builtin().unwrap()).unwrap();
+
+    assert_eq!(stream.len(), reference.len(), "{}: stream length", name);
+    if let Some(offset) = stream.iter().zip(&reference).position(|(a, b)| a != b) {
+        panic!(
+            "{}: streams differ at offset {:#x} (got {:#04x}, expected {:#04x})",
+            name, offset, stream[offset], reference[offset]
+        );
+    }
+}
+
+#[test]
+fn empty_payload() {
+    assert_matches_reference("empty");
+}
+
+#[test]
+fn one_byte_payload() {
+    assert_matches_reference("one_byte");
+}
+
+#[test]
+fn odd_sized_payload() {
+    assert_matches_reference("odd_size");
+}
+
+#[test]
+fn payload_just_under_the_spray() {
+    assert_matches_reference("just_under_spray");
+}
+
+#[test]
+fn payload_exactly_filling_the_space_before_the_spray() {
+    assert_matches_reference("exactly_spray");
+}
+
+#[test]
+fn payload_just_over_the_spray() {
+    assert_matches_reference("just_over_spray");
+}
+
+#[test]
+fn payload_ending_on_a_buffer_boundary() {
+    assert_matches_reference("buffer_aligned");
+}
+
+#[test]
+fn hekate_sized_payload() {
+    assert_matches_reference("hekate_sized");
+}
+
+#[test]
+fn largest_payload_that_fits() {
+    assert_matches_reference("maximum");
+}
+
+#[test]
+fn payload_too_large() {
+    let case = case("too_large");
+    let size = case["payload_size"].as_u64().unwrap() as usize;
+
+    let error = build_payload(&fixture_payload(size), &Intermezzo::builtin().unwrap()).unwrap_err();
+    assert_eq!(error.code(), "PayloadTooLarge");
+    assert_eq!(error.to_string(), case["error"].as_str().unwrap());
+}
+
+#[test]
+fn every_fixture_is_covered() {
+    let covered = [
+        "empty",
+        "one_byte",
+        "odd_size",
+        "just_under_spray",
+        "exactly_spray",
+        "just_over_spray",
+        "buffer_aligned",
+        "hekate_sized",
+        "maximum",
+        "too_large",
+    ];
+
+    for case in manifest() {
+        let name = case["name"].as_str().unwrap();
+        assert!(covered.contains(&name), "fixture {} has no test", name);
+    }
+}
@@ -0,0 +1,50 @@
+use whiz_lib::ChipInfo;
+
+/// Packs fuse values into a device ID the way the bootROM does.
+fn device_id(vendor: u32, fab: u32, lot0: u32, lot1: u32, wafer: u32, x: u32, y: u32) -> Vec<u8> {
+    let ecid = [
+        (lot1 << 30) | (wafer << 24) | (x << 15) | (y << 6) | 0x2A,
+        (lot0 << 26) | (lot1 >> 2),
+        (fab << 26) | (lot0 >> 6),
+        vendor,
+    ];
+    ecid.iter().flat_map(|word| word.to_le_bytes()).collect()
+}
+
+/// Encodes a lot name as five 6-bit base-36 digits.
+fn lot_code(name: &str) -> u32 {
+    name.chars()
+        .fold(0, |code, c| (code << 6) | c.to_digit(36).unwrap())
+}
+
+#[test]
+fn decodes_the_fuse_fields() {
+    let lot0 = lot_code("N4K2Z");
+    let id = device_id(0x3, 0x21, lot0, 0x0ABCDEF1, 0x17, 0x1A5, 0x0F3);
+
+    let chip = ChipInfo::from_device_id(&id).unwrap();
+
+    assert_eq!(chip.uid, hex::encode(&id));
+    assert_eq!(chip.vendor_code, 0x3);
+    assert_eq!(chip.fab_code, 0x21);
+    assert_eq!(chip.lot_code_0, lot0);
+    assert_eq!(chip.lot.as_deref(), Some("N4K2Z"));
+    assert_eq!(chip.lot_code_1, 0x0ABCDEF1);
+    assert_eq!(chip.wafer_id, 0x17);
+    assert_eq!(chip.x_coordinate, 0x1A5);
+    assert_eq!(chip.y_coordinate, 0x0F3);
+    assert_eq!(chip.reserved, 0x2A);
+}
+
+#[test]
+fn lot_codes_outside_base_36_are_left_undecoded() {
+    // 0x3F is not a base-36 digit.
+    let id = device_id(0, 0, 0x3F, 0, 0, 0, 0);
+
+    assert_eq!(ChipInfo::from_device_id(&id).unwrap().lot, None);
+}
+
+#[test]
+fn short_device_ids_are_rejected() {
+    assert!(ChipInfo::from_device_id(&[0u8; 12]).is_err());
+}
@@ -0,0 +1,36 @@
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+/// An empty directory of its own for a test, removed again when it's dropped.
+pub struct ScratchDir(PathBuf);
+
+impl ScratchDir {
+    /// `name` has to be unique across the test suite, since test binaries run
+    /// side by side.
+    pub fn new(name: &str) -> ScratchDir {
+        let dir = std::env::temp_dir().join(format!("jolt-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        ScratchDir(dir)
+    }
+}
+
+impl Deref for ScratchDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for ScratchDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
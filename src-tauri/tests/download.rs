@@ -0,0 +1,216 @@
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+use std::thread;
+use whiz_lib::download::{
+    download, find_checksum, move_unclobbered, sanitize_filename, DownloadOptions, STAGING_DIR,
+};
+
+mod common;
+use common::ScratchDir;
+
+/// Serves `body` once over HTTP, claiming it's `content_length` bytes long if given.
+fn serve_once(body: Vec<u8>, content_length: Option<usize>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/payload.bin", listener.local_addr().unwrap());
+
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = [0u8; 1024];
+        let _ = stream.read(&mut request);
+
+        let mut response = b"HTTP/1.1 200 OK\r\nConnection: close\r\n".to_vec();
+        if let Some(length) = content_length {
+            response.extend(format!("Content-Length: {}\r\n", length).as_bytes());
+        }
+        response.extend(b"\r\n");
+        response.extend(&body);
+        let _ = stream.write_all(&response);
+    });
+
+    url
+}
+
+fn files_in(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn filenames_cannot_escape_the_directory() {
+    assert_eq!(sanitize_filename("hekate.bin").unwrap(), "hekate.bin");
+    assert_eq!(sanitize_filename("../../.bashrc").unwrap(), "bashrc");
+    assert_eq!(sanitize_filename("..\\..\\evil.bin").unwrap(), "evil.bin");
+    assert_eq!(sanitize_filename("a<b>c:d?.bin").unwrap(), "abcd.bin");
+    assert!(sanitize_filename("..").is_err());
+    assert!(sanitize_filename("payloads/").is_err());
+}
+
+#[test]
+fn downloads_land_in_place_with_progress() {
+    let body: Vec<u8> = (0..200_000).map(|i| (i % 251) as u8).collect();
+    let url = serve_once(body.clone(), Some(body.len()));
+    let dir = ScratchDir::new("download-ok");
+    let expected_sha256 = hex::encode(Sha256::digest(&body)).to_uppercase();
+
+    let last_progress = std::cell::Cell::new(0);
+    let options = DownloadOptions {
+        expected_sha256: Some(expected_sha256),
+        ..Default::default()
+    };
+    let path = download(
+        &url,
+        &dir,
+        "payload.bin",
+        &options,
+        &AtomicBool::new(false),
+        &|progress| {
+            assert_eq!(progress.bytes_total, Some(body.len() as u64));
+            last_progress.set(progress.bytes_downloaded);
+        },
+    )
+    .unwrap();
+
+    assert_eq!(path, dir.join("payload.bin"));
+    assert_eq!(std::fs::read(&path).unwrap(), body);
+    assert_eq!(last_progress.get(), body.len() as u64);
+    assert_eq!(files_in(&dir), ["payload.bin"]);
+}
+
+#[test]
+fn failed_downloads_leave_nothing_behind() {
+    let dir = ScratchDir::new("download-failed");
+    std::fs::write(dir.join("payload.bin"), b"the old payload").unwrap();
+
+    let cases = [
+        // Says it's too big up front.
+        (
+            serve_once(vec![0; 100], Some(100)),
+            DownloadOptions {
+                max_size: 99,
+                ..Default::default()
+            },
+            "TooLarge",
+        ),
+        // Doesn't say how big it is, and turns out to be too big.
+        (
+            serve_once(vec![0; 100], None),
+            DownloadOptions {
+                max_size: 99,
+                ..Default::default()
+            },
+            "TooLarge",
+        ),
+        (
+            serve_once(vec![0; 100], Some(100)),
+            DownloadOptions {
+                expected_sha256: Some("00".repeat(32)),
+                ..Default::default()
+            },
+            "ChecksumMismatch",
+        ),
+    ];
+
+    for (url, options, code) in cases {
+        let error = download(
+            &url,
+            &dir,
+            "payload.bin",
+            &options,
+            &AtomicBool::new(false),
+            &|_| {},
+        )
+        .unwrap_err();
+        assert_eq!(error.code(), code);
+    }
+
+    let error = download(
+        &serve_once(vec![0; 100], Some(100)),
+        &dir,
+        "payload.bin",
+        &DownloadOptions::default(),
+        &AtomicBool::new(true),
+        &|_| {},
+    )
+    .unwrap_err();
+    assert_eq!(error.code(), "Cancelled");
+
+    assert_eq!(files_in(&dir), ["payload.bin"]);
+    assert_eq!(
+        std::fs::read(dir.join("payload.bin")).unwrap(),
+        b"the old payload"
+    );
+}
+
+#[test]
+fn downloads_do_not_clobber_a_different_file() {
+    let dir = ScratchDir::new("download-clobber");
+    std::fs::write(dir.join("payload.bin"), b"the old payload").unwrap();
+
+    let fetch = |body: &[u8]| {
+        download(
+            &serve_once(body.to_vec(), Some(body.len())),
+            &dir,
+            "payload.bin",
+            &DownloadOptions::default(),
+            &AtomicBool::new(false),
+            &|_| {},
+        )
+        .unwrap()
+    };
+
+    let new_payload = b"the new payload";
+    let path = fetch(new_payload);
+    let prefix = &hex::encode(Sha256::digest(new_payload))[..8];
+    assert_eq!(path, dir.join(format!("{}-payload.bin", prefix)));
+    assert_eq!(std::fs::read(&path).unwrap(), new_payload);
+    assert_eq!(
+        std::fs::read(dir.join("payload.bin")).unwrap(),
+        b"the old payload"
+    );
+
+    // The same file again just replaces itself.
+    assert_eq!(fetch(b"the old payload"), dir.join("payload.bin"));
+    assert_eq!(files_in(&dir).len(), 2);
+
+    // Checked downloads are moved in from the staging directory the same way.
+    let staging_dir = dir.join(STAGING_DIR);
+    std::fs::create_dir_all(&staging_dir).unwrap();
+    std::fs::write(staging_dir.join("payload.bin"), new_payload).unwrap();
+    let moved = move_unclobbered(&staging_dir.join("payload.bin"), &dir).unwrap();
+    assert_eq!(moved, path);
+    assert!(!staging_dir.join("payload.bin").exists());
+}
+
+#[test]
+fn checksums_are_found_in_sha256sum_listings() {
+    let hash = "a".repeat(64);
+    let other = "b".repeat(64);
+
+    let listing = format!(
+        "{}  hekate_ctcaer_6.2.1.zip\n{} *dist/fusee.bin\n",
+        hash, other
+    );
+    assert_eq!(
+        find_checksum(&listing, "hekate_ctcaer_6.2.1.zip").as_deref(),
+        Some(hash.as_str())
+    );
+    assert_eq!(
+        find_checksum(&listing, "fusee.bin").as_deref(),
+        Some(other.as_str())
+    );
+    assert_eq!(find_checksum(&listing, "payload.bin"), None);
+
+    // A file that's only a hash, e.g. payload.bin.sha256.
+    assert_eq!(
+        find_checksum(&format!("{}\n", hash.to_uppercase()), "payload.bin").as_deref(),
+        Some(hash.as_str())
+    );
+    assert_eq!(find_checksum("not a hash", "payload.bin"), None);
+}
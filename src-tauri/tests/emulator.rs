@@ -0,0 +1,228 @@
+use std::cell::RefCell;
+use std::time::Duration;
+
+use whiz_lib::emulator::{EmulatorState, RcmEmulator};
+use whiz_lib::verify::Observation;
+use whiz_lib::{
+    execute_fusee_gelee_exploit, ignore_progress, InjectionProgress, InjectionReport, Intermezzo,
+    RCMHax, Transport, TriggerOutcome, PAYLOAD_START_ADDR, RCM_PAYLOAD_ADDR, STACK_SPRAY_END,
+    STACK_SPRAY_START,
+};
+
+fn test_payload(length: usize) -> Vec<u8> {
+    (0..length).map(|i| (i * 7 + 3) as u8).collect()
+}
+
+fn run_exploit(name: &str, payload: &[u8]) -> (RcmEmulator, InjectionReport) {
+    let emulator = RcmEmulator::default();
+
+    let result = execute_fusee_gelee_exploit(
+        RCMHax::from_transport(&emulator),
+        &format!("{}.bin", name),
+        payload,
+        &Intermezzo::builtin().unwrap(),
+        &ignore_progress,
+    );
+
+    (emulator, result.unwrap())
+}
+
+fn assert_payload_in_place(emulator: &RcmEmulator, payload: &[u8]) {
+    let intermezzo = Intermezzo::builtin().unwrap();
+    let head_size = (STACK_SPRAY_START - PAYLOAD_START_ADDR) as usize;
+
+    // The relocator sits at the start of the loaded image...
+    assert_eq!(
+        emulator.read_memory(RCM_PAYLOAD_ADDR, intermezzo.len()),
+        intermezzo.bytes()
+    );
+
+    // ... the start of the payload sits right where it expects it...
+    assert_eq!(
+        emulator.read_memory(PAYLOAD_START_ADDR, head_size),
+        payload[..head_size]
+    );
+
+    // ... followed by the spray and the rest of the payload.
+    for address in (STACK_SPRAY_START..STACK_SPRAY_END).step_by(4) {
+        assert_eq!(emulator.read_u32(address), RCM_PAYLOAD_ADDR);
+    }
+    assert_eq!(
+        emulator.read_memory(STACK_SPRAY_END, payload.len() - head_size),
+        payload[head_size..]
+    );
+}
+
+#[test]
+fn exploit_returns_into_the_relocator() {
+    // 13 buffers: the upload already ends on the high buffer.
+    let payload = test_payload(0x9000);
+    let (emulator, report) = run_exploit("odd", &payload);
+
+    assert_eq!(emulator.buffers_received(), 13);
+    assert_eq!(report.buffers_written, 13);
+    assert!(!report.padded_to_high_buffer);
+    assert_eq!(report.rcm_stream_size, 13 * 0x1000);
+    // Nothing watches the bus here.
+    assert_eq!(report.verification.observation, Observation::NotWatched);
+    assert_eq!(
+        emulator.state(),
+        EmulatorState::Smashed {
+            return_address: RCM_PAYLOAD_ADDR
+        }
+    );
+    assert_payload_in_place(&emulator, &payload);
+}
+
+#[test]
+fn exploit_pads_onto_the_high_buffer() {
+    // 12 buffers: one extra buffer is needed to land on the high buffer.
+    let payload = test_payload(0x8000);
+    let (emulator, report) = run_exploit("even", &payload);
+
+    assert_eq!(emulator.buffers_received(), 13);
+    assert_eq!(report.buffers_written, 13);
+    assert!(report.padded_to_high_buffer);
+    assert_eq!(report.rcm_stream_size, 12 * 0x1000);
+    assert_eq!(
+        emulator.state(),
+        EmulatorState::Smashed {
+            return_address: RCM_PAYLOAD_ADDR
+        }
+    );
+    assert_payload_in_place(&emulator, &payload);
+}
+
+#[test]
+fn injection_reports_each_phase() {
+    let emulator = RcmEmulator::default();
+    let events = RefCell::new(Vec::new());
+
+    execute_fusee_gelee_exploit(
+        RCMHax::from_transport(&emulator),
+        "progress.bin",
+        &test_payload(0x8000),
+        &Intermezzo::builtin().unwrap(),
+        &|progress| events.borrow_mut().push(progress),
+    )
+    .unwrap();
+
+    let events = events.into_inner();
+    let uploads: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event {
+            InjectionProgress::Uploading {
+                buffers_written,
+                buffers_total,
+                bytes_written,
+                bytes_total,
+            } => Some((
+                *buffers_written,
+                *buffers_total,
+                *bytes_written,
+                *bytes_total,
+            )),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(events[0], InjectionProgress::ReadingDeviceId);
+    assert_eq!(events[1], InjectionProgress::BuildingPayload);
+    assert_eq!(
+        events[2],
+        InjectionProgress::TryingStrategy {
+            strategy: "fusee-launcher".to_string()
+        }
+    );
+    assert_eq!(uploads.len(), 12);
+    assert_eq!(uploads[0], (1, 12, 0x1000, 0xC000));
+    assert_eq!(uploads[11], (12, 12, 0xC000, 0xC000));
+    assert_eq!(
+        events[events.len() - 2..],
+        [
+            InjectionProgress::SwitchingToHighBuffer,
+            InjectionProgress::Triggering
+        ]
+    );
+}
+
+#[test]
+fn injection_reports_the_chip() {
+    let emulator = RcmEmulator::default();
+    let payload = test_payload(0x1000);
+    let intermezzo = Intermezzo::builtin().unwrap();
+
+    let first = execute_fusee_gelee_exploit(
+        RCMHax::from_transport(&emulator),
+        "chip.bin",
+        &payload,
+        &intermezzo,
+        &ignore_progress,
+    )
+    .unwrap();
+
+    // The bootROM only hands out the device ID once per RCM session.
+    emulator.reset().unwrap();
+    emulator
+        .read_bulk(0x81, &mut [0u8; 16], Duration::from_millis(1000))
+        .unwrap();
+    let second = execute_fusee_gelee_exploit(
+        RCMHax::from_transport(&emulator),
+        "chip.bin",
+        &payload,
+        &intermezzo,
+        &ignore_progress,
+    )
+    .unwrap();
+
+    assert_eq!(first.payload_size, 0x1000);
+    assert_eq!(
+        first.payload_sha256,
+        whiz_lib::intermezzo::sha256_hex(&test_payload(0x1000))
+    );
+    assert_eq!(first.trigger, TriggerOutcome::TimedOut);
+
+    let chip_info = first.chip_info.unwrap();
+    assert_eq!(chip_info.uid, hex::encode(RcmEmulator::DEFAULT_DEVICE_ID));
+    assert!(!first.device_id_read_failed);
+
+    assert!(second.device_id_read_failed);
+    assert!(second.chip_info.is_none());
+}
+
+#[test]
+fn device_id_is_only_sent_once() {
+    let emulator = RcmEmulator::new([0x42; 16]);
+    let mut buffer = [0u8; 16];
+
+    let read = emulator
+        .read_bulk(0x81, &mut buffer, Duration::from_millis(1000))
+        .unwrap();
+    assert_eq!(read, 16);
+    assert_eq!(buffer, [0x42; 16]);
+
+    assert_eq!(
+        emulator.read_bulk(0x81, &mut buffer, Duration::from_millis(1000)),
+        Err(rusb::Error::Timeout)
+    );
+}
+
+#[test]
+fn device_rejects_data_past_the_announced_length() {
+    let emulator = RcmEmulator::default();
+    let mut command = vec![0u8; 0x1000];
+    command[..4].copy_from_slice(&0x1800u32.to_le_bytes());
+
+    emulator
+        .write_bulk(0x01, &command, Duration::from_millis(1000))
+        .unwrap();
+    emulator
+        .write_bulk(0x01, &[0u8; 0x1000], Duration::from_millis(1000))
+        .unwrap();
+
+    assert_eq!(emulator.state(), EmulatorState::Rejected);
+    assert_eq!(
+        emulator.write_bulk(0x01, &[0u8; 0x1000], Duration::from_millis(1000)),
+        Err(rusb::Error::Pipe)
+    );
+}
@@ -0,0 +1,39 @@
+use whiz_lib::JoltError;
+
+#[test]
+fn errors_serialize_with_a_code_and_the_usb_error() {
+    let error = JoltError::ClaimFailed {
+        interface: 0,
+        source: rusb::Error::Busy,
+    };
+
+    assert_eq!(
+        serde_json::to_value(&error).unwrap(),
+        serde_json::json!({
+            "code": "ClaimFailed",
+            "message": "Failed to claim interface 0: Resource busy",
+            "usb_error": "Busy",
+        })
+    );
+}
+
+#[test]
+fn errors_without_a_usb_cause_serialize_a_null_usb_error() {
+    let value = serde_json::to_value(JoltError::DeviceNotFound).unwrap();
+
+    assert_eq!(value["code"], "DeviceNotFound");
+    assert!(value["usb_error"].is_null());
+}
+
+#[test]
+fn unsupported_controllers_explain_themselves() {
+    let error = JoltError::UnsupportedController("it's bound to ehci-pci.".to_string());
+    let value = serde_json::to_value(&error).unwrap();
+
+    assert_eq!(value["code"], "UnsupportedController");
+    assert!(value["message"]
+        .as_str()
+        .unwrap()
+        .contains("it's bound to ehci-pci."));
+    assert!(value["usb_error"].is_null());
+}
@@ -0,0 +1,129 @@
+#!/usr/bin/env python3
+"""
+Regenerates the reference RCM streams used by tests/build_payload.rs.
+
+The stream construction below is fusee-launcher's (fusee-launcher.py, from
+"Prefix the image with an RCM command" through the size check), kept as close
+to the original as possible so the fixtures reflect the reference
+implementation rather than our port of it.
+
+Run from this directory:  python3 generate.py
+"""
+
+import json
+import os
+
+RCM_PAYLOAD_ADDR    = 0x40010000
+PAYLOAD_START_ADDR  = 0x40010E40
+STACK_SPRAY_START   = 0x40014E40
+STACK_SPRAY_END     = 0x40017000
+
+HERE = os.path.dirname(os.path.abspath(__file__))
+INTERMEZZO = os.path.join(HERE, '..', '..', '..', '..', 'assets', 'intermezzo.bin')
+
+
+def fixture_payload(size):
+    """ Deterministic payload contents; mirrored by fixture_payload() in the tests. """
+    return bytes((i * 31 + 7) & 0xFF for i in range(size))
+
+
+def build_stream(target_payload):
+    """ fusee-launcher's RCM stream construction. Returns (stream, error). """
+
+    # Prefix the image with an RCM command, so it winds up loaded into memory
+    # at the right location (0x40010000).
+
+    # Use the maximum length accepted by RCM, so we can transmit as much payload as
+    # we want; we'll take over before we get to the end.
+    length  = 0x30298
+    payload = length.to_bytes(4, byteorder='little')
+
+    # pad out to 680 so the payload starts at the right address in IRAM
+    payload += b'\0' * (680 - len(payload))
+
+    # Populate from [RCM_PAYLOAD_ADDR, INTERMEZZO_LOCATION) with the payload address.
+    # We'll use this data to smash the stack when we execute the vulnerable memcpy.
+
+    # Include the Intermezzo binary in the command stream. This is our first-stage
+    # payload, and it's responsible for relocating the final payload to 0x40010000.
+    with open(INTERMEZZO, "rb") as f:
+        intermezzo      = f.read()
+        intermezzo_size = len(intermezzo)
+        payload        += intermezzo
+
+    # Pad the payload till the start of the user payload.
+    padding_size   = PAYLOAD_START_ADDR - (RCM_PAYLOAD_ADDR + intermezzo_size)
+    payload += (b'\0' * padding_size)
+
+    # Fit a collection of the payload before the stack spray...
+    padding_size   = STACK_SPRAY_START - PAYLOAD_START_ADDR
+    payload += target_payload[:padding_size]
+
+    # ... insert the stack spray...
+    repeat_count = int((STACK_SPRAY_END - STACK_SPRAY_START) / 4)
+    payload += (RCM_PAYLOAD_ADDR.to_bytes(4, byteorder='little') * repeat_count)
+
+    # ... and follow the stack spray with the remainder of the payload.
+    payload += target_payload[padding_size:]
+
+    # Pad the payload to fill a USB request exactly, so we don't send a short
+    # packet and break out of the RCM loop.
+    payload_length = len(payload)
+    padding_size   = 0x1000 - (payload_length % 0x1000)
+    payload += (b'\0' * padding_size)
+
+    # Check to see if our payload packet will fit inside the RCM high buffer.
+    # If it won't, error out.
+    if len(payload) > length:
+        size_over = len(payload) - length
+        return None, "ERROR: Payload is too large to be submitted via RCM. ({} bytes larger than max).".format(size_over)
+
+    return payload, None
+
+
+def max_payload_size():
+    size = 0
+    while build_stream(fixture_payload(size + 0x100))[0] is not None:
+        size += 0x100
+    while build_stream(fixture_payload(size + 1))[0] is not None:
+        size += 1
+    return size
+
+
+def main():
+    head = STACK_SPRAY_START - PAYLOAD_START_ADDR
+    maximum = max_payload_size()
+
+    cases = [
+        ('empty', 0),
+        ('one_byte', 1),
+        ('odd_size', 0x123),
+        ('just_under_spray', head - 1),
+        ('exactly_spray', head),
+        ('just_over_spray', head + 1),
+        # Lands exactly on a 0x1000 boundary, so a whole extra buffer of padding is added.
+        ('buffer_aligned', 0x4D58),
+        ('hekate_sized', 0x1E000),
+        ('maximum', maximum),
+        ('too_large', maximum + 1),
+    ]
+
+    manifest = []
+    for name, size in cases:
+        stream, error = build_stream(fixture_payload(size))
+        entry = {'name': name, 'payload_size': size}
+        if error:
+            entry['error'] = error
+        else:
+            entry['stream'] = name + '.rcm'
+            with open(os.path.join(HERE, entry['stream']), 'wb') as f:
+                f.write(stream)
+        manifest.append(entry)
+
+    with open(os.path.join(HERE, 'manifest.json'), 'w') as f:
+        json.dump(manifest, f, indent=2)
+        f.write('\n')
+
+
+if __name__ == '__main__':
+    main()
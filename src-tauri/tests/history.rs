@@ -0,0 +1,188 @@
+use std::time::Duration;
+use whiz_lib::emulator::RcmEmulator;
+use whiz_lib::history::{
+    self, ExportFormat, HistoryEntry, HistoryFilter, InjectionHistory, Outcome,
+};
+use whiz_lib::report::PhaseTimings;
+use whiz_lib::verify::{Observation, Verification, VerificationStatus};
+use whiz_lib::{
+    chip, execute_fusee_gelee_exploit, ignore_progress, InjectionReport, Intermezzo, JoltError,
+    RCMHax, Transport, TriggerOutcome,
+};
+
+mod common;
+use common::ScratchDir;
+
+/// A device on the bus that hands out its ID, then fails every upload.
+struct UploadFails(RcmEmulator);
+
+impl Transport for UploadFails {
+    fn read_control(
+        &self,
+        request_type: u8,
+        request: u8,
+        value: u16,
+        index: u16,
+        buf: &mut [u8],
+        timeout: Duration,
+    ) -> Result<usize, rusb::Error> {
+        self.0
+            .read_control(request_type, request, value, index, buf, timeout)
+    }
+
+    fn read_bulk(
+        &self,
+        endpoint: u8,
+        buf: &mut [u8],
+        timeout: Duration,
+    ) -> Result<usize, rusb::Error> {
+        self.0.read_bulk(endpoint, buf, timeout)
+    }
+
+    fn write_bulk(&self, _: u8, _: &[u8], _: Duration) -> Result<usize, rusb::Error> {
+        Err(rusb::Error::Io)
+    }
+
+    fn claim_interface(&self, iface: u8) -> Result<(), rusb::Error> {
+        self.0.claim_interface(iface)
+    }
+
+    fn reset(&self) -> Result<(), rusb::Error> {
+        self.0.reset()
+    }
+
+    fn clear_halt(&self, endpoint: u8) -> Result<(), rusb::Error> {
+        self.0.clear_halt(endpoint)
+    }
+
+    fn bus_and_address(&self) -> Option<(u8, u8)> {
+        Some((0xFE, 0x22))
+    }
+}
+
+fn report(payload_path: &str) -> InjectionReport {
+    InjectionReport {
+        chip_info: None,
+        device_id_read_failed: true,
+        payload_path: payload_path.to_string(),
+        payload_sha256: "ab".repeat(32),
+        payload_size: 0x1000,
+        rcm_stream_size: 0x30298,
+        buffers_written: 49,
+        padded_to_high_buffer: false,
+        trigger: TriggerOutcome::TimedOut,
+        strategy: "fusee-launcher".to_string(),
+        timings: PhaseTimings {
+            upload_ms: 12.5,
+            total_ms: 20.0,
+            ..PhaseTimings::default()
+        },
+        verification: Verification::new(Observation::Vanished, Duration::from_secs(5)),
+    }
+}
+
+fn attempt(
+    timestamp: u64,
+    port_path: &str,
+    result: Result<InjectionReport, JoltError>,
+) -> HistoryEntry {
+    let mut entry = HistoryEntry::begin("/payloads/hekate.bin");
+    entry.timestamp = timestamp;
+    entry.port_path = Some(port_path.to_string());
+    entry.finish(&result, Duration::from_millis(250));
+    entry
+}
+
+#[test]
+fn attempts_are_appended_and_queried_newest_first() {
+    let dir = ScratchDir::new("history-query");
+    let history = InjectionHistory::open(dir.join(history::HISTORY_FILE));
+    assert!(history.entries().unwrap().is_empty());
+
+    let succeeded = attempt(100, "1-2", Ok(report("/payloads/hekate.bin")));
+    assert_eq!(succeeded.outcome, Outcome::Succeeded);
+    assert_eq!(succeeded.timings.as_ref().unwrap().upload_ms, 12.5);
+    assert_eq!(succeeded.duration_ms, 250.0);
+    assert_eq!(succeeded.strategy.as_deref(), Some("fusee-launcher"));
+    assert_eq!(succeeded.verification, Some(VerificationStatus::Unverified));
+    let failed = attempt(200, "1-2", Err(JoltError::DeviceNotFound));
+    assert_eq!(failed.error_code.as_deref(), Some("DeviceNotFound"));
+    assert!(failed.timings.is_none());
+    let cancelled = attempt(300, "3-1", Err(JoltError::WaitCancelled));
+    assert_eq!(cancelled.outcome, Outcome::Cancelled);
+
+    for entry in [&succeeded, &failed, &cancelled] {
+        history.append(entry).unwrap();
+    }
+    // A torn line doesn't take the rest of the history with it.
+    let mut contents = std::fs::read_to_string(history.path()).unwrap();
+    contents.push_str("{\"timestamp\": 4\n");
+    std::fs::write(history.path(), contents).unwrap();
+
+    let all = history.query(&HistoryFilter::default()).unwrap();
+    assert_eq!(
+        all,
+        vec![cancelled.clone(), failed.clone(), succeeded.clone()]
+    );
+
+    let filter = HistoryFilter {
+        port_path: Some("1-2".to_string()),
+        outcome: Some(Outcome::Failed),
+        ..HistoryFilter::default()
+    };
+    assert_eq!(history.query(&filter).unwrap(), vec![failed.clone()]);
+
+    let filter = HistoryFilter {
+        since: Some(150),
+        limit: Some(1),
+        ..HistoryFilter::default()
+    };
+    assert_eq!(history.query(&filter).unwrap(), vec![cancelled]);
+
+    history.clear().unwrap();
+    assert!(history.entries().unwrap().is_empty());
+}
+
+#[test]
+fn exports_quote_what_they_need_to() {
+    let mut entry = attempt(100, "1-2", Err(JoltError::DeviceNotFound));
+    entry.payload_path = "/payloads/a, \"b\".bin".to_string();
+
+    let csv = history::export(&[entry.clone()], ExportFormat::Csv);
+    let lines: Vec<&str> = csv.split("\r\n").collect();
+    assert!(lines[0].starts_with("timestamp,outcome,verification,payload_path,"));
+    assert!(
+        lines[1].starts_with("100,failed,,\"/payloads/a, \"\"b\"\".bin\",,,1-2,,DeviceNotFound,")
+    );
+    // A failure has no phase timings.
+    assert!(lines[1].ends_with(",250.000,,,,,,"));
+
+    let json = history::export(&[entry.clone()], ExportFormat::Json);
+    let parsed: Vec<HistoryEntry> = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, vec![entry]);
+}
+
+#[test]
+fn failed_attempts_keep_the_chip_they_read() {
+    let device = UploadFails(RcmEmulator::default());
+    let mut entry = HistoryEntry::begin("/payloads/hekate.bin");
+
+    let result = execute_fusee_gelee_exploit(
+        RCMHax::from_transport(&device),
+        "/payloads/hekate.bin",
+        &[0u8; 0x1000],
+        &Intermezzo::builtin().unwrap(),
+        &ignore_progress,
+    );
+    // What injecting does once the exploit returns.
+    entry.chip_uid = chip::recall((0xFE, 0x22)).map(|chip_info| chip_info.uid);
+    entry.finish(&result, Duration::from_millis(250));
+
+    assert_eq!(entry.outcome, Outcome::Failed);
+    assert_eq!(entry.error_code.as_deref(), Some("UploadFailed"));
+    assert_eq!(
+        entry.chip_uid,
+        Some(hex::encode(RcmEmulator::DEFAULT_DEVICE_ID))
+    );
+    chip::forget((0xFE, 0x22));
+}
@@ -0,0 +1,81 @@
+use std::path::PathBuf;
+
+use whiz_lib::intermezzo::{sha256_hex, BUILTIN_INTERMEZZO_SHA256, MAX_INTERMEZZO_SIZE};
+use whiz_lib::Intermezzo;
+
+fn write_override(name: &str, contents: &[u8]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "jolt-intermezzo-{}-{}.bin",
+        name,
+        std::process::id()
+    ));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn builtin_matches_the_pinned_hash() {
+    let intermezzo = Intermezzo::builtin().unwrap();
+
+    assert_eq!(intermezzo.len(), 124);
+    assert_eq!(sha256_hex(intermezzo.bytes()), BUILTIN_INTERMEZZO_SHA256);
+}
+
+#[test]
+fn pinned_override_is_accepted() {
+    let contents = Intermezzo::builtin().unwrap().bytes().to_vec();
+    let path = write_override("pinned", &contents);
+
+    let intermezzo = Intermezzo::from_file(&path, &BUILTIN_INTERMEZZO_SHA256.to_uppercase());
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(intermezzo.unwrap().bytes(), contents.as_slice());
+}
+
+#[test]
+fn truncated_override_is_rejected() {
+    let contents = Intermezzo::builtin().unwrap().bytes().to_vec();
+    let path = write_override("truncated", &contents[..100]);
+
+    let result = Intermezzo::from_file(&path, BUILTIN_INTERMEZZO_SHA256);
+    std::fs::remove_file(&path).unwrap();
+
+    let error = result.unwrap_err();
+    assert_eq!(error.code(), "IntermezzoCorrupted");
+    assert!(error.to_string().contains("SHA-256 mismatch"));
+}
+
+#[test]
+fn override_with_a_different_pin_is_rejected() {
+    let mut contents = Intermezzo::builtin().unwrap().bytes().to_vec();
+    contents[0x64] ^= 0xFF;
+    let path = write_override("tampered", &contents);
+
+    let result = Intermezzo::from_file(&path, BUILTIN_INTERMEZZO_SHA256);
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn empty_override_is_rejected() {
+    let path = write_override("empty", &[]);
+
+    let result = Intermezzo::from_file(&path, &sha256_hex(&[]));
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(result.unwrap_err().to_string().contains("empty"));
+}
+
+#[test]
+fn oversized_override_is_rejected() {
+    let contents = vec![0u8; MAX_INTERMEZZO_SIZE + 4];
+    let path = write_override("oversized", &contents);
+
+    let result = Intermezzo::from_file(&path, &sha256_hex(&contents));
+    std::fs::remove_file(&path).unwrap();
+
+    let error = result.unwrap_err();
+    assert_eq!(error.code(), "IntermezzoCorrupted");
+    assert!(error.to_string().contains("fit before the payload"));
+}
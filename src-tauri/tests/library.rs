@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use whiz_lib::library::{LibraryError, Origin, PayloadLibrary, RetentionPolicy};
+
+mod common;
+use common::ScratchDir;
+
+/// A file that passes for a payload, different for each `seed`.
+fn payload(seed: u8) -> Vec<u8> {
+    vec![seed; 0x1000]
+}
+
+fn write_payload(dir: &Path, name: &str, seed: u8) -> PathBuf {
+    std::fs::create_dir_all(dir).unwrap();
+    let path = dir.join(name);
+    std::fs::write(&path, payload(seed)).unwrap();
+    path
+}
+
+fn origin(source: &str, tag: &str, published_at: &str) -> Origin {
+    Origin {
+        source: Some(source.to_string()),
+        asset_name: Some(format!("{}-{}.bin", source, tag)),
+        tag: Some(tag.to_string()),
+        published_at: Some(published_at.to_string()),
+    }
+}
+
+#[test]
+fn entries_survive_reopening_and_are_not_duplicated() {
+    let dir = ScratchDir::new("library-reopen");
+    let library = PayloadLibrary::open(dir.to_path_buf());
+    assert!(library.is_new());
+
+    let path = dir.join("payload.bin");
+    std::fs::write(&path, payload(1)).unwrap();
+    let entry = library
+        .add(&path, origin("hekate", "v6.2.1", "2024-06-01T00:00:00Z"))
+        .unwrap();
+    assert_eq!(entry.size, 0x1000);
+    assert_eq!(entry.origin.tag.as_deref(), Some("v6.2.1"));
+
+    // The same payload again is the same entry.
+    assert_eq!(library.add(&path, Origin::default()).unwrap(), entry);
+    library.set_pinned(&entry.sha256, true).unwrap();
+    library.record_injection(&entry.sha256).unwrap();
+    // Injecting something that isn't in the library isn't an error.
+    library.record_injection(&"0".repeat(64)).unwrap();
+
+    let reopened = PayloadLibrary::open(dir.to_path_buf());
+    assert!(!reopened.is_new());
+    let entries = reopened.entries();
+    assert_eq!(entries.len(), 1);
+    assert!(entries[0].pinned);
+    assert!(entries[0].last_injected_at.is_some());
+
+    // Archives and the like aren't payloads.
+    let archive = dir.join("archive.zip");
+    std::fs::write(&archive, b"PK\x03\x04 not really").unwrap();
+    assert!(matches!(
+        reopened.add(&archive, Origin::default()),
+        Err(LibraryError::Payload(_))
+    ));
+
+    reopened.remove(&entry.sha256).unwrap();
+    assert!(!path.exists());
+    assert!(matches!(
+        reopened.remove(&entry.sha256),
+        Err(LibraryError::NotFound(_))
+    ));
+}
+
+#[test]
+fn imports_are_copied_in_without_clobbering() {
+    let dir = ScratchDir::new("library-import");
+    let elsewhere = ScratchDir::new("library-import-elsewhere");
+    let library = PayloadLibrary::open(dir.join("library"));
+
+    let first = elsewhere.join("payload.bin");
+    std::fs::write(&first, payload(1)).unwrap();
+    let imported = library.import(&first).unwrap();
+    assert_eq!(
+        PathBuf::from(&imported.path),
+        dir.join("library").join("payload.bin")
+    );
+    assert_eq!(imported.origin, Origin::default());
+
+    // Another payload with the same name goes next to it.
+    std::fs::write(&first, payload(2)).unwrap();
+    let second = library.import(&first).unwrap();
+    assert_ne!(second.path, imported.path);
+    assert_eq!(std::fs::read(&imported.path).unwrap(), payload(1));
+    assert_eq!(std::fs::read(&second.path).unwrap(), payload(2));
+
+    // Deleting an imported payload leaves the original alone.
+    library.remove(&second.sha256).unwrap();
+    assert!(first.exists());
+}
+
+#[test]
+fn removing_an_entry_keeps_a_file_another_one_uses() {
+    let dir = ScratchDir::new("library-shared");
+    let library = PayloadLibrary::open(dir.to_path_buf());
+
+    // Downloads used to replace a file with the same name, leaving two entries
+    // pointing at it.
+    let path = write_payload(&dir, "hekate.bin", 1);
+    let old = library
+        .add(&path, origin("hekate", "v1", "2024-01-01T00:00:00Z"))
+        .unwrap();
+    write_payload(&dir, "hekate.bin", 2);
+    let new = library
+        .add(&path, origin("hekate", "v2", "2024-02-01T00:00:00Z"))
+        .unwrap();
+    assert_eq!(old.path, new.path);
+
+    library.remove(&old.sha256).unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), payload(2));
+
+    library.remove(&new.sha256).unwrap();
+    assert!(!path.exists());
+}
+
+#[test]
+fn pruning_keeps_the_newest_pinned_and_imported_payloads() {
+    let dir = ScratchDir::new("library-prune");
+    let library = PayloadLibrary::open(dir.to_path_buf());
+
+    let add = |seed: u8, origin: Origin| {
+        let path = dir.join(format!("{}.bin", seed));
+        std::fs::write(&path, payload(seed)).unwrap();
+        library.add(&path, origin).unwrap()
+    };
+    let v1 = add(1, origin("hekate", "v1", "2024-01-01T00:00:00Z"));
+    let v2 = add(2, origin("hekate", "v2", "2024-02-01T00:00:00Z"));
+    let v3 = add(3, origin("hekate", "v3", "2024-03-01T00:00:00Z"));
+    let v4 = add(4, origin("hekate", "v4", "2024-04-01T00:00:00Z"));
+    let other = add(5, origin("tegraexplorer", "v1", "2023-01-01T00:00:00Z"));
+    let imported = add(6, Origin::default());
+    library.set_pinned(&v1.sha256, true).unwrap();
+
+    let policy = RetentionPolicy {
+        keep_latest: 2,
+        max_age_days: None,
+    };
+    let pruned = library.prune(&policy).unwrap();
+    assert_eq!(pruned.len(), 1);
+    assert_eq!(pruned[0].sha256, v2.sha256);
+    assert!(!PathBuf::from(&v2.path).exists());
+
+    let left: Vec<String> = library
+        .entries()
+        .into_iter()
+        .map(|entry| entry.sha256)
+        .collect();
+    for kept in [&v1, &v3, &v4, &other, &imported] {
+        assert!(left.contains(&kept.sha256));
+    }
+
+    // Everything here was added just now, so nothing is too old yet.
+    let policy = RetentionPolicy {
+        keep_latest: 5,
+        max_age_days: Some(1),
+    };
+    assert!(library.prunable(&policy).is_empty());
+}
+
+#[test]
+fn legacy_downloads_are_migrated_out_of_their_archives() {
+    // The old downloads folder may or may not be where the library is now.
+    for in_place in [true, false] {
+        let name = if in_place { "in-place" } else { "elsewhere" };
+        let dir = ScratchDir::new(&format!("library-legacy-{}", name));
+        let legacy_dir = dir.join("payloads");
+        let library_dir = if in_place {
+            legacy_dir.clone()
+        } else {
+            dir.join("library")
+        };
+        let library = PayloadLibrary::open(library_dir.clone());
+
+        std::fs::create_dir_all(&legacy_dir).unwrap();
+        let archive = legacy_dir.join("hekate_ctcaer_6.2.1_Nyx_1.6.1.zip");
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&archive).unwrap());
+        zip.start_file(
+            "hekate_ctcaer_6.2.1.bin",
+            zip::write::SimpleFileOptions::default(),
+        )
+        .unwrap();
+        zip.write_all(&payload(1)).unwrap();
+        zip.finish().unwrap();
+        // Not something payloads.dat knew about.
+        let stray = write_payload(&legacy_dir, "stray.bin", 3);
+
+        let moved = write_payload(&dir.join("moved"), "fusee.bin", 2);
+
+        let downloaded = vec![
+            "hekate_ctcaer_6.2.1_Nyx_1.6.1.zip".to_string(),
+            "fusee.bin".to_string(),
+            "deleted_since.bin".to_string(),
+        ];
+        let paths = HashMap::from([("fusee.bin".to_string(), moved.to_string_lossy().to_string())]);
+
+        let migrated = library.migrate_legacy(&downloaded, &paths, &legacy_dir);
+        if !in_place {
+            library.adopt(&legacy_dir).unwrap();
+        }
+        assert_eq!(migrated.len(), 2, "{}", name);
+
+        assert_eq!(migrated[0].origin.source.as_deref(), Some("hekate"));
+        assert_eq!(
+            PathBuf::from(&migrated[0].path),
+            library_dir.join("hekate_ctcaer_6.2.1.bin")
+        );
+        assert_eq!(
+            migrated[1].origin.source.as_deref(),
+            Some("atmosphere-fusee")
+        );
+        assert_eq!(PathBuf::from(&migrated[1].path), moved);
+
+        // The archive the payload came out of and the unlisted file stay put, and
+        // are reported.
+        assert_eq!(
+            library.left_behind(&legacy_dir),
+            vec![archive, stray],
+            "{}",
+            name
+        );
+    }
+}
+
+#[test]
+fn moving_the_library_takes_its_payloads_along() {
+    let dir = ScratchDir::new("library-move");
+    let elsewhere = ScratchDir::new("library-move-elsewhere");
+    let library = PayloadLibrary::open(dir.join("old"));
+
+    let owned = library
+        .import(&write_payload(&elsewhere, "owned.bin", 1))
+        .unwrap();
+    let outside = write_payload(&elsewhere, "outside.bin", 2);
+    library.add(&outside, Origin::default()).unwrap();
+
+    library.move_to(&dir.join("new")).unwrap();
+    assert_eq!(library.dir(), dir.join("new"));
+    // The old directory is gone, and payloads that were never the library's
+    // stayed put.
+    assert!(!dir.join("old").exists());
+    assert!(outside.exists());
+
+    let reopened = PayloadLibrary::open(dir.join("new"));
+    let moved = reopened.get(&owned.sha256).unwrap();
+    assert_eq!(
+        PathBuf::from(&moved.path),
+        dir.join("new").join("owned.bin")
+    );
+    assert_eq!(std::fs::read(&moved.path).unwrap(), payload(1));
+    assert_eq!(reopened.entries().len(), 2);
+}
+
+#[test]
+fn an_old_library_is_adopted_without_clobbering() {
+    let dir = ScratchDir::new("library-adopt");
+    let old = PayloadLibrary::open(dir.join("downloads"));
+    let old_entry = old
+        .add(
+            &write_payload(&dir.join("downloads"), "payload.bin", 1),
+            origin("hekate", "v1", "2024-01-01T00:00:00Z"),
+        )
+        .unwrap();
+    // Not something the old library knew about, so it isn't taken.
+    let stray = write_payload(&dir.join("downloads"), "stray.bin", 3);
+
+    let library = PayloadLibrary::open(dir.join("library"));
+    let existing = library
+        .add(
+            &write_payload(&dir.join("library"), "payload.bin", 2),
+            Origin::default(),
+        )
+        .unwrap();
+
+    assert_eq!(library.adopt(&dir.join("downloads")).unwrap(), 1);
+    let adopted = library.get(&old_entry.sha256).unwrap();
+    assert_eq!(adopted.origin.source.as_deref(), Some("hekate"));
+    assert_ne!(adopted.path, existing.path);
+    assert_eq!(std::fs::read(&adopted.path).unwrap(), payload(1));
+    assert_eq!(std::fs::read(&existing.path).unwrap(), payload(2));
+
+    assert!(stray.exists());
+    assert!(!dir.join("downloads").join("library.json").exists());
+}
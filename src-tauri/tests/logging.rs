@@ -0,0 +1,24 @@
+use whiz_lib::logging::LogBuffer;
+
+fn texts(lines: &[whiz_lib::logging::LogLine]) -> Vec<&str> {
+    lines.iter().map(|line| line.text.as_str()).collect()
+}
+
+#[test]
+fn output_becomes_numbered_lines() {
+    let buffer = LogBuffer::new(3);
+    buffer.push("first\nsec");
+    // Half a line isn't a line yet.
+    assert_eq!(texts(&buffer.lines_after(None, 10)), ["first"]);
+
+    buffer.push("ond\r\nthird\nfourth\n");
+    let lines = buffer.lines_after(None, 10);
+    // Only the newest three are kept.
+    assert_eq!(texts(&lines), ["second", "third", "fourth"]);
+    assert_eq!(lines[0].seq, 1);
+
+    // Readers pick up where they left off.
+    assert_eq!(texts(&buffer.lines_after(Some(2), 10)), ["fourth"]);
+    assert!(buffer.lines_after(Some(3), 10).is_empty());
+    assert_eq!(texts(&buffer.lines_after(None, 1)), ["fourth"]);
+}
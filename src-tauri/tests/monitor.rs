@@ -0,0 +1,91 @@
+use whiz_lib::monitor::{DeviceKind, DeviceRegistry, TrackedDevice};
+use whiz_lib::{ChipInfo, DeviceInfo};
+
+fn device(bus: u8, address: u8, vendor_id: u16, product_id: u16) -> TrackedDevice {
+    TrackedDevice {
+        bus,
+        address,
+        kind: DeviceKind::classify(vendor_id, product_id),
+        port_path: None,
+        info: DeviceInfo {
+            vendor_id,
+            product_id,
+            manufacturer: None,
+            product: None,
+            serial_number: None,
+        },
+        chip_info: None,
+        chip_id_read_failed: None,
+    }
+}
+
+#[test]
+fn classifies_switches() {
+    assert_eq!(DeviceKind::classify(0x0955, 0x7321), DeviceKind::Rcm);
+    assert_eq!(DeviceKind::classify(0x057E, 0x2000), DeviceKind::Switch);
+    assert_eq!(DeviceKind::classify(0x0955, 0x7100), DeviceKind::Other);
+    assert_eq!(DeviceKind::classify(0x046D, 0xC52B), DeviceKind::Other);
+}
+
+#[test]
+fn status_prefers_a_device_in_rcm() {
+    let registry = DeviceRegistry::default();
+    assert!(!registry.status().device_connected);
+
+    registry.insert(device(1, 2, 0x046D, 0xC52B));
+    assert!(!registry.status().device_connected);
+
+    registry.insert(device(1, 3, 0x057E, 0x2000));
+    let status = registry.status();
+    assert!(status.switch_connected_not_rcm);
+    assert!(!status.rcm_detected);
+
+    let mut rcm = device(1, 4, 0x0955, 0x7321);
+    rcm.chip_id_read_failed = Some(true);
+    registry.insert(rcm);
+    let status = registry.status();
+    assert!(status.rcm_detected);
+    assert!(!status.switch_connected_not_rcm);
+    assert_eq!(status.chip_id_read_failed, Some(true));
+    assert_eq!(status.device_info.unwrap().product_id, 0x7321);
+
+    assert_eq!(registry.remove((1, 4)).unwrap().kind, DeviceKind::Rcm);
+    assert!(registry.remove((1, 4)).is_none());
+    assert!(registry.status().switch_connected_not_rcm);
+    assert_eq!(registry.device_infos().len(), 2);
+}
+
+#[test]
+fn only_one_injection_at_a_time() {
+    let registry = DeviceRegistry::default();
+
+    {
+        let _injecting = registry.begin_injection().unwrap();
+        assert!(registry.is_injecting());
+        assert!(registry.begin_injection().is_none());
+    }
+    assert!(!registry.is_injecting());
+    assert!(registry.begin_injection().is_some());
+}
+
+#[test]
+fn records_what_an_injection_read() {
+    let registry = DeviceRegistry::default();
+    registry.insert(device(1, 4, 0x0955, 0x7321));
+    // Nothing has tried to read the device ID yet.
+    assert!(registry.status().chip_info.is_none());
+    assert_eq!(registry.status().chip_id_read_failed, None);
+
+    registry.record_chip((1, 4), None);
+    assert_eq!(registry.status().chip_id_read_failed, Some(true));
+
+    let chip_info = ChipInfo::from_device_id(&[0x11; 16]).unwrap();
+    registry.record_chip((1, 4), Some(chip_info.clone()));
+    let status = registry.status();
+    assert_eq!(status.chip_info, Some(chip_info));
+    assert_eq!(status.chip_id_read_failed, Some(false));
+
+    // A device that's already gone stays gone.
+    registry.record_chip((1, 5), None);
+    assert_eq!(registry.devices().len(), 1);
+}
@@ -0,0 +1,107 @@
+use whiz_lib::payload::{inspect, PayloadKind};
+use whiz_lib::{build_payload, Intermezzo, MAX_PAYLOAD_SIZE};
+
+/// A payload-sized blob of code-like filler with `strings` dropped in at the end.
+fn fake_payload(length: usize, strings: &[&[u8]]) -> Vec<u8> {
+    let mut data: Vec<u8> = (0..length).map(|i| (i * 31 + 7) as u8).collect();
+    let mut offset = length - strings.iter().map(|s| s.len() + 1).sum::<usize>();
+    for string in strings {
+        data[offset..offset + string.len()].copy_from_slice(string);
+        data[offset + string.len()] = 0;
+        offset += string.len() + 1;
+    }
+    data
+}
+
+#[test]
+fn recognises_hekate_and_its_versions() {
+    let mut data = fake_payload(0x20000, &[b"Nyx v1.6.4"]);
+    data[0x118..0x11F].copy_from_slice(b"ICTC621");
+
+    let info = inspect(&data);
+    assert_eq!(
+        info.kind,
+        PayloadKind::Hekate {
+            version: "6.2.1".to_string(),
+            nyx_version: Some("1.6.4".to_string()),
+        }
+    );
+    assert_eq!(info.description, "hekate v6.2.1 (Nyx v1.6.4)");
+    assert!(info.check().is_ok());
+}
+
+#[test]
+fn recognises_payloads_by_their_strings() {
+    let cases: [(&[&[u8]], PayloadKind); 3] = [
+        (
+            &[b"Lockpick_RCM v1.9.12"],
+            PayloadKind::LockpickRcm {
+                version: Some("1.9.12".to_string()),
+            },
+        ),
+        (&[b"TegraExplorer"], PayloadKind::TegraExplorer),
+        (&[b"fusee", b"Atmosphere"], PayloadKind::Fusee),
+    ];
+
+    for (strings, kind) in cases {
+        assert_eq!(inspect(&fake_payload(0x10000, strings)).kind, kind);
+    }
+}
+
+#[test]
+fn recognises_reboot_to_rcm_stubs() {
+    let mut data = vec![0u8; 0x200];
+    data[0x100..0x104].copy_from_slice(&0x7000E450u32.to_le_bytes());
+
+    assert_eq!(inspect(&data).kind, PayloadKind::RebootToRcm);
+}
+
+#[test]
+fn unknown_payloads_are_allowed() {
+    let info = inspect(&fake_payload(0x8000, &[]));
+
+    assert_eq!(info.kind, PayloadKind::Unknown);
+    assert_eq!(info.headroom, (MAX_PAYLOAD_SIZE - 0x8000) as i64);
+    assert!(info.check().is_ok());
+}
+
+#[test]
+fn obvious_mistakes_are_rejected() {
+    let mut nro = vec![0u8; 0x1000];
+    nro[0x10..0x14].copy_from_slice(b"NRO0");
+    let mut xci = vec![0u8; 0x1000];
+    xci[0x100..0x104].copy_from_slice(b"HEAD");
+
+    let cases = [
+        (Vec::new(), PayloadKind::Empty),
+        (b"PK\x03\x04rest of the archive".to_vec(), PayloadKind::Zip),
+        (b"7z\xBC\xAF\x27\x1Crest".to_vec(), PayloadKind::SevenZip),
+        (nro, PayloadKind::Nro),
+        (b"PFS0 and some entries".to_vec(), PayloadKind::Nsp),
+        (xci, PayloadKind::Xci),
+    ];
+
+    for (data, kind) in cases {
+        let info = inspect(&data);
+        assert_eq!(info.kind, kind);
+        assert!(info.problem.is_some());
+        assert_eq!(info.check().unwrap_err().code(), "PayloadRejected");
+    }
+}
+
+#[test]
+fn headroom_matches_what_build_payload_accepts() {
+    let intermezzo = Intermezzo::builtin().unwrap();
+
+    let largest = fake_payload(MAX_PAYLOAD_SIZE, &[]);
+    let info = inspect(&largest);
+    assert_eq!(info.headroom, 0);
+    assert!(info.check().is_ok());
+    assert!(build_payload(&largest, &intermezzo).is_ok());
+
+    let too_large = fake_payload(MAX_PAYLOAD_SIZE + 1, &[]);
+    let info = inspect(&too_large);
+    assert_eq!(info.headroom, -1);
+    assert_eq!(info.check().unwrap_err().code(), "PayloadTooLarge");
+    assert!(build_payload(&too_large, &intermezzo).is_err());
+}
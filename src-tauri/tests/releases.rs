@@ -0,0 +1,161 @@
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use whiz_lib::releases::{ListingSource, ReleaseClient, ReleaseError};
+
+mod common;
+use common::ScratchDir;
+
+const RELEASES: &str = r#"[{
+    "id": 1,
+    "name": "hekate v6.2.1",
+    "tag_name": "v6.2.1",
+    "published_at": "2024-06-01T00:00:00Z",
+    "draft": false,
+    "assets": [{
+        "id": 10,
+        "name": "hekate_ctcaer_6.2.1.zip",
+        "browser_download_url": "https://example.com/hekate_ctcaer_6.2.1.zip",
+        "size": 1234,
+        "content_type": "application/zip"
+    }]
+}]"#;
+
+/// Answers one request per response, in order, and passes on each request's head.
+fn serve(responses: Vec<String>) -> (String, Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let (requests, received) = mpsc::channel();
+
+    thread::spawn(move || {
+        for response in responses {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0u8; 4096];
+            let read = stream.read(&mut request).unwrap_or(0);
+            let _ = requests.send(String::from_utf8_lossy(&request[..read]).to_lowercase());
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+
+    (url, received)
+}
+
+fn response(status: &str, headers: &[&str], body: &str) -> String {
+    let mut response = format!("HTTP/1.1 {}\r\nConnection: close\r\n", status);
+    for header in headers {
+        response.push_str(&format!("{}\r\n", header));
+    }
+    response.push_str(&format!("Content-Length: {}\r\n\r\n{}", body.len(), body));
+    response
+}
+
+fn client(cache_dir: &Path, api_base: &str) -> ReleaseClient {
+    ReleaseClient {
+        api_base: api_base.to_string(),
+        ..ReleaseClient::new(cache_dir.to_path_buf())
+    }
+}
+
+#[test]
+fn listings_are_cached_and_revalidated_with_their_etag() {
+    let rate_limit = [
+        "X-RateLimit-Limit: 60",
+        "X-RateLimit-Remaining: 59",
+        "X-RateLimit-Reset: 1700000000",
+    ];
+    let (url, requests) = serve(vec![
+        response(
+            "200 OK",
+            &[&["ETag: \"abc\""][..], &rate_limit[..]].concat(),
+            RELEASES,
+        ),
+        response("304 Not Modified", &rate_limit, ""),
+    ]);
+    let cache_dir = ScratchDir::new("releases-etag");
+    let client = ReleaseClient {
+        token: Some("secret".to_string()),
+        ..client(&cache_dir, &url)
+    };
+
+    let first = client.list_releases("CTCaer", "hekate").unwrap();
+    assert_eq!(first.source, ListingSource::Fetched);
+    assert_eq!(first.releases.len(), 1);
+    assert_eq!(first.releases[0].assets[0].name, "hekate_ctcaer_6.2.1.zip");
+    assert_eq!(first.rate_limit.unwrap().remaining, 59);
+
+    let request = requests.recv().unwrap();
+    assert!(request.starts_with("get /repos/ctcaer/hekate/releases"));
+    assert!(request.contains("authorization: bearer secret"));
+    assert!(!request.contains("if-none-match"));
+
+    let second = client.list_releases("CTCaer", "hekate").unwrap();
+    assert_eq!(second.source, ListingSource::NotModified);
+    assert_eq!(second.releases, first.releases);
+    assert!(requests.recv().unwrap().contains("if-none-match: \"abc\""));
+}
+
+#[test]
+fn rate_limits_are_reported_and_fall_back_to_the_cache() {
+    let (url, _requests) = serve(vec![
+        response("200 OK", &[], RELEASES),
+        response(
+            "403 Forbidden",
+            &[
+                "X-RateLimit-Limit: 60",
+                "X-RateLimit-Remaining: 0",
+                "X-RateLimit-Reset: 1700000000",
+            ],
+            r#"{"message": "API rate limit exceeded"}"#,
+        ),
+        response("404 Not Found", &[], r#"{"message": "Not Found"}"#),
+    ]);
+    let cache_dir = ScratchDir::new("releases-rate-limit");
+    let client = client(&cache_dir, &url);
+
+    client.list_releases("CTCaer", "hekate").unwrap();
+
+    let limited = client.list_releases("CTCaer", "hekate").unwrap();
+    assert_eq!(limited.source, ListingSource::Cached);
+    assert_eq!(limited.releases.len(), 1);
+    assert_eq!(limited.rate_limit.unwrap().remaining, 0);
+    assert!(matches!(limited.error, Some(ReleaseError::RateLimited(_))));
+
+    // Without a cache to fall back on, the error is all there is.
+    match client.list_releases("shchmue", "Lockpick_RCM") {
+        Err(ReleaseError::Status { status, message }) => {
+            assert_eq!(status, 404);
+            assert_eq!(message.as_deref(), Some("Not Found"));
+        }
+        other => panic!("expected a 404, got {:?}", other),
+    }
+}
+
+#[test]
+fn offline_mode_only_serves_the_cache() {
+    let (url, requests) = serve(vec![response("200 OK", &[], RELEASES)]);
+    let cache_dir = ScratchDir::new("releases-offline");
+    let online = client(&cache_dir, &url);
+    online.list_releases("CTCaer", "hekate").unwrap();
+    requests.recv().unwrap();
+
+    let offline = ReleaseClient {
+        offline: true,
+        ..online
+    };
+    let listing = offline.list_releases("CTCaer", "hekate").unwrap();
+    assert_eq!(listing.source, ListingSource::Cached);
+    assert_eq!(listing.releases.len(), 1);
+    assert!(listing.error.is_none());
+
+    assert!(matches!(
+        offline.list_releases("suchmememanyskill", "TegraExplorer"),
+        Err(ReleaseError::NotCached { .. })
+    ));
+    assert!(matches!(
+        offline.list_releases("..", "hekate"),
+        Err(ReleaseError::InvalidRepo(_))
+    ));
+    assert!(requests.try_recv().is_err());
+}
@@ -0,0 +1,216 @@
+use whiz_lib::relocator::{assemble, RelocatorParams};
+
+const MEMORY_BASE: u32 = 0x40000000;
+const MEMORY_SIZE: usize = 0x50000;
+
+/// Just enough of an ARM core to run the relocator.
+struct Cpu {
+    registers: [u32; 16],
+    zero: bool,
+    memory: Vec<u8>,
+}
+
+impl Cpu {
+    fn new() -> Self {
+        Self {
+            registers: [0; 16],
+            zero: false,
+            memory: vec![0; MEMORY_SIZE],
+        }
+    }
+
+    fn offset(address: u32) -> usize {
+        (address - MEMORY_BASE) as usize
+    }
+
+    fn load(&mut self, address: u32, data: &[u8]) {
+        let offset = Self::offset(address);
+        self.memory[offset..offset + data.len()].copy_from_slice(data);
+    }
+
+    fn read(&self, address: u32, length: usize) -> &[u8] {
+        let offset = Self::offset(address);
+        &self.memory[offset..offset + length]
+    }
+
+    fn read_u32(&self, address: u32) -> u32 {
+        u32::from_le_bytes(self.read(address, 4).try_into().unwrap())
+    }
+
+    fn write_u32(&mut self, address: u32, value: u32) {
+        self.load(address, &value.to_le_bytes());
+    }
+
+    /// Runs from `start` until something branches to `entry_point`.
+    fn run(&mut self, start: u32, entry_point: u32) {
+        let mut pc = start;
+
+        for _ in 0..1_000_000 {
+            let instruction = self.read_u32(pc);
+            let rd = ((instruction >> 12) & 0xF) as usize;
+            let rn = ((instruction >> 16) & 0xF) as usize;
+            let rm = (instruction & 0xF) as usize;
+            let mut next = pc + 4;
+
+            let condition = instruction >> 28;
+            let executes = match condition {
+                0xE => true,
+                0x1 => !self.zero,
+                _ => panic!("unexpected condition in {:08X}", instruction),
+            };
+
+            if executes {
+                match instruction & 0x0FFFFFFF {
+                    0x01A00000 => {} // nop
+                    i if i & 0x0FFF0000 == 0x059F0000 => {
+                        self.registers[rd] = self.read_u32(pc + 8 + (i & 0xFFF));
+                    }
+                    i if i & 0x0FFF0000 == 0x03A00000 => {
+                        let rotation = (i >> 8) & 0xF;
+                        self.registers[rd] = (i & 0xFF).rotate_right(rotation * 2);
+                    }
+                    i if i & 0x0FE00FF0 == 0x00400000 => {
+                        self.registers[rd] = self.registers[rn] - self.registers[rm];
+                    }
+                    i if i & 0x0FE00FF0 == 0x00800000 => {
+                        self.registers[rd] = self.registers[rn] + self.registers[rm];
+                    }
+                    i if i & 0x0FF00000 == 0x02500000 => {
+                        self.registers[rd] = self.registers[rn] - (i & 0xFF);
+                        self.zero = self.registers[rd] == 0;
+                    }
+                    i if i & 0x0FF00FFF == 0x04900004 => {
+                        self.registers[rd] = self.read_u32(self.registers[rn]);
+                        self.registers[rn] += 4;
+                    }
+                    i if i & 0x0FF00FFF == 0x04800004 => {
+                        self.write_u32(self.registers[rn], self.registers[rd]);
+                        self.registers[rn] += 4;
+                    }
+                    i if i & 0x0E000000 == 0x0A000000 => {
+                        if i & 0x01000000 != 0 {
+                            self.registers[14] = pc + 4;
+                        }
+                        let displacement = ((i << 8) as i32 >> 6) as u32;
+                        next = pc.wrapping_add(8).wrapping_add(displacement);
+                    }
+                    i if i & 0x0FFFFFF0 == 0x012FFF10 => {
+                        next = self.registers[rm];
+                        if next == entry_point {
+                            return;
+                        }
+                    }
+                    _ => panic!("unexpected instruction {:08X} at {:08X}", instruction, pc),
+                }
+            }
+
+            pc = next;
+        }
+
+        panic!("the relocator never reached the entry point");
+    }
+}
+
+fn test_data(length: usize, seed: u8) -> Vec<u8> {
+    (0..length)
+        .map(|i| (i as u8).wrapping_mul(13) ^ seed)
+        .collect()
+}
+
+/// Loads the stub and a payload split around the spray, runs the stub and
+/// returns where the payload ended up.
+fn relocate(params: &RelocatorParams) -> (Cpu, Vec<u8>) {
+    let stub = assemble(params).unwrap();
+    let head = test_data(params.head_length as usize, 0x5A);
+    let tail = test_data(params.tail_length as usize, 0xA5);
+
+    let mut cpu = Cpu::new();
+    cpu.load(params.load_address, &stub);
+    cpu.load(params.head_source, &head);
+    cpu.load(params.tail_source, &tail);
+    cpu.run(params.load_address, params.entry_point);
+
+    (cpu, [head, tail].concat())
+}
+
+#[test]
+fn default_layout_matches_the_shipped_blob() {
+    let shipped = std::fs::read(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../assets/intermezzo.bin"
+    ))
+    .unwrap();
+
+    assert_eq!(assemble(&RelocatorParams::default()).unwrap(), shipped);
+}
+
+#[test]
+fn default_layout_moves_the_payload_into_place() {
+    // Keep the tail inside our memory model.
+    let params = RelocatorParams {
+        tail_length: 0x8000,
+        ..RelocatorParams::default()
+    };
+    let (cpu, payload) = relocate(&params);
+
+    assert_eq!(cpu.read(params.destination, payload.len()), payload);
+}
+
+#[test]
+fn payload_can_be_linked_elsewhere() {
+    // Sized like the CLI sizes it. A 0x7FFC tail doesn't fit in an immediate, so
+    // this also goes through the literal pool.
+    let params = RelocatorParams::linked_at(0x40030000, 0xBFFA);
+    assert_eq!(params.tail_length, 0x7FFC);
+    let (cpu, payload) = relocate(&params);
+
+    assert_ne!(assemble(&params).unwrap().len(), 124);
+    assert_eq!(cpu.read(0x40030000, payload.len()), payload);
+}
+
+#[test]
+fn payloads_shorter_than_the_head_still_copy_a_tail() {
+    let params = RelocatorParams::linked_at(0x40030000, 0x100);
+
+    assert_eq!(params.tail_length, 4);
+    assert!(assemble(&params).is_ok());
+}
+
+#[test]
+fn unaligned_addresses_are_rejected() {
+    let error = assemble(&RelocatorParams::linked_at(0x40030002, 0x8000)).unwrap_err();
+    assert!(error.contains("word-aligned"), "{}", error);
+}
+
+#[test]
+fn empty_copies_are_rejected() {
+    let params = RelocatorParams {
+        tail_length: 0,
+        ..RelocatorParams::default()
+    };
+
+    let error = assemble(&params).unwrap_err();
+    assert!(error.contains("tail length"), "{}", error);
+}
+
+#[test]
+fn relocating_over_the_payload_is_rejected() {
+    let params = RelocatorParams {
+        relocation_address: 0x40012000,
+        ..RelocatorParams::default()
+    };
+
+    let error = assemble(&params).unwrap_err();
+    assert!(
+        error.contains("overlap the payload destination"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn copying_over_unread_payload_is_rejected() {
+    // The head would land on the tail before the tail is copied.
+    let error = assemble(&RelocatorParams::linked_at(0x40020000, 0x20000)).unwrap_err();
+    assert!(error.contains("overwrite it"), "{}", error);
+}
@@ -0,0 +1,157 @@
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::sync::mpsc;
+use std::thread;
+use whiz_lib::releases::{Release, ReleaseAsset, ReleaseSettings};
+use whiz_lib::sources::{default_sources, PayloadSource, SourceLocation};
+
+mod common;
+use common::ScratchDir;
+
+fn asset(name: &str) -> ReleaseAsset {
+    ReleaseAsset {
+        id: 1,
+        name: name.to_string(),
+        browser_download_url: format!("https://example.com/{}", name),
+        size: 100,
+    }
+}
+
+fn custom_source(location: SourceLocation) -> PayloadSource {
+    PayloadSource {
+        id: "internal".to_string(),
+        name: "Internal builds".to_string(),
+        location,
+        asset_pattern: "payload-*.bin".to_string(),
+        archive_member: None,
+        checksum_asset: Some("SHA256SUMS".to_string()),
+    }
+}
+
+#[test]
+fn default_sources_are_valid() {
+    let sources = default_sources();
+    for source in &sources {
+        source.validate().unwrap();
+    }
+
+    let ids: HashSet<&str> = sources.iter().map(|source| source.id.as_str()).collect();
+    assert_eq!(ids.len(), sources.len());
+    assert!(ids.contains("hekate"));
+
+    let mut broken = sources[0].clone();
+    broken.location = SourceLocation::Repo {
+        owner: "../..".to_string(),
+        repo: "hekate".to_string(),
+        api_base: None,
+    };
+    assert!(broken.validate().is_err());
+
+    let broken = custom_source(SourceLocation::Url {
+        url: "file:///etc/passwd".to_string(),
+    });
+    assert!(broken.validate().is_err());
+
+    for id in ["..", "../releases", "a/b", ".hidden"] {
+        let mut broken = sources[0].clone();
+        broken.id = id.to_string();
+        assert!(broken.validate().is_err(), "{:?} was allowed", id);
+    }
+}
+
+#[test]
+fn sources_pick_their_asset_and_checksum_out_of_a_release() {
+    let hekate = &default_sources()[0];
+    let release = Release {
+        id: 1,
+        name: Some("hekate v6.2.1".to_string()),
+        tag_name: "v6.2.1".to_string(),
+        published_at: None,
+        prerelease: false,
+        assets: vec![
+            asset("nyx_usb_max_rate__run_only_once_per_windows_pc.reg"),
+            asset("hekate_ctcaer_6.2.1_Nyx_1.6.1.zip"),
+        ],
+    };
+    let selected = hekate.select(&release).unwrap();
+    assert_eq!(selected.asset.name, "hekate_ctcaer_6.2.1_Nyx_1.6.1.zip");
+    assert_eq!(selected.checksum_url, None);
+
+    let internal = custom_source(SourceLocation::Repo {
+        owner: "team".to_string(),
+        repo: "payloads".to_string(),
+        api_base: None,
+    });
+    assert_eq!(internal.select(&release), None);
+
+    let release = Release {
+        assets: vec![asset("SHA256SUMS"), asset("payload-1.2.bin")],
+        ..release
+    };
+    let selected = internal.select(&release).unwrap();
+    assert_eq!(selected.asset.name, "payload-1.2.bin");
+    assert_eq!(
+        selected.checksum_url.as_deref(),
+        Some("https://example.com/SHA256SUMS")
+    );
+}
+
+#[test]
+fn url_sources_have_one_release() {
+    let source = custom_source(SourceLocation::Url {
+        url: "https://builds.example.com/rcm/payload-nightly.bin?token=x".to_string(),
+    });
+    let listing = source.list(std::path::Path::new("unused"), &ReleaseSettings::default());
+
+    assert!(listing.error.is_none());
+    assert_eq!(listing.releases.len(), 1);
+    let release = &listing.releases[0];
+    assert_eq!(release.asset.name, "payload-nightly.bin");
+    assert_eq!(
+        release.checksum_url.as_deref(),
+        Some("https://builds.example.com/rcm/SHA256SUMS")
+    );
+}
+
+#[test]
+fn github_tokens_are_not_sent_to_other_servers() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let api_base = format!("http://{}/api/v1", listener.local_addr().unwrap());
+    let (requests, received) = mpsc::channel();
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = [0u8; 4096];
+        let read = stream.read(&mut request).unwrap_or(0);
+        let _ = requests.send(String::from_utf8_lossy(&request[..read]).to_lowercase());
+        let body = r#"[{"id": 1, "name": null, "tag_name": "v1", "published_at": null,
+            "assets": [{"id": 2, "name": "payload-1.bin", "size": 10,
+                        "browser_download_url": "http://example.com/payload-1.bin"}]}]"#;
+        let _ = write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+    });
+
+    let cache_dir = ScratchDir::new("sources-list");
+    let source = custom_source(SourceLocation::Repo {
+        owner: "team".to_string(),
+        repo: "payloads".to_string(),
+        api_base: Some(api_base),
+    });
+    let settings = ReleaseSettings {
+        github_token: Some("secret".to_string()),
+        offline: false,
+    };
+
+    let listing = source.list(&cache_dir, &settings);
+    assert!(listing.error.is_none(), "{:?}", listing.error);
+    assert_eq!(listing.releases.len(), 1);
+    assert_eq!(listing.releases[0].asset.name, "payload-1.bin");
+
+    let request = received.recv().unwrap();
+    assert!(request.starts_with("get /api/v1/repos/team/payloads/releases"));
+    assert!(!request.contains("authorization"));
+}
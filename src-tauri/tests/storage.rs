@@ -0,0 +1,42 @@
+use std::path::PathBuf;
+use whiz_lib::storage::{Storage, StorageSettings};
+
+#[test]
+fn portable_storage_keeps_everything_together() {
+    let root = std::env::temp_dir().join("jolt-data");
+    let storage = Storage::portable(root.clone());
+
+    assert_eq!(storage.settings_store(), root.join("settings.json"));
+    assert!(storage.cache_dir.starts_with(&root));
+    assert!(storage.log_dir.starts_with(&root));
+    assert_eq!(
+        storage.library_dir(&StorageSettings::default()),
+        root.join("payloads")
+    );
+
+    // Relative locations stay with the data; absolute ones go where they say.
+    let relative = StorageSettings {
+        library_dir: Some("mine".to_string()),
+    };
+    assert_eq!(storage.library_dir(&relative), root.join("mine"));
+    let absolute = std::env::temp_dir().join("elsewhere");
+    let settings = StorageSettings {
+        library_dir: Some(absolute.to_string_lossy().to_string()),
+    };
+    assert_eq!(storage.library_dir(&settings), absolute);
+    let blank = StorageSettings {
+        library_dir: Some("  ".to_string()),
+    };
+    assert_eq!(storage.library_dir(&blank), root.join("payloads"));
+}
+
+#[test]
+fn installed_storage_uses_the_store_plugins_directory() {
+    let storage = Storage {
+        portable: false,
+        data_dir: PathBuf::from("/data"),
+        cache_dir: PathBuf::from("/cache"),
+        log_dir: PathBuf::from("/logs"),
+    };
+    assert_eq!(storage.settings_store(), PathBuf::from("settings.json"));
+}
@@ -0,0 +1,103 @@
+use whiz_lib::emulator::{EmulatorState, RcmEmulator};
+use whiz_lib::strategy::{Delivered, Delivery, ExploitStrategy, StrategyRegistry};
+use whiz_lib::{
+    execute_exploit, ignore_progress, Intermezzo, JoltError, RCMHax, Transport, RCM_PAYLOAD_ADDR,
+};
+
+fn test_payload() -> Vec<u8> {
+    (0..0x8000).map(|i| (i * 7 + 3) as u8).collect()
+}
+
+/// A strategy that gives up without touching the device.
+struct GivesUp(&'static str);
+
+impl<T: Transport> ExploitStrategy<T> for GivesUp {
+    fn id(&self) -> &'static str {
+        self.0
+    }
+
+    fn name(&self) -> &'static str {
+        "Gives up"
+    }
+
+    fn description(&self) -> &'static str {
+        "Never works."
+    }
+
+    fn deliver(&self, _: Delivery<'_, T>) -> Result<Delivered, JoltError> {
+        Err(JoltError::StrategyFailed {
+            strategy: self.0.to_string(),
+            reason: "gave up".to_string(),
+        })
+    }
+}
+
+#[test]
+fn strategies_resolve_in_the_order_given() {
+    let registry = <StrategyRegistry>::builtin();
+    let infos = registry.infos();
+    assert_eq!(infos[0].id, "fusee-launcher");
+    assert!(infos[0].default);
+    assert_eq!(infos.iter().filter(|info| info.default).count(), 1);
+
+    let ids = |order: &[&str]| -> Result<Vec<&str>, JoltError> {
+        let order: Vec<String> = order.iter().map(|id| id.to_string()).collect();
+        Ok(registry.resolve(&order)?.iter().map(|s| s.id()).collect())
+    };
+    assert_eq!(ids(&[]).unwrap(), ["fusee-launcher"]);
+    assert_eq!(
+        ids(&["device-reset", "fusee-launcher", "device-reset"]).unwrap(),
+        ["device-reset", "fusee-launcher"]
+    );
+    assert!(matches!(
+        ids(&["fusee-launcher", "nope"]),
+        Err(JoltError::UnknownStrategy(id)) if id == "nope"
+    ));
+}
+
+#[test]
+fn injection_falls_back_to_the_next_strategy() {
+    let emulator = RcmEmulator::default();
+    let mut registry = StrategyRegistry::builtin();
+    registry.register(GivesUp("gives-up"));
+    let order = ["gives-up".to_string(), "fusee-launcher".to_string()];
+
+    let report = execute_exploit(
+        RCMHax::from_transport(&emulator),
+        "payload.bin",
+        &test_payload(),
+        &Intermezzo::builtin().unwrap(),
+        &registry.resolve(&order).unwrap(),
+        &ignore_progress,
+    )
+    .unwrap();
+
+    assert_eq!(report.strategy, "fusee-launcher");
+    assert_eq!(
+        emulator.state(),
+        EmulatorState::Smashed {
+            return_address: RCM_PAYLOAD_ADDR
+        }
+    );
+}
+
+#[test]
+fn injection_reports_the_first_failure_when_every_strategy_fails() {
+    let emulator = RcmEmulator::default();
+    let first = GivesUp("first");
+    let second = GivesUp("second");
+
+    let result = execute_exploit(
+        RCMHax::from_transport(&emulator),
+        "payload.bin",
+        &test_payload(),
+        &Intermezzo::builtin().unwrap(),
+        &[&first, &second],
+        &ignore_progress,
+    );
+
+    assert!(matches!(
+        result,
+        Err(JoltError::StrategyFailed { strategy, .. }) if strategy == "first"
+    ));
+}
@@ -0,0 +1,113 @@
+use std::time::Duration;
+
+use whiz_lib::verify::{self, BusDevice, Observation, VerificationStatus, Watcher};
+
+fn device(address: u8, port_path: &str, vendor_id: u16, product_id: u16) -> BusDevice {
+    BusDevice {
+        bus: 1,
+        address,
+        port_path: Some(port_path.to_string()),
+        vendor_id,
+        product_id,
+    }
+}
+
+fn rcm(address: u8) -> BusDevice {
+    device(address, "1-2", 0x0955, 0x7321)
+}
+
+fn mouse() -> BusDevice {
+    device(3, "1-4", 0x046D, 0xC52B)
+}
+
+#[test]
+fn recognises_what_payloads_bring_up() {
+    assert_eq!(
+        verify::recognise(0x11EC, 0xA7E0),
+        Some("hekate USB mass storage")
+    );
+    assert_eq!(verify::recognise(0x11EC, 0x1234), Some("hekate"));
+    assert_eq!(verify::recognise(0x057E, 0x2000), Some("a booted Switch"));
+    assert_eq!(verify::recognise(0x0955, 0x7321), None);
+    assert_eq!(verify::recognise(0x046D, 0xC52B), None);
+}
+
+#[test]
+fn a_payload_device_on_the_same_port_verifies_the_injection() {
+    let before = [rcm(5), mouse(), device(9, "1-3", 0x057E, 0x2000)];
+    let mut watcher = Watcher::new((1, 5), &before);
+
+    // A Switch that was already booted elsewhere doesn't count, and nor does the
+    // device going away by itself.
+    assert_eq!(watcher.observe(&before), None);
+    assert_eq!(watcher.observe(&[mouse()]), None);
+    assert_eq!(
+        watcher.observe(&[mouse(), device(10, "1-3", 0x057E, 0x2000)]),
+        None
+    );
+    assert_eq!(watcher.conclude(), Observation::Vanished);
+
+    let appeared = watcher
+        .observe(&[mouse(), device(6, "1-2", 0x11EC, 0xA7E0)])
+        .unwrap();
+    assert_eq!(
+        appeared,
+        Observation::KnownDeviceAppeared {
+            name: "hekate USB mass storage".to_string(),
+            vendor_id: 0x11EC,
+            product_id: 0xA7E0,
+        }
+    );
+    assert_eq!(appeared.status(), VerificationStatus::Verified);
+}
+
+#[test]
+fn coming_back_in_rcm_fails_the_injection() {
+    let before = [rcm(5), mouse()];
+
+    let mut watcher = Watcher::new((1, 5), &before);
+    assert_eq!(watcher.observe(&[mouse()]), None);
+    let observation = watcher.observe(&[rcm(7), mouse()]).unwrap();
+    assert_eq!(observation, Observation::ReturnedToRcm);
+    assert_eq!(observation.status(), VerificationStatus::Failed);
+
+    // Re-enumerating between looks is caught too.
+    let mut watcher = Watcher::new((1, 5), &before);
+    assert_eq!(
+        watcher.observe(&[rcm(8), mouse()]),
+        Some(Observation::ReturnedToRcm)
+    );
+}
+
+#[test]
+fn a_device_that_stays_in_rcm_fails_the_injection() {
+    let before = vec![rcm(5), mouse()];
+    let mut looks = 0;
+
+    let verification = verify::watch(
+        Watcher::new((1, 5), &before),
+        Duration::from_millis(20),
+        Duration::from_millis(1),
+        || {
+            looks += 1;
+            Ok(before.clone())
+        },
+    );
+
+    assert!(looks > 1);
+    assert_eq!(verification.observation, Observation::StillInRcm);
+    assert_eq!(verification.status, VerificationStatus::Failed);
+    assert!(verification.elapsed_ms >= 20.0);
+}
+
+#[test]
+fn verification_can_be_turned_off() {
+    let verification = verify::verify(
+        Some(Watcher::new((1, 5), &[rcm(5)])),
+        Duration::ZERO,
+        &whiz_lib::ignore_progress,
+    );
+
+    assert_eq!(verification.observation, Observation::NotWatched);
+    assert_eq!(verification.status, VerificationStatus::Unverified);
+}
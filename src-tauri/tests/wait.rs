@@ -0,0 +1,40 @@
+use std::time::Duration;
+use whiz_lib::wait::{DeviceWait, WaitState};
+
+#[test]
+fn clones_share_cancellation() {
+    let wait = DeviceWait::new(Some(Duration::from_secs(5)));
+    let waiting = wait.clone();
+
+    wait.cancel();
+    assert!(waiting.is_cancelled());
+    assert_eq!(waiting.timeout(), Some(Duration::from_secs(5)));
+}
+
+#[test]
+fn a_new_wait_cancels_the_previous_one() {
+    let waits = WaitState::default();
+    let first = DeviceWait::new(None);
+    let second = DeviceWait::new(None);
+
+    waits.begin(&first);
+    waits.begin(&second);
+    assert!(first.is_cancelled());
+    assert!(!second.is_cancelled());
+
+    // The first wait finishing mustn't forget about the second.
+    waits.finish(&first);
+    assert!(waits.cancel());
+    assert!(second.is_cancelled());
+}
+
+#[test]
+fn cancelling_with_nothing_waiting_does_nothing() {
+    let waits = WaitState::default();
+    let wait = DeviceWait::new(None);
+
+    waits.begin(&wait);
+    waits.finish(&wait);
+    assert!(!waits.cancel());
+    assert!(!wait.is_cancelled());
+}
@@ -2,6 +2,7 @@
 
 import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { open as openDialog } from "@tauri-apps/plugin-dialog";
 import { Button } from "@/components/ui/button";
 import { Input } from "@/components/ui/input";
@@ -9,6 +10,12 @@ import { ModeToggle } from "@/components/ui/mode-toggle";
 import { Zap, Usb, AlertCircle, CheckCircle, Loader2, Syringe, LoaderPinwheel, FolderSearch, CircleX, Undo2, Globe, Lock, Unlock } from "lucide-react";
 import { ButtonGroup } from "@/components/ui/button-group";
 import { FetchPayloads } from "@/components/fetch-payloads";
+import { PayloadLibrary } from "@/components/payload-library";
+import { AutoInject } from "@/components/auto-inject";
+import { InjectionHistory } from "@/components/injection-history";
+import { LogViewer } from "@/components/log-viewer";
+import { ExploitStrategies } from "@/components/exploit-strategies";
+import { VerificationWindow } from "@/components/verification-window";
 import {
   InputGroup,
   InputGroupAddon,
@@ -25,20 +32,111 @@ interface DeviceInfo {
   serial_number?: string;
 }
 
+interface PayloadInfo {
+  description: string;
+  size: number;
+  max_size: number;
+  headroom: number;
+  problem?: string;
+}
+
+interface ChipInfo {
+  uid: string;
+  lot?: string;
+  wafer_id: number;
+  x_coordinate: number;
+  y_coordinate: number;
+}
+
 interface RcmStatus {
   device_connected: boolean;
   device_info?: DeviceInfo;
   rcm_detected: boolean;
   switch_connected_not_rcm: boolean;
+  chip_info?: ChipInfo;
+  // null until something has tried to read the device ID.
+  chip_id_read_failed?: boolean | null;
 }
 
+type InjectionProgress =
+  | { phase: "opening_device" }
+  | { phase: "waiting_for_device"; timeout_secs?: number }
+  | { phase: "claiming_interface"; interface: number }
+  | { phase: "reading_device_id" }
+  | { phase: "building_payload" }
+  | { phase: "trying_strategy"; strategy: string }
+  | { phase: "uploading"; buffers_written: number; buffers_total: number; bytes_written: number; bytes_total: number }
+  | { phase: "switching_to_high_buffer" }
+  | { phase: "triggering" }
+  | { phase: "verifying"; window_secs: number };
+
+const PHASE_LABELS: Record<InjectionProgress["phase"], string> = {
+  opening_device: "opening device...",
+  waiting_for_device: "waiting for a switch in RCM...",
+  claiming_interface: "claiming interface...",
+  reading_device_id: "reading device ID...",
+  building_payload: "building payload...",
+  trying_strategy: "trying strategy...",
+  uploading: "uploading payload...",
+  switching_to_high_buffer: "switching to high buffer...",
+  triggering: "smashing the stack...",
+  verifying: "checking the payload is running...",
+};
+
+interface JoltError {
+  code: string;
+  message: string;
+  usb_error?: string;
+}
+
+interface InjectionReport {
+  chip_info?: ChipInfo;
+  device_id_read_failed: boolean;
+  payload_path: string;
+  payload_sha256: string;
+  payload_size: number;
+  rcm_stream_size: number;
+  buffers_written: number;
+  padded_to_high_buffer: boolean;
+  trigger: "timed_out" | "returned" | "submitted" | "unresponsive";
+  strategy: string;
+  timings: { total_ms: number };
+  verification: {
+    status: "verified" | "unverified" | "failed";
+    observation:
+      | { kind: "not_watched" | "still_in_rcm" | "vanished" | "returned_to_rcm" }
+      | { kind: "known_device_appeared"; name: string; vendor_id: number; product_id: number };
+  };
+}
+
+// What the bus said after the smash, for the message after an injection.
+const describeVerification = ({ status, observation }: InjectionReport["verification"]) => {
+  switch (observation.kind) {
+    case "known_device_appeared":
+      return `Verified: ${observation.name} showed up.`;
+    case "vanished":
+      return "The Switch left RCM, so the payload is probably running.";
+    case "still_in_rcm":
+      return "The Switch is still in RCM - it may be wedged. Try again after re-entering RCM.";
+    case "returned_to_rcm":
+      return "The Switch went back to RCM - the payload probably crashed.";
+    default:
+      return status === "verified" ? "Verified." : "Check your Switch - it should be running the payload now.";
+  }
+};
+
+// How long "inject when ready" waits for a device before giving up.
+const WAIT_FOR_DEVICE_TIMEOUT_SECS = 120;
+
 export default function Home() {
   const [rcmStatus, setRcmStatus] = useState<RcmStatus | null>(null);
   const [isManuallyScanning, setIsManuallyScanning] = useState(false);
   const [selectedPayload, setSelectedPayload] = useState<string>("");
+  const [payloadInfo, setPayloadInfo] = useState<PayloadInfo | null>(null);
   const [usbDevices, setUsbDevices] = useState<DeviceInfo[]>([]);
   const [showDevices, setShowDevices] = useState(false);
   const [isInjecting, setIsInjecting] = useState(false);
+  const [injectionProgress, setInjectionProgress] = useState<InjectionProgress | null>(null);
   const [version, setVersion] = useState<string>("");
 
   // State for external links history and current index
@@ -105,8 +203,8 @@ export default function Home() {
     setIsManuallyScanning(false);
   };
 
-  const injectPayload = async () => {
-    if (!selectedPayload || !rcmStatus?.rcm_detected) {
+  const injectPayload = async (whenReady = false) => {
+    if (!selectedPayload || (!whenReady && !rcmStatus?.rcm_detected)) {
       return;
     }
 
@@ -116,30 +214,82 @@ export default function Home() {
     }
 
     setIsInjecting(true);
+    setInjectionProgress(null);
     try {
-      const result: string = await invoke("inject_payload", { payloadPath: selectedPayload });
-      alert(`Success: ${result}`);
+      const report: InjectionReport = whenReady
+        ? await invoke("inject_when_ready", {
+            payloadPath: selectedPayload,
+            timeoutSecs: WAIT_FOR_DEVICE_TIMEOUT_SECS,
+          })
+        : await invoke("inject_payload", { payloadPath: selectedPayload });
+      alert(
+        `${report.verification.status === "failed" ? "Payload sent, but it doesn't seem to be running." : "Payload injected!"}\n` +
+        `${describeVerification(report.verification)}\n` +
+        `Sent ${report.buffers_written} buffers in ${Math.round(report.timings.total_ms)} ms with ${report.strategy}` +
+        (report.chip_info ? `\nDevice ID: ${report.chip_info.uid}` : "")
+      );
     } catch (error) {
-      alert(`Injection failed: ${error}`);
+      const { code, message } = error as JoltError;
+      if (code === "WaitCancelled") {
+        return;
+      }
+      alert(code === "AccessDenied"
+        ? `Injection failed: ${message}\nOn Linux, make sure your user can access the device (udev rules).`
+        : `Injection failed: ${message ?? error}`);
     } finally {
       setIsInjecting(false);
+      setInjectionProgress(null);
+      // The injection is what reads the device ID, so pick up what it found.
+      scanForDevice();
     }
   };
 
+  const cancelWaitForDevice = async () => {
+    try {
+      await invoke("cancel_wait_for_device");
+    } catch (error) {
+      console.error("Failed to cancel waiting for the device:", error);
+    }
+  };
+
+  useEffect(() => {
+    const unlisten = listen<InjectionProgress>("injection-progress", (event) => {
+      setInjectionProgress(event.payload);
+    });
+    return () => {
+      unlisten.then((f) => f());
+    };
+  }, []);
+
+  useEffect(() => {
+    if (!selectedPayload) {
+      setPayloadInfo(null);
+      return;
+    }
+
+    invoke<PayloadInfo>("inspect_payload", { payloadPath: selectedPayload })
+      .then(setPayloadInfo)
+      .catch((error) => {
+        console.error("Failed to inspect payload:", error);
+        setPayloadInfo(null);
+      });
+  }, [selectedPayload]);
+
   useEffect(() => {
     scanForDevice();
     listUsbDevices();
-    
-    const interval = setInterval(() => {
-      if (!isInjecting) {
-        scanForDevice();
-        if (showDevices) {
-          listUsbDevices();
-        }
-      }
-    }, 2000);
-    return () => clearInterval(interval);
-  }, [isInjecting, showDevices]);
+
+    // The backend watches the bus and tells us when something changes.
+    const unlisteners = [
+      ...["rcm-connected", "rcm-disconnected", "switch-connected", "switch-disconnected"].map((event) =>
+        listen(event, () => scanForDevice())
+      ),
+      listen<DeviceInfo[]>("usb-devices-changed", (event) => setUsbDevices(event.payload)),
+    ];
+    return () => {
+      unlisteners.forEach((unlisten) => unlisten.then((f) => f()));
+    };
+  }, []);
 
   const goBackExternal = () => {
     // Close the mini-browser and return to main app view
@@ -176,7 +326,9 @@ export default function Home() {
 
   return (
     <main className="flex min-h-screen bg-background items-center justify-center relative p-4">
-      <div className="absolute top-4 right-4">
+      <div className="absolute top-4 right-4 flex gap-2">
+        <InjectionHistory />
+        <LogViewer />
         <ModeToggle />
       </div>
 
@@ -231,7 +383,18 @@ export default function Home() {
               )}
               <div className="flex flex-col gap-1">
                 {rcmStatus?.rcm_detected
-                  ? "Switch in RCM mode detected"
+                  ? (
+                      <>
+                        <span>Switch in RCM mode detected</span>
+                        <span className="text-sm text-muted-foreground font-mono">
+                          {rcmStatus.chip_info
+                            ? `device ID: ${rcmStatus.chip_info.uid}`
+                            : rcmStatus.chip_id_read_failed
+                              ? "couldn't read the device ID"
+                              : "device ID: unknown until injecting"}
+                        </span>
+                      </>
+                    )
                   : rcmStatus?.switch_connected_not_rcm
                     ? (
                         <>
@@ -319,23 +482,66 @@ export default function Home() {
               >
                 <FolderSearch /> browse
               </Button>
-                <FetchPayloads onSelectPayload={setSelectedPayload} />
+                <FetchPayloads selectedPayload={selectedPayload} onSelectPayload={setSelectedPayload} />
+                <PayloadLibrary selectedPayload={selectedPayload} onSelectPayload={setSelectedPayload} />
             </ButtonGroup>
 
-            <Button
-              onClick={injectPayload}
-              disabled={!rcmStatus?.rcm_detected || !selectedPayload || isInjecting}
-              className="w-full"
-            >
-              {isInjecting ? (
-                <LoaderPinwheel size={16} className="animate-spin" />
-              ) : (
-                <>
-                  <Syringe size={16} className="mr-2" />
-                  inject payload
-                </>
-              )}
-            </Button>
+            {payloadInfo && (
+              <div className="text-sm text-muted-foreground">
+                {payloadInfo.problem ? (
+                  <span className="text-red-500">{payloadInfo.description}: {payloadInfo.problem}</span>
+                ) : (
+                  <span>
+                    {payloadInfo.description} ({payloadInfo.size.toLocaleString()} bytes,{" "}
+                    {payloadInfo.headroom.toLocaleString()} bytes to spare)
+                  </span>
+                )}
+              </div>
+            )}
+
+            {isInjecting && injectionProgress && (
+              <div className="space-y-1">
+                <div className="text-sm text-muted-foreground">
+                  {PHASE_LABELS[injectionProgress.phase]}
+                  {injectionProgress.phase === "trying_strategy" && ` ${injectionProgress.strategy}`}
+                  {injectionProgress.phase === "uploading" &&
+                    ` ${injectionProgress.buffers_written}/${injectionProgress.buffers_total}`}
+                </div>
+                {injectionProgress.phase === "uploading" && (
+                  <div className="h-2 w-full rounded bg-muted">
+                    <div
+                      className="h-2 rounded bg-primary transition-all"
+                      style={{ width: `${(100 * injectionProgress.bytes_written) / injectionProgress.bytes_total}%` }}
+                    />
+                  </div>
+                )}
+              </div>
+            )}
+
+            <AutoInject selectedPayload={selectedPayload} chipUid={rcmStatus?.chip_info?.uid} />
+            <ExploitStrategies />
+            <VerificationWindow />
+
+            {injectionProgress?.phase === "waiting_for_device" ? (
+              <Button onClick={cancelWaitForDevice} variant="outline" className="w-full">
+                stop waiting
+              </Button>
+            ) : (
+              <Button
+                onClick={() => injectPayload(!rcmStatus?.rcm_detected)}
+                disabled={!selectedPayload || !!payloadInfo?.problem || isInjecting}
+                className="w-full"
+              >
+                {isInjecting ? (
+                  <LoaderPinwheel size={16} className="animate-spin" />
+                ) : (
+                  <>
+                    <Syringe size={16} className="mr-2" />
+                    {rcmStatus?.rcm_detected ? "inject payload" : "inject when ready"}
+                  </>
+                )}
+              </Button>
+            )}
           </div>
         </div>
       </div>
@@ -0,0 +1,128 @@
+'use client';
+import { Zap, X } from "lucide-react";
+import { Button } from "./ui/button";
+import { useState, useEffect } from 'react';
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+
+type DeviceMatch =
+    | { by: "chip_uid"; uid: string }
+    | { by: "port_path"; port_path: string };
+
+interface AutoInjectRule {
+    match: DeviceMatch;
+    payload_path: string;
+}
+
+interface AutoInjectSettings {
+    enabled: boolean;
+    default_payload?: string | null;
+    rules: AutoInjectRule[];
+}
+
+interface AutoInjectOutcome {
+    device: { port_path?: string; chip_info?: { uid: string } };
+    payload_path: string;
+    report?: { buffers_written: number; verification: { status: "verified" | "unverified" | "failed" } };
+    error?: { code: string; message: string };
+}
+
+const fileName = (path: string) => path.split(/[\\/]/).pop() ?? path;
+
+const describeMatch = (match: DeviceMatch) =>
+    match.by === "chip_uid" ? `console ${match.uid.slice(0, 8)}…` : `port ${match.port_path}`;
+
+interface AutoInjectProps {
+    selectedPayload: string;
+    // The console currently in RCM, if we know it.
+    chipUid?: string;
+}
+
+export function AutoInject({ selectedPayload, chipUid }: AutoInjectProps) {
+    const [settings, setSettings] = useState<AutoInjectSettings | null>(null);
+    const [lastOutcome, setLastOutcome] = useState<string | null>(null);
+
+    useEffect(() => {
+        invoke<AutoInjectSettings>("get_auto_inject_settings")
+            .then(setSettings)
+            .catch((error) => console.error("Failed to load auto-inject settings:", error));
+
+        const unlisten = listen<AutoInjectOutcome>("auto-inject", (event) => {
+            const { payload_path, report, error } = event.payload;
+            setLastOutcome(error
+                ? `auto-inject of ${fileName(payload_path)} failed: ${error.message}`
+                : `auto-injected ${fileName(payload_path)} (${report?.verification.status ?? "unverified"})`);
+        });
+        return () => {
+            unlisten.then((f) => f());
+        };
+    }, []);
+
+    const save = async (next: AutoInjectSettings) => {
+        try {
+            await invoke("set_auto_inject_settings", { settings: next });
+            setSettings(next);
+        } catch (error) {
+            alert(`Couldn't save auto-inject settings: ${error}`);
+        }
+    };
+
+    if (!settings) {
+        return null;
+    }
+
+    const toggle = () => save({
+        ...settings,
+        enabled: !settings.enabled,
+        // Turning it on with a payload selected makes that the default.
+        default_payload: !settings.enabled && selectedPayload ? selectedPayload : settings.default_payload,
+    });
+
+    const addRuleForThisConsole = () => chipUid && save({
+        ...settings,
+        rules: [
+            ...settings.rules.filter((rule) => !(rule.match.by === "chip_uid" && rule.match.uid === chipUid)),
+            { match: { by: "chip_uid", uid: chipUid }, payload_path: selectedPayload },
+        ],
+    });
+
+    const removeRule = (index: number) => save({
+        ...settings,
+        rules: settings.rules.filter((_, i) => i !== index),
+    });
+
+    return (
+        <div className="space-y-2 text-sm">
+            <div className="flex items-center gap-2">
+                <Button variant={settings.enabled ? "default" : "outline"} size="sm" onClick={toggle}>
+                    <Zap size={14} className="mr-1" />
+                    auto-inject {settings.enabled ? "on" : "off"}
+                </Button>
+                {settings.enabled && settings.default_payload && (
+                    <span className="text-muted-foreground truncate">
+                        {fileName(settings.default_payload)} by default
+                    </span>
+                )}
+            </div>
+
+            {settings.rules.map((rule, index) => (
+                <div key={index} className="flex items-center gap-2 text-muted-foreground">
+                    <span className="truncate">
+                        {describeMatch(rule.match)} → {fileName(rule.payload_path)}
+                    </span>
+                    <Button variant="ghost" size="sm" onClick={() => removeRule(index)}>
+                        <X size={14} />
+                    </Button>
+                </div>
+            ))}
+
+            {chipUid && selectedPayload && (
+                <Button variant="outline" size="sm" onClick={addRuleForThisConsole}>
+                    always inject {fileName(selectedPayload)} into this console
+                </Button>
+            )}
+
+            {lastOutcome && <div className="text-muted-foreground">{lastOutcome}</div>}
+        </div>
+    );
+}
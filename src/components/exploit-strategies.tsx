@@ -0,0 +1,98 @@
+'use client';
+import { ArrowUp, ArrowDown, X, Plus } from "lucide-react";
+import { Button } from "./ui/button";
+import { useState, useEffect } from 'react';
+import { invoke } from "@tauri-apps/api/core";
+
+interface StrategyInfo {
+    id: string;
+    name: string;
+    description: string;
+    default: boolean;
+}
+
+interface StrategySettings {
+    order: string[];
+}
+
+// Which exploit strategies injections try, and in what order. The first one that
+// works wins; the rest are fallbacks.
+export function ExploitStrategies() {
+    const [strategies, setStrategies] = useState<StrategyInfo[]>([]);
+    const [settings, setSettings] = useState<StrategySettings | null>(null);
+
+    useEffect(() => {
+        invoke<StrategyInfo[]>("list_strategies")
+            .then(setStrategies)
+            .catch((error) => console.error("Failed to list exploit strategies:", error));
+        invoke<StrategySettings>("get_strategy_settings")
+            .then(setSettings)
+            .catch((error) => console.error("Failed to load strategy settings:", error));
+    }, []);
+
+    const save = async (order: string[]) => {
+        try {
+            await invoke("set_strategy_settings", { settings: { order } });
+            setSettings({ order });
+        } catch (error) {
+            alert(`Couldn't save the strategy order: ${error}`);
+        }
+    };
+
+    if (!settings || strategies.length === 0) {
+        return null;
+    }
+
+    const byId = (id: string) => strategies.find((strategy) => strategy.id === id);
+    const unused = strategies.filter((strategy) => !settings.order.includes(strategy.id));
+
+    const move = (index: number, by: number) => {
+        const order = [...settings.order];
+        [order[index], order[index + by]] = [order[index + by], order[index]];
+        save(order);
+    };
+
+    return (
+        <details className="text-sm">
+            <summary className="cursor-pointer text-muted-foreground">
+                exploit strategy: {settings.order.map((id) => byId(id)?.name ?? id).join(" → ") || "default"}
+            </summary>
+            <div className="mt-2 space-y-1">
+                {settings.order.map((id, index) => (
+                    <div key={id} className="flex items-center gap-1" title={byId(id)?.description}>
+                        <span className="flex-1 truncate">
+                            {index + 1}. {byId(id)?.name ?? id}
+                        </span>
+                        <Button variant="ghost" size="sm" disabled={index === 0} onClick={() => move(index, -1)}>
+                            <ArrowUp size={14} />
+                        </Button>
+                        <Button
+                            variant="ghost"
+                            size="sm"
+                            disabled={index === settings.order.length - 1}
+                            onClick={() => move(index, 1)}
+                        >
+                            <ArrowDown size={14} />
+                        </Button>
+                        <Button
+                            variant="ghost"
+                            size="sm"
+                            disabled={settings.order.length === 1}
+                            onClick={() => save(settings.order.filter((other) => other !== id))}
+                        >
+                            <X size={14} />
+                        </Button>
+                    </div>
+                ))}
+                {unused.map((strategy) => (
+                    <div key={strategy.id} className="flex items-center gap-1 text-muted-foreground" title={strategy.description}>
+                        <span className="flex-1 truncate">{strategy.name}</span>
+                        <Button variant="ghost" size="sm" onClick={() => save([...settings.order, strategy.id])}>
+                            <Plus size={14} />
+                        </Button>
+                    </div>
+                ))}
+            </div>
+        </details>
+    );
+}
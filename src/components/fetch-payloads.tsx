@@ -1,12 +1,10 @@
 'use client';
-import { DownloadCloud, Check } from "lucide-react";
+import { DownloadCloud, Check, WifiOff } from "lucide-react";
 import { Button } from "./ui/button";
+import { Input } from "./ui/input";
 import { useState, useEffect } from 'react';
-import { Octokit } from "octokit";
 import { invoke } from "@tauri-apps/api/core";
-import { exists } from "@tauri-apps/plugin-fs";
-import { downloadDir, join } from "@tauri-apps/api/path";
-import { Store } from "@tauri-apps/plugin-store";
+import { listen } from "@tauri-apps/api/event";
 import {
     Dialog,
     DialogContent,
@@ -22,138 +20,166 @@ import {
     CardTitle,
 } from "./ui/card";
 import { Badge } from './ui/badge';
+import { PayloadSource, SourcePicker } from './payload-sources';
+import { LibraryEntry, LibraryItem, listLibrary } from './payload-library';
 
-const octokit = new Octokit();
-let store: Store | null = null;
-
-// GitHub API types
-interface GitHubAsset {
+interface ReleaseAsset {
     id: number;
     name: string;
     browser_download_url: string;
     size: number;
 }
 
-interface GitHubRelease {
-    id: number;
+// A release of a source, narrowed down to the asset we'd download.
+interface SourceRelease {
     name: string | null;
     tag_name: string;
-    published_at: string;
-    assets: GitHubAsset[];
+    published_at: string | null;
+    prerelease: boolean;
+    asset: ReleaseAsset;
+    checksum_url?: string | null;
 }
 
-export async function getReleases(repo: { owner: string, repo: string }): Promise<GitHubRelease[]> {
-    try {
-        const releases = await octokit.rest.repos.listReleases({
-            owner: repo.owner,
-            repo: repo.repo,
-        });
-        return releases.data as GitHubRelease[];
-    } catch (error) {
-        console.error('oh that\'s a bit not allowed:', error);
-        return [];
-    }
+interface SourceListing {
+    source_id: string;
+    releases: SourceRelease[];
+    listed_from?: "fetched" | "not_modified" | "cached" | null;
+    fetched_at?: number | null;
+    rate_limit?: { limit: number; remaining: number; reset_at: number } | null;
+    // Why there's nothing to show, or why the cached listing was shown instead.
+    error?: { code: string; message: string } | null;
+}
+
+interface ReleaseSettings {
+    github_token?: string | null;
+    offline: boolean;
+}
+
+// Listed by the backend, which caches them so we don't burn GitHub's rate limit.
+export async function getReleases(sourceId: string): Promise<SourceListing> {
+    const [listing] = await invoke<SourceListing[]>("refresh_sources", { ids: [sourceId] });
+    return listing;
 }
 
-async function downloadPayload(assetUrl: string, fileName: string) {
+async function downloadPayload(release: SourceRelease, source?: PayloadSource) {
+    const fileName = release.asset.name;
     try {
-        // Use Tauri backend to download and save the file
-        const filePath: string = await invoke("download_payload", {
-            url: assetUrl,
-            filename: fileName
+        // The backend downloads it into the payload library.
+        const entry = await invoke<LibraryEntry>("download_payload", {
+            request: {
+                url: release.asset.browser_download_url,
+                filename: fileName,
+                checksum_url: release.checksum_url ?? null,
+                payload_patterns: source?.archive_member ? [source.archive_member] : null,
+                origin: {
+                    source: source?.id ?? null,
+                    tag: release.tag_name,
+                    published_at: release.published_at,
+                },
+            },
         });
-        console.log(`Downloaded ${fileName} to ${filePath}`);
-        return filePath;
+        console.log(`Downloaded ${fileName} to ${entry.path}`);
+        return entry;
     } catch (error) {
         console.error("Download failed:", error);
         throw error;
     }
 }
 
+interface DownloadProgress {
+    filename: string;
+    bytes_downloaded: number;
+    bytes_total?: number | null;
+}
+
 interface FetchPayloadsProps {
+    selectedPayload?: string;
     onSelectPayload?: (path: string) => void;
 }
 
-export function FetchPayloads({ onSelectPayload }: FetchPayloadsProps) {
+export function FetchPayloads({ selectedPayload, onSelectPayload }: FetchPayloadsProps) {
     const [open, setOpen] = useState(false);
-    const [releases, setReleases] = useState<GitHubRelease[]>([]);
+    const [sources, setSources] = useState<PayloadSource[]>([]);
+    const [sourceId, setSourceId] = useState<string | null>(null);
+    const [releases, setReleases] = useState<SourceRelease[]>([]);
     const [loading, setLoading] = useState(false);
-    const [downloading, setDownloading] = useState<number | null>(null);
-    const [downloadedFiles, setDownloadedFiles] = useState<Set<string>>(new Set());
-    const [storeLoaded, setStoreLoaded] = useState(false);
-    const [selectedPayload, setSelectedPayload] = useState<string | null>(null);
+    // The asset being downloaded.
+    const [downloading, setDownloading] = useState<string | null>(null);
+    const [library, setLibrary] = useState<LibraryItem[]>([]);
+    const [downloadProgress, setDownloadProgress] = useState<DownloadProgress | null>(null);
+    const [listingNote, setListingNote] = useState<string | null>(null);
+    const [releaseSettings, setReleaseSettings] = useState<ReleaseSettings | null>(null);
 
-    // Load downloaded files from store on mount
     useEffect(() => {
-        const loadDownloadedFiles = async () => {
-            store = await Store.load("payloads.dat");
-            try {
-                const stored = await store?.get<string[]>('downloadedFiles');
-                if (stored && Array.isArray(stored)) {
-                    setDownloadedFiles(new Set(stored));
-                }
-            } catch (error) {
-                console.log('No stored download data found, starting fresh');
-            }
-            setStoreLoaded(true);
+        const unlisten = listen<DownloadProgress>("download-progress", (event) => {
+            setDownloadProgress(event.payload);
+        });
+        return () => {
+            unlisten.then((f) => f());
         };
-        loadDownloadedFiles();
     }, []);
 
-    // Save downloaded files to store whenever they change
-    useEffect(() => {
-        if (storeLoaded && downloadedFiles.size > 0) {
-            const saveToStore = async () => {
-                try {
-                    await store?.set('downloadedFiles', Array.from(downloadedFiles));
-                    await store?.save();
-                } catch (error) {
-                    console.error('Failed to save download data:', error);
-                }
-            };
-            saveToStore();
+    const downloadLabel = (filename: string) => {
+        if (downloadProgress?.filename !== filename) {
+            return "downloading...";
         }
-    }, [downloadedFiles, storeLoaded]);
+        const { bytes_downloaded, bytes_total } = downloadProgress;
+        return bytes_total
+            ? `downloading... ${Math.floor((100 * bytes_downloaded) / bytes_total)}%`
+            : `downloading... ${Math.floor(bytes_downloaded / 1024)} KiB`;
+    };
 
-    const checkDownloadedFiles = async (releases: GitHubRelease[]) => {
-        const downloaded = new Set<string>();
-        const downloadDirPath = await downloadDir();
-        const payloadsDir = await join(downloadDirPath, "payloads");
+    // What's already been downloaded from a source, by asset.
+    const libraryEntry = (assetName: string) => library.find((item) =>
+        item.present && item.source === sourceId && item.asset_name === assetName);
 
-        for (const release of releases) {
-            if (release.assets.length > 0) {
-                const assetName = release.assets[0].name;
-                const filePath = await join(payloadsDir, assetName);
-                try {
-                    const fileExists = await exists(filePath);
-                    if (fileExists) {
-                        downloaded.add(assetName);
-                    }
-                } catch (error) {
-                    // File doesn't exist or can't be checked
-                }
-            }
+    const saveReleaseSettings = async (next: ReleaseSettings) => {
+        try {
+            await invoke("set_release_settings", { settings: next });
+            setReleaseSettings(next);
+        } catch (error) {
+            alert(`Couldn't save release settings: ${error}`);
         }
-        setDownloadedFiles(prev => new Set([...prev, ...downloaded]));
     };
 
     useEffect(() => {
         if (open) {
+            Promise.all([
+                listLibrary().then(setLibrary),
+                invoke<ReleaseSettings>("get_release_settings").then(setReleaseSettings),
+                invoke<PayloadSource[]>("list_sources").then((sources) => {
+                    setSources(sources);
+                    setSourceId((current) => current ?? sources[0]?.id ?? null);
+                }),
+            ]).catch((error) => console.error("Failed to load sources:", error));
+        }
+    }, [open]);
+
+    useEffect(() => {
+        if (open && sourceId) {
             setLoading(true);
-            async function fetchReleases() {
-                const data = await getReleases({
-                    owner: 'CTCaer',
-                    repo: 'hekate',
-                });
-                if (data) {
-                    setReleases(data);
-                    await checkDownloadedFiles(data);
+            async function fetchReleases(sourceId: string) {
+                try {
+                    const listing = await getReleases(sourceId);
+                    setReleases(listing?.releases ?? []);
+                    const fetched = listing?.fetched_at
+                        ? new Date(listing.fetched_at * 1000).toLocaleString()
+                        : null;
+                    setListingNote(listing?.error
+                        ? fetched ? `${listing.error.message} (showing releases from ${fetched})` : listing.error.message
+                        : listing?.listed_from === "cached" ? `offline: showing releases from ${fetched}` : null);
+                } catch (error) {
+                    const { message } = error as { message?: string };
+                    setReleases([]);
+                    setListingNote(message ?? String(error));
                 }
                 setLoading(false);
             }
-            fetchReleases();
+            fetchReleases(sourceId);
         }
-    }, [open]);
+    }, [open, sourceId, sources, releaseSettings?.offline]);
+
+    const source = sources.find((source) => source.id === sourceId);
 
     return (
         <Dialog open={open} onOpenChange={setOpen}>
@@ -167,7 +193,38 @@ export function FetchPayloads({ onSelectPayload }: FetchPayloadsProps) {
                     <DialogTitle>fetch payloads</DialogTitle>
                 </DialogHeader>
                 <div className="space-y-4">
-                    {loading || !storeLoaded ? (
+                    <SourcePicker
+                        sources={sources}
+                        selectedId={sourceId}
+                        onSelect={setSourceId}
+                        onChange={(next) => {
+                            setSources(next);
+                            if (!next.some((source) => source.id === sourceId)) {
+                                setSourceId(next[0]?.id ?? null);
+                            }
+                        }}
+                    />
+                    {releaseSettings && (
+                        <div className="flex items-center gap-2">
+                            <Input
+                                type="password"
+                                placeholder="GitHub token (optional)"
+                                defaultValue={releaseSettings.github_token ?? ""}
+                                onBlur={(event) => saveReleaseSettings({ ...releaseSettings, github_token: event.target.value })}
+                            />
+                            <Button
+                                variant={releaseSettings.offline ? "default" : "outline"}
+                                size="sm"
+                                onClick={() => saveReleaseSettings({ ...releaseSettings, offline: !releaseSettings.offline })}
+                            >
+                                <WifiOff size={14} className="mr-1" /> offline
+                            </Button>
+                        </div>
+                    )}
+                    {listingNote && (
+                        <div className="text-sm text-muted-foreground">{listingNote}</div>
+                    )}
+                    {loading ? (
                         <div className="text-center py-8">loading releases...</div>
                     ) : releases.length === 0 ? (
                         <div className="text-center py-8 text-muted-foreground">
@@ -176,12 +233,12 @@ export function FetchPayloads({ onSelectPayload }: FetchPayloadsProps) {
                     ) : (
                         releases.map((release, index) => {
                             const isLatest = index === 0;
-                            const assetName = release.assets[0]?.name;
-                            const isDownloaded = assetName && downloadedFiles.has(assetName);
-                            const isInUse = assetName && selectedPayload === assetName;
+                            const assetName = release.asset.name;
+                            const entry = libraryEntry(assetName);
+                            const isInUse = entry && selectedPayload === entry.path;
 
                             return (
-                                <Card key={release.id}>
+                                <Card key={`${release.tag_name}/${assetName}`}>
                                     <CardHeader>
                                         <div className="flex flex-row justify-between">
                                             <div className="flex gap-2 flex-col">
@@ -189,7 +246,7 @@ export function FetchPayloads({ onSelectPayload }: FetchPayloadsProps) {
                                                 {release.name || release.tag_name}
                                             </CardTitle>
                                             <CardDescription>
-                                            {new Date(release.published_at).toLocaleDateString()}
+                                            {release.published_at ? new Date(release.published_at).toLocaleDateString() : assetName}
                                         </CardDescription>
                                             </div>
                                             <div className="flex flex-col items-end gap-2">
@@ -203,57 +260,62 @@ export function FetchPayloads({ onSelectPayload }: FetchPayloadsProps) {
                                         </div>
                                         
                                     </CardHeader>
-                                    {release.assets.length > 0 && (
-                                        <CardFooter className="flex justify-end gap-2">
-                                            {isDownloaded ? (
-                                                <Button
-                                                    variant="default"
-                                                    size="sm"
-                                                    className="self-end"
-                                                    disabled={!!isInUse}
-                                                    onClick={async () => {
-                                                        const downloadDirPath = await downloadDir();
-                                                        const payloadsDir = await join(downloadDirPath, "payloads");
-                                                        const filePath = await join(payloadsDir, assetName);
-                                                        setSelectedPayload(assetName);
-                                                        onSelectPayload?.(filePath);
-                                                        setOpen(false);
-                                                        alert(`selected payload: ${assetName}`);
-                                                    }}
-                                                >
-                                                    {isInUse ? <>
-                                                        <Check /> in use
-                                                    </> : 'use'}
-                                                </Button>
-                                            ) : (
-                                                <Button
-                                                    variant="outline"
-                                                    size="sm"
-                                                    disabled={downloading === release.id}
-                                                    onClick={async () => {
-                                                        if (!release.assets[0]) return;
-
-                                                        setDownloading(release.id);
-                                                        try {
-                                                            const filePath = await downloadPayload(
-                                                                release.assets[0].browser_download_url,
-                                                                release.assets[0].name
-                                                            );
-                                                            setDownloadedFiles(prev => new Set(prev).add(release.assets[0].name));
-                                                            alert(`downloaded to: ${filePath}`);
-                                                        } catch (error) {
-                                                            alert(`download failed: ${error}`);
-                                                        } finally {
-                                                            setDownloading(null);
+                                    <CardFooter className="flex justify-end gap-2">
+                                        {entry ? (
+                                            <Button
+                                                variant="default"
+                                                size="sm"
+                                                className="self-end"
+                                                disabled={!!isInUse}
+                                                onClick={() => {
+                                                    onSelectPayload?.(entry.path);
+                                                    setOpen(false);
+                                                    alert(`selected payload: ${entry.description}`);
+                                                }}
+                                            >
+                                                {isInUse ? <>
+                                                    <Check /> in use
+                                                </> : 'use'}
+                                            </Button>
+                                        ) : (
+                                            <Button
+                                                variant="outline"
+                                                size="sm"
+                                                disabled={downloading === assetName}
+                                                onClick={async () => {
+                                                    setDownloading(assetName);
+                                                    try {
+                                                        const downloaded = await downloadPayload(release, source);
+                                                        setLibrary(prev => [
+                                                            ...prev.filter((item) => item.sha256 !== downloaded.sha256),
+                                                            { ...downloaded, present: true },
+                                                        ]);
+                                                        alert(`downloaded to: ${downloaded.path}`);
+                                                    } catch (error) {
+                                                        const { code, message } = error as { code?: string; message?: string };
+                                                        if (code !== "Cancelled") {
+                                                            alert(`download failed: ${message ?? error}`);
                                                         }
-                                                    }}
-                                                >
-                                                    <DownloadCloud className="w-4 h-4 mr-2" />
-                                                    {downloading === release.id ? "downloading..." : "download"}
-                                                </Button>
-                                            )}
-                                        </CardFooter>
-                                    )}
+                                                    } finally {
+                                                        setDownloading(null);
+                                                        setDownloadProgress(null);
+                                                    }
+                                                }}
+                                            >
+                                                <DownloadCloud className="w-4 h-4 mr-2" />
+                                                {downloading === assetName ? downloadLabel(assetName) : "download"}
+                                            </Button>
+                                        )}
+                                        {downloading === assetName && (
+                                            <Button
+                                                variant="ghost"
+                                                size="sm"
+                                                onClick={() => invoke("cancel_download", { filename: assetName })}
+                                            >
+                                                cancel
+                                            </Button>
+                                        )}
+                                    </CardFooter>
                                 </Card>
                             );
                         })
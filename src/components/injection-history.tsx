@@ -0,0 +1,160 @@
+'use client';
+import { History, Download, Trash2 } from "lucide-react";
+import { Button } from "./ui/button";
+import { Badge } from "./ui/badge";
+import { useState, useEffect } from 'react';
+import { invoke } from "@tauri-apps/api/core";
+import { save as saveDialog } from "@tauri-apps/plugin-dialog";
+import {
+    Dialog,
+    DialogContent,
+    DialogHeader,
+    DialogTitle,
+    DialogTrigger,
+} from "./ui/dialog";
+
+type Outcome = "succeeded" | "failed" | "cancelled";
+
+// One injection attempt, as the backend recorded it.
+interface HistoryEntry {
+    timestamp: number;
+    payload_path: string;
+    payload_sha256?: string | null;
+    chip_uid?: string | null;
+    port_path?: string | null;
+    strategy?: string | null;
+    outcome: Outcome;
+    verification?: "verified" | "unverified" | "failed" | null;
+    error_code?: string | null;
+    error_message?: string | null;
+    duration_ms: number;
+    timings?: { upload_ms: number; total_ms: number } | null;
+}
+
+interface HistoryFilter {
+    outcome?: Outcome;
+    chip_uid?: string;
+    limit?: number;
+}
+
+const fileName = (path: string) => path.split(/[\\/]/).pop() ?? path;
+
+const outcomeVariant = (outcome: Outcome) =>
+    outcome === "succeeded" ? "default" : outcome === "failed" ? "destructive" : "secondary";
+
+export function InjectionHistory() {
+    const [open, setOpen] = useState(false);
+    const [entries, setEntries] = useState<HistoryEntry[]>([]);
+    const [filter, setFilter] = useState<HistoryFilter>({ limit: 200 });
+
+    const refresh = () => invoke<HistoryEntry[]>("query_history", { filter })
+        .then(setEntries)
+        .catch((error) => console.error("Failed to read the injection history:", error));
+
+    useEffect(() => {
+        if (open) {
+            refresh();
+        }
+    }, [open, filter]);
+
+    const exportAs = async (format: "csv" | "json") => {
+        const path = await saveDialog({
+            defaultPath: `jolt-history.${format}`,
+            filters: [{ name: format.toUpperCase(), extensions: [format] }],
+        });
+        if (!path) {
+            return;
+        }
+        try {
+            // Everything that matches, not just what's on screen.
+            const count = await invoke<number>("export_history", {
+                path,
+                format,
+                filter: { ...filter, limit: null },
+            });
+            alert(`exported ${count} entries`);
+        } catch (error) {
+            alert(`Couldn't export the history: ${error}`);
+        }
+    };
+
+    const clear = async () => {
+        if (confirm("clear the whole injection history?")) {
+            await invoke("clear_history").catch((error) => alert(String(error)));
+            refresh();
+        }
+    };
+
+    return (
+        <Dialog open={open} onOpenChange={setOpen}>
+            <DialogTrigger asChild>
+                <Button variant="ghost" size="icon">
+                    <History />
+                </Button>
+            </DialogTrigger>
+            <DialogContent className="max-w-2xl max-h-[80vh] overflow-y-auto">
+                <DialogHeader>
+                    <DialogTitle>injection history</DialogTitle>
+                </DialogHeader>
+                <div className="flex flex-wrap gap-2">
+                    {([undefined, "succeeded", "failed", "cancelled"] as const).map((outcome) => (
+                        <Button
+                            key={outcome ?? "all"}
+                            variant={filter.outcome === outcome ? "default" : "outline"}
+                            size="sm"
+                            onClick={() => setFilter({ ...filter, outcome })}
+                        >
+                            {outcome ?? "all"}
+                        </Button>
+                    ))}
+                    {filter.chip_uid && (
+                        <Button variant="outline" size="sm" onClick={() => setFilter({ ...filter, chip_uid: undefined })}>
+                            console {filter.chip_uid.slice(0, 8)}… ✕
+                        </Button>
+                    )}
+                    <div className="flex-1" />
+                    <Button variant="outline" size="sm" onClick={() => exportAs("csv")}>
+                        <Download size={14} className="mr-1" /> csv
+                    </Button>
+                    <Button variant="outline" size="sm" onClick={() => exportAs("json")}>
+                        <Download size={14} className="mr-1" /> json
+                    </Button>
+                    <Button variant="ghost" size="sm" onClick={clear}>
+                        <Trash2 size={14} />
+                    </Button>
+                </div>
+                <div className="space-y-2">
+                    {entries.length === 0 && (
+                        <div className="text-center py-8 text-muted-foreground">nothing injected yet</div>
+                    )}
+                    {entries.map((entry, index) => (
+                        <div key={`${entry.timestamp}-${index}`} className="border rounded-md p-2 text-sm">
+                            <div className="flex items-center gap-2">
+                                <Badge variant={outcomeVariant(entry.outcome)}>{entry.outcome}</Badge>
+                                <span className="truncate font-medium">{fileName(entry.payload_path)}</span>
+                                <span className="ml-auto text-muted-foreground">
+                                    {new Date(entry.timestamp * 1000).toLocaleString()}
+                                </span>
+                            </div>
+                            <div className="truncate text-muted-foreground">
+                                {entry.chip_uid ? (
+                                    <button className="underline" onClick={() => setFilter({ ...filter, chip_uid: entry.chip_uid! })}>
+                                        console {entry.chip_uid.slice(0, 8)}…
+                                    </button>
+                                ) : "unknown console"}
+                                {entry.port_path && ` · port ${entry.port_path}`}
+                                {entry.strategy && ` · ${entry.strategy}`}
+                                {entry.verification && ` · ${entry.verification}`}
+                                {` · ${Math.round(entry.duration_ms)} ms`}
+                                {entry.timings && ` (upload ${Math.round(entry.timings.upload_ms)} ms)`}
+                            </div>
+                            {entry.error_message && (
+                                <div className="text-red-500">{entry.error_code}: {entry.error_message}</div>
+                            )}
+                        </div>
+                    ))}
+                </div>
+            </DialogContent>
+        </Dialog>
+    );
+}
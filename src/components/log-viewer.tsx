@@ -0,0 +1,85 @@
+'use client';
+import { ScrollText, Copy } from "lucide-react";
+import { Button } from "./ui/button";
+import { useState, useEffect, useRef } from 'react';
+import { invoke } from "@tauri-apps/api/core";
+import {
+    Dialog,
+    DialogContent,
+    DialogHeader,
+    DialogTitle,
+    DialogTrigger,
+} from "./ui/dialog";
+
+interface LogLine {
+    seq: number;
+    text: string;
+}
+
+// How many lines to keep on screen; the backend keeps about as many.
+const MAX_LINES = 2000;
+
+export function LogViewer() {
+    const [open, setOpen] = useState(false);
+    const [lines, setLines] = useState<LogLine[]>([]);
+    const [logDir, setLogDir] = useState<string | null>(null);
+    const bottom = useRef<HTMLDivElement>(null);
+
+    // Poll for whatever's been logged since the last line we have.
+    useEffect(() => {
+        if (!open) {
+            return;
+        }
+        invoke<{ log_dir: string }>("get_storage_info")
+            .then((info) => setLogDir(info.log_dir))
+            .catch(() => setLogDir(null));
+
+        let last: number | null = null;
+        setLines([]);
+        const poll = async () => {
+            try {
+                const newer = await invoke<LogLine[]>("recent_logs", { after: last });
+                if (newer.length > 0) {
+                    last = newer[newer.length - 1].seq;
+                    setLines((lines) => [...lines, ...newer].slice(-MAX_LINES));
+                }
+            } catch (error) {
+                console.error("Failed to read the log:", error);
+            }
+        };
+        poll();
+        const timer = setInterval(poll, 1000);
+        return () => clearInterval(timer);
+    }, [open]);
+
+    useEffect(() => {
+        bottom.current?.scrollIntoView();
+    }, [lines]);
+
+    const copy = () => navigator.clipboard.writeText(lines.map((line) => line.text).join("\n"));
+
+    return (
+        <Dialog open={open} onOpenChange={setOpen}>
+            <DialogTrigger asChild>
+                <Button variant="ghost" size="icon">
+                    <ScrollText />
+                </Button>
+            </DialogTrigger>
+            <DialogContent className="max-w-3xl">
+                <DialogHeader>
+                    <DialogTitle>log</DialogTitle>
+                </DialogHeader>
+                <div className="flex items-center gap-2 text-xs text-muted-foreground">
+                    <span className="truncate flex-1">{logDir && `full logs are in ${logDir}`}</span>
+                    <Button variant="outline" size="sm" onClick={copy}>
+                        <Copy size={14} className="mr-1" /> copy
+                    </Button>
+                </div>
+                <pre className="h-[60vh] overflow-auto rounded-md bg-muted p-2 text-xs whitespace-pre-wrap">
+                    {lines.map((line) => line.text).join("\n")}
+                    <div ref={bottom} />
+                </pre>
+            </DialogContent>
+        </Dialog>
+    );
+}
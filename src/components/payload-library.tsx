@@ -0,0 +1,202 @@
+'use client';
+import { Library, Pin, PinOff, Trash2, FilePlus, Scissors, FolderInput } from "lucide-react";
+import { Button } from "./ui/button";
+import { Badge } from "./ui/badge";
+import { useState, useEffect } from 'react';
+import { invoke } from "@tauri-apps/api/core";
+import { open as openDialog } from "@tauri-apps/plugin-dialog";
+import {
+    Dialog,
+    DialogContent,
+    DialogHeader,
+    DialogTitle,
+    DialogTrigger,
+} from "./ui/dialog";
+
+// A payload in the backend's library. Everything about where it came from is
+// missing for imported files.
+export interface LibraryEntry {
+    sha256: string;
+    path: string;
+    source?: string | null;
+    asset_name?: string | null;
+    tag?: string | null;
+    published_at?: string | null;
+    size: number;
+    description: string;
+    added_at: number;
+    last_injected_at?: number | null;
+    pinned: boolean;
+}
+
+// An entry as the library listing shows it.
+export interface LibraryItem extends LibraryEntry {
+    present: boolean;
+}
+
+export const listLibrary = () => invoke<LibraryItem[]>("list_library");
+
+// Where the backend keeps things. Portable mode keeps it all next to jolt.
+interface StorageInfo {
+    portable: boolean;
+    data_dir: string;
+    cache_dir: string;
+    log_dir: string;
+    library_dir: string;
+    default_library_dir: string;
+}
+
+const fileName = (path: string) => path.split(/[\\/]/).pop() ?? path;
+
+const formatDate = (seconds: number) => new Date(seconds * 1000).toLocaleDateString();
+
+interface PayloadLibraryProps {
+    selectedPayload?: string;
+    onSelectPayload?: (path: string) => void;
+}
+
+export function PayloadLibrary({ selectedPayload, onSelectPayload }: PayloadLibraryProps) {
+    const [open, setOpen] = useState(false);
+    const [items, setItems] = useState<LibraryItem[]>([]);
+    const [storage, setStorage] = useState<StorageInfo | null>(null);
+
+    const refresh = () => {
+        listLibrary()
+            .then(setItems)
+            .catch((error) => console.error("Failed to list the library:", error));
+        invoke<StorageInfo>("get_storage_info")
+            .then(setStorage)
+            .catch((error) => console.error("Failed to get the storage location:", error));
+    };
+
+    useEffect(() => {
+        if (open) {
+            refresh();
+        }
+    }, [open]);
+
+    const run = async (command: string, args: Record<string, unknown>) => {
+        try {
+            return await invoke(command, args);
+        } catch (error) {
+            const { message } = error as { message?: string };
+            alert(message ?? String(error));
+        } finally {
+            refresh();
+        }
+    };
+
+    const importFiles = async () => {
+        const paths = await openDialog({
+            multiple: true,
+            filters: [{ name: "Payload files", extensions: ["bin", "payload"] }],
+        });
+        if (paths && paths.length > 0) {
+            await run("import_payloads", { paths });
+        }
+    };
+
+    const prune = async () => {
+        const removed = await run("prune_library", { policy: null }) as LibraryEntry[] | undefined;
+        if (removed) {
+            alert(removed.length > 0
+                ? `removed ${removed.map((item) => fileName(item.path)).join(", ")}`
+                : "nothing to prune");
+        }
+    };
+
+    const moveLibrary = async () => {
+        const path = await openDialog({ directory: true });
+        if (typeof path === "string") {
+            await run("set_library_dir", { path });
+        }
+    };
+
+    // Newest first.
+    const sorted = [...items].sort((a, b) => b.added_at - a.added_at);
+
+    return (
+        <Dialog open={open} onOpenChange={setOpen}>
+            <DialogTrigger asChild>
+                <Button variant="outline">
+                    <Library /> library
+                </Button>
+            </DialogTrigger>
+            <DialogContent className="max-w-2xl max-h-[80vh] overflow-y-auto">
+                <DialogHeader>
+                    <DialogTitle>payload library</DialogTitle>
+                </DialogHeader>
+                <div className="flex gap-2">
+                    <Button variant="outline" size="sm" onClick={importFiles}>
+                        <FilePlus size={14} className="mr-1" /> import
+                    </Button>
+                    <Button variant="outline" size="sm" onClick={prune}>
+                        <Scissors size={14} className="mr-1" /> prune old versions
+                    </Button>
+                    <Button variant="outline" size="sm" onClick={moveLibrary}>
+                        <FolderInput size={14} className="mr-1" /> move
+                    </Button>
+                </div>
+                {storage && (
+                    <div className="text-xs text-muted-foreground truncate">
+                        {storage.portable && <Badge variant="secondary" className="mr-2">portable</Badge>}
+                        stored in {storage.library_dir}
+                        {storage.library_dir !== storage.default_library_dir && (
+                            <button
+                                className="ml-2 underline"
+                                onClick={() => run("set_library_dir", { path: null })}
+                            >
+                                move back to default
+                            </button>
+                        )}
+                    </div>
+                )}
+                <div className="space-y-2">
+                    {sorted.length === 0 && (
+                        <div className="text-center py-8 text-muted-foreground">no payloads yet</div>
+                    )}
+                    {sorted.map((item) => (
+                        <div key={item.sha256} className="flex items-center gap-2 border rounded-md p-2 text-sm">
+                            <div className="flex-1 min-w-0">
+                                <div className="truncate font-medium">
+                                    {item.description}
+                                    {item.tag && <Badge variant="secondary" className="ml-2">{item.tag}</Badge>}
+                                    {!item.present && <Badge variant="destructive" className="ml-2">missing</Badge>}
+                                </div>
+                                <div className="truncate text-muted-foreground">
+                                    {fileName(item.path)} · {item.source ?? "imported"} · added {formatDate(item.added_at)}
+                                    {item.last_injected_at && ` · last injected ${formatDate(item.last_injected_at)}`}
+                                </div>
+                            </div>
+                            <Button
+                                variant="default"
+                                size="sm"
+                                disabled={!item.present || selectedPayload === item.path}
+                                onClick={() => {
+                                    onSelectPayload?.(item.path);
+                                    setOpen(false);
+                                }}
+                            >
+                                {selectedPayload === item.path ? "in use" : "use"}
+                            </Button>
+                            <Button
+                                variant="ghost"
+                                size="sm"
+                                onClick={() => run("pin_payload", { sha256: item.sha256, pinned: !item.pinned })}
+                            >
+                                {item.pinned ? <PinOff size={14} /> : <Pin size={14} />}
+                            </Button>
+                            <Button
+                                variant="ghost"
+                                size="sm"
+                                onClick={() => confirm(`delete ${fileName(item.path)}?`) && run("delete_payload", { sha256: item.sha256 })}
+                            >
+                                <Trash2 size={14} />
+                            </Button>
+                        </div>
+                    ))}
+                </div>
+            </DialogContent>
+        </Dialog>
+    );
+}
@@ -0,0 +1,113 @@
+'use client';
+import { Plus, RotateCcw, X } from "lucide-react";
+import { Button } from "./ui/button";
+import { Input } from "./ui/input";
+import { useState } from 'react';
+import { invoke } from "@tauri-apps/api/core";
+
+export type SourceLocation =
+    | { kind: "repo"; owner: string; repo: string; api_base?: string | null }
+    | { kind: "url"; url: string };
+
+export interface PayloadSource {
+    id: string;
+    name: string;
+    location: SourceLocation;
+    asset_pattern: string;
+    archive_member?: string | null;
+    checksum_asset?: string | null;
+}
+
+interface SourcePickerProps {
+    sources: PayloadSource[];
+    selectedId: string | null;
+    onSelect: (id: string) => void;
+    onChange: (sources: PayloadSource[]) => void;
+}
+
+// "owner/repo" for GitHub, anything with :// for a direct link.
+const parseLocation = (where: string): SourceLocation | null => {
+    if (where.includes("://")) {
+        return { kind: "url", url: where };
+    }
+    const [owner, repo, ...rest] = where.split("/");
+    return owner && repo && rest.length === 0 ? { kind: "repo", owner, repo } : null;
+};
+
+export function SourcePicker({ sources, selectedId, onSelect, onChange }: SourcePickerProps) {
+    const [adding, setAdding] = useState(false);
+    const [where, setWhere] = useState("");
+    const [assetPattern, setAssetPattern] = useState("*.bin");
+    const [archiveMember, setArchiveMember] = useState("");
+
+    const update = async (command: string, args: Record<string, unknown>) => {
+        try {
+            onChange(await invoke<PayloadSource[]>(command, args));
+        } catch (error) {
+            alert(`Couldn't update sources: ${error}`);
+        }
+    };
+
+    const add = async () => {
+        const location = parseLocation(where.trim());
+        if (!location) {
+            alert("enter owner/repo or a URL");
+            return;
+        }
+        const name = location.kind === "repo" ? location.repo : where.trim().split("/").pop() ?? where;
+        const id = name.toLowerCase().replace(/[^a-z0-9]+/g, "-");
+        await update("add_source", {
+            source: {
+                id,
+                name,
+                location,
+                asset_pattern: assetPattern.trim(),
+                archive_member: archiveMember.trim() || null,
+                checksum_asset: null,
+            },
+        });
+        setAdding(false);
+        setWhere("");
+        onSelect(id);
+    };
+
+    return (
+        <div className="space-y-2">
+            <div className="flex flex-wrap items-center gap-2">
+                {sources.map((source) => (
+                    <div key={source.id} className="flex items-center">
+                        <Button
+                            variant={source.id === selectedId ? "default" : "outline"}
+                            size="sm"
+                            onClick={() => onSelect(source.id)}
+                        >
+                            {source.name}
+                        </Button>
+                        <Button
+                            variant="ghost"
+                            size="sm"
+                            onClick={() => update("remove_source", { id: source.id })}
+                        >
+                            <X size={14} />
+                        </Button>
+                    </div>
+                ))}
+                <Button variant="ghost" size="sm" onClick={() => setAdding(!adding)}>
+                    <Plus size={14} className="mr-1" /> source
+                </Button>
+                <Button variant="ghost" size="sm" onClick={() => update("reset_sources", {})}>
+                    <RotateCcw size={14} />
+                </Button>
+            </div>
+
+            {adding && (
+                <div className="flex items-center gap-2">
+                    <Input placeholder="owner/repo or URL" value={where} onChange={(e) => setWhere(e.target.value)} />
+                    <Input placeholder="asset, e.g. *.bin" value={assetPattern} onChange={(e) => setAssetPattern(e.target.value)} />
+                    <Input placeholder="file in archive (optional)" value={archiveMember} onChange={(e) => setArchiveMember(e.target.value)} />
+                    <Button size="sm" onClick={add}>add</Button>
+                </div>
+            )}
+        </div>
+    );
+}
@@ -0,0 +1,54 @@
+'use client';
+import { Input } from "./ui/input";
+import { useState, useEffect } from 'react';
+import { invoke } from "@tauri-apps/api/core";
+
+interface VerificationSettings {
+    window_secs: number;
+}
+
+// How long to watch the bus after an injection to check the payload took. Zero
+// turns the check off.
+export function VerificationWindow() {
+    const [settings, setSettings] = useState<VerificationSettings | null>(null);
+
+    useEffect(() => {
+        invoke<VerificationSettings>("get_verification_settings")
+            .then(setSettings)
+            .catch((error) => console.error("Failed to load verification settings:", error));
+    }, []);
+
+    const save = async (windowSecs: number) => {
+        const next = { window_secs: windowSecs };
+        try {
+            await invoke("set_verification_settings", { settings: next });
+            setSettings(next);
+        } catch (error) {
+            alert(`Couldn't save verification settings: ${error}`);
+        }
+    };
+
+    if (!settings) {
+        return null;
+    }
+
+    return (
+        <div className="flex items-center gap-2 text-sm text-muted-foreground">
+            <span>check the payload is running for</span>
+            <Input
+                type="number"
+                min={0}
+                max={60}
+                className="h-8 w-16"
+                value={settings.window_secs}
+                onChange={(event) => {
+                    const secs = Number.parseInt(event.target.value, 10);
+                    if (Number.isFinite(secs) && secs >= 0) {
+                        save(secs);
+                    }
+                }}
+            />
+            <span>{settings.window_secs === 0 ? "seconds (off)" : "seconds"}</span>
+        </div>
+    );
+}